@@ -0,0 +1,248 @@
+// Example buffer provider that serves `local://<path>` buffers through `cat` and `sh`
+// the same way a remote or archive provider would through its own processes.
+// It needs a unix shell, so run it with `cargo run --example local_provider`.
+
+use std::{
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use pepper::{
+    buffer::BufferHandle,
+    editor::EditorContext,
+    editor_utils::MessageKind,
+    platform::{PlatformProcessHandle, PlatformRequest, PooledBuf, ProcessTag},
+    plugin::{Plugin, PluginDefinition, PluginHandle},
+};
+
+static LOCAL_PROVIDER_SCHEME: &str = "local://";
+
+// the write command only prints this after the whole content was written
+static WRITE_SUCCESS_OUTPUT: &[u8] = b"ok";
+
+enum LocalProcess {
+    Free,
+    Read {
+        buffer_handle: BufferHandle,
+        path: PathBuf,
+        output: Vec<u8>,
+    },
+    Write {
+        buffer_handle: BufferHandle,
+        path: PathBuf,
+        input: Option<PooledBuf>,
+        output: Vec<u8>,
+    },
+}
+
+#[derive(Default)]
+struct LocalProviderPlugin {
+    processes: Vec<LocalProcess>,
+}
+impl LocalProviderPlugin {
+    fn spawn(
+        plugin_handle: PluginHandle,
+        ctx: &mut EditorContext,
+        mut command: Command,
+        process: LocalProcess,
+    ) {
+        let plugin = ctx.plugins.get_as::<Self>(plugin_handle);
+        let id = match plugin
+            .processes
+            .iter()
+            .position(|p| matches!(p, LocalProcess::Free))
+        {
+            Some(id) => {
+                plugin.processes[id] = process;
+                id
+            }
+            None => {
+                plugin.processes.push(process);
+                plugin.processes.len() - 1
+            }
+        };
+
+        command.stderr(Stdio::null());
+        ctx.platform
+            .requests
+            .enqueue(PlatformRequest::SpawnProcess {
+                tag: ProcessTag::Plugin {
+                    plugin_handle,
+                    id: id as _,
+                },
+                command,
+                buf_len: 4 * 1024,
+            });
+    }
+}
+
+static LOCAL_PROVIDER_PLUGIN: PluginDefinition = PluginDefinition {
+    instantiate: |handle, ctx| {
+        ctx.editor
+            .buffers
+            .add_provider(handle, LOCAL_PROVIDER_SCHEME)
+            .ok()?;
+
+        Some(Plugin {
+            data: Box::new(LocalProviderPlugin::default()),
+
+            on_process_spawned,
+            on_process_output,
+            on_process_exit,
+
+            on_buffer_read: |plugin_handle, ctx, buffer_handle| {
+                let buffer_path = &ctx.editor.buffers.get(buffer_handle).path;
+                let path = match buffer_path
+                    .to_str()
+                    .and_then(|p| p.strip_prefix(LOCAL_PROVIDER_SCHEME))
+                {
+                    Some(path) => PathBuf::from(path),
+                    None => return,
+                };
+
+                let mut command = Command::new("cat");
+                command
+                    .arg(&path)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::piped());
+                let process = LocalProcess::Read {
+                    buffer_handle,
+                    path: buffer_path.clone(),
+                    output: Vec::new(),
+                };
+                LocalProviderPlugin::spawn(plugin_handle, ctx, command, process);
+            },
+            on_buffer_write: |plugin_handle, ctx, buffer_handle| {
+                let buffer = ctx.editor.buffers.get_mut(buffer_handle);
+                let path = match buffer
+                    .path
+                    .to_str()
+                    .and_then(|p| p.strip_prefix(LOCAL_PROVIDER_SCHEME))
+                {
+                    Some(path) => PathBuf::from(path),
+                    None => {
+                        buffer.finish_provider_write(false, &mut ctx.editor.events);
+                        return;
+                    }
+                };
+
+                let mut buf = ctx.platform.buf_pool.acquire();
+                let encoding = buffer.properties.encoding;
+                if buffer
+                    .content()
+                    .write_encoded(buf.write(), encoding)
+                    .is_err()
+                {
+                    ctx.platform.buf_pool.release(buf);
+                    buffer.finish_provider_write(false, &mut ctx.editor.events);
+                    return;
+                }
+
+                let mut command = Command::new("sh");
+                command
+                    .arg("-c")
+                    .arg("cat > \"$0\" && printf ok")
+                    .arg(&path)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped());
+                let process = LocalProcess::Write {
+                    buffer_handle,
+                    path: buffer.path.clone(),
+                    input: Some(buf),
+                    output: Vec::new(),
+                };
+                LocalProviderPlugin::spawn(plugin_handle, ctx, command, process);
+            },
+
+            ..Default::default()
+        })
+    },
+    help_pages: &[],
+};
+
+fn on_process_spawned(
+    plugin_handle: PluginHandle,
+    ctx: &mut EditorContext,
+    id: u32,
+    process_handle: PlatformProcessHandle,
+) {
+    let plugin = ctx.plugins.get_as::<LocalProviderPlugin>(plugin_handle);
+    if let LocalProcess::Write { input, .. } = &mut plugin.processes[id as usize] {
+        if let Some(buf) = input.take() {
+            ctx.platform
+                .requests
+                .enqueue(PlatformRequest::WriteToProcess {
+                    handle: process_handle,
+                    buf,
+                });
+            ctx.platform
+                .requests
+                .enqueue(PlatformRequest::CloseProcessInput {
+                    handle: process_handle,
+                });
+        }
+    }
+}
+
+fn on_process_output(plugin_handle: PluginHandle, ctx: &mut EditorContext, id: u32, bytes: &[u8]) {
+    let plugin = ctx.plugins.get_as::<LocalProviderPlugin>(plugin_handle);
+    match &mut plugin.processes[id as usize] {
+        LocalProcess::Read { output, .. } | LocalProcess::Write { output, .. } => {
+            output.extend_from_slice(bytes)
+        }
+        LocalProcess::Free => (),
+    }
+}
+
+fn on_process_exit(plugin_handle: PluginHandle, ctx: &mut EditorContext, id: u32) {
+    let plugin = ctx.plugins.get_as::<LocalProviderPlugin>(plugin_handle);
+    let process = std::mem::replace(&mut plugin.processes[id as usize], LocalProcess::Free);
+    match process {
+        LocalProcess::Free => (),
+        LocalProcess::Read {
+            buffer_handle,
+            path,
+            output,
+        } => {
+            let buffer = match ctx.editor.buffers.try_get_mut(buffer_handle) {
+                Some(buffer) if buffer.path == path => buffer,
+                _ => return,
+            };
+            if let Err(error) = buffer.provide_content(
+                &mut ctx.editor.word_database,
+                &mut &output[..],
+                &mut ctx.editor.events,
+            ) {
+                ctx.editor
+                    .status_bar
+                    .write(MessageKind::Error)
+                    .fmt(format_args!("{}", error));
+            }
+        }
+        LocalProcess::Write {
+            buffer_handle,
+            path,
+            output,
+            ..
+        } => {
+            let buffer = match ctx.editor.buffers.try_get_mut(buffer_handle) {
+                Some(buffer) if buffer.path == path => buffer,
+                _ => return,
+            };
+            let written = output == WRITE_SUCCESS_OUTPUT;
+            buffer.finish_provider_write(written, &mut ctx.editor.events);
+            if !written {
+                ctx.editor
+                    .status_bar
+                    .write(MessageKind::Error)
+                    .fmt(format_args!("could not write buffer to {:?}", &path));
+            }
+        }
+    }
+}
+
+fn main() {
+    let mut config = pepper::application::ApplicationConfig::default();
+    config.plugin_definitions.push(LOCAL_PROVIDER_PLUGIN);
+    pepper::run(config);
+}
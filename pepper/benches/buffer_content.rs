@@ -0,0 +1,281 @@
+use std::{
+    io::BufRead,
+    time::{Duration, Instant},
+};
+
+use pepper::{
+    buffer::{BufferContent, DisplayLen, LineEnding},
+    buffer_position::{BufferPosition, BufferRange},
+};
+
+const LINE_COUNT: usize = 1_000_000;
+const BLOCK_LINE_COUNT: usize = 100_000;
+const SINGLE_EDIT_COUNT: usize = 10_000;
+const RUN_COUNT: usize = 3;
+const NAVIGATION_PASS_COUNT: usize = 3;
+
+// baseline that keeps the same line data as `BufferContent` in a single vec, like it used to
+struct VecLine {
+    text: String,
+    display_len: DisplayLen,
+    ending: LineEnding,
+}
+impl VecLine {
+    fn new(text: String) -> Self {
+        let display_len = DisplayLen::from(&text[..]);
+        Self {
+            text,
+            display_len,
+            ending: LineEnding::Lf,
+        }
+    }
+
+    fn update_display_len(&mut self) {
+        self.display_len = DisplayLen::from(&self.text[..]);
+    }
+}
+
+struct VecLines {
+    lines: Vec<VecLine>,
+}
+impl VecLines {
+    fn read<R>(&mut self, read: &mut R)
+    where
+        R: BufRead,
+    {
+        self.lines.clear();
+        loop {
+            let mut text = String::new();
+            match read.read_line(&mut text) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if text.ends_with('\n') {
+                        text.pop();
+                    }
+                    let ending = if text.ends_with('\r') {
+                        text.pop();
+                        LineEnding::CrLf
+                    } else {
+                        LineEnding::Lf
+                    };
+                    let mut line = VecLine::new(text);
+                    line.ending = ending;
+                    self.lines.push(line);
+                }
+            }
+        }
+    }
+
+    fn insert_text(&mut self, position: BufferPosition, text: &str) -> BufferRange {
+        let line_index = position.line_index as usize;
+        let column = position.column_byte_index as usize;
+        let line = &mut self.lines[line_index].text;
+        let after = line.split_off(column);
+
+        let mut new_lines = text.split('\n');
+        if let Some(first) = new_lines.next() {
+            line.push_str(first);
+        }
+        let new_lines: Vec<_> = new_lines.map(|l| VecLine::new(l.into())).collect();
+        let last_index = line_index + new_lines.len();
+        self.lines.splice(line_index + 1..line_index + 1, new_lines);
+
+        let line = &mut self.lines[last_index];
+        let end = BufferPosition::line_col(last_index as _, line.text.len() as _);
+        line.text.push_str(&after);
+        line.update_display_len();
+        self.lines[line_index].update_display_len();
+        BufferRange::between(position, end)
+    }
+
+    fn delete_range(&mut self, range: BufferRange) {
+        let from_index = range.from.line_index as usize;
+        let to_index = range.to.line_index as usize;
+        let after = self.lines[to_index].text[range.to.column_byte_index as usize..].to_string();
+        self.lines.drain(from_index + 1..=to_index);
+        let line = &mut self.lines[from_index];
+        line.text.truncate(range.from.column_byte_index as usize);
+        line.text.push_str(&after);
+        line.update_display_len();
+    }
+}
+
+static MEASUREMENTS: &[&str] = &[
+    "read",
+    "insert block",
+    "delete block",
+    "insert single lines",
+    "delete single lines",
+    "iterate lines",
+    "random access",
+];
+
+fn measure<F>(f: F) -> Duration
+where
+    F: FnOnce(),
+{
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+// the vec edits just moved every line while the buffer ones barely touched them,
+// so only warm passes tell how fast navigating the lines is
+fn measure_navigation<F>(mut f: F) -> Duration
+where
+    F: FnMut(),
+{
+    (0..NAVIGATION_PASS_COUNT)
+        .map(|_| measure(&mut f))
+        .min()
+        .unwrap_or_default()
+}
+
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn generate_text(line_count: usize) -> String {
+    let mut text = String::new();
+    for i in 0..line_count {
+        text.push_str("line ");
+        text.push_str(&i.to_string());
+        text.push_str(" with some words in it\n");
+    }
+    text
+}
+
+fn run_content(file_text: &str, block_text: &str) -> Vec<Duration> {
+    let mut timings = Vec::new();
+    let mut content = BufferContent::new();
+    timings.push(measure(|| {
+        content.read(&mut file_text.as_bytes()).unwrap();
+    }));
+
+    let middle = BufferPosition::line_col((LINE_COUNT / 2) as _, 0);
+    let mut block_range = BufferRange::between(middle, middle);
+    timings.push(measure(|| {
+        block_range = content.insert_text(middle, block_text);
+    }));
+    assert_eq!(LINE_COUNT + BLOCK_LINE_COUNT, content.lines().len());
+
+    timings.push(measure(|| {
+        content.delete_range(block_range);
+    }));
+    assert_eq!(LINE_COUNT, content.lines().len());
+
+    timings.push(measure(|| {
+        for i in 0..SINGLE_EDIT_COUNT {
+            let position = BufferPosition::line_col((i * 97 % LINE_COUNT) as _, 0);
+            content.insert_text(position, "new line\n");
+        }
+    }));
+
+    timings.push(measure(|| {
+        for i in 0..SINGLE_EDIT_COUNT {
+            let from = BufferPosition::line_col((i * 89 % LINE_COUNT) as _, 0);
+            let to = BufferPosition::line_col(from.line_index + 1, 0);
+            content.delete_range(BufferRange::between(from, to));
+        }
+    }));
+    assert_eq!(LINE_COUNT, content.lines().len());
+
+    timings.push(measure_navigation(|| {
+        let len: usize = content.lines().iter().map(|l| l.as_str().len()).sum();
+        assert!(len > 0);
+    }));
+
+    timings.push(measure_navigation(|| {
+        let mut len = 0;
+        for i in 0..LINE_COUNT {
+            len += content.lines()[i * 31 % LINE_COUNT].as_str().len();
+        }
+        assert!(len > 0);
+    }));
+
+    timings
+}
+
+fn run_vec(file_text: &str, block_text: &str) -> Vec<Duration> {
+    let mut timings = Vec::new();
+    let mut lines = VecLines { lines: Vec::new() };
+    timings.push(measure(|| lines.read(&mut file_text.as_bytes())));
+
+    let middle = BufferPosition::line_col((LINE_COUNT / 2) as _, 0);
+    let mut block_range = BufferRange::between(middle, middle);
+    timings.push(measure(|| {
+        block_range = lines.insert_text(middle, block_text);
+    }));
+    assert_eq!(LINE_COUNT + BLOCK_LINE_COUNT, lines.lines.len());
+
+    timings.push(measure(|| lines.delete_range(block_range)));
+    assert_eq!(LINE_COUNT, lines.lines.len());
+
+    timings.push(measure(|| {
+        for i in 0..SINGLE_EDIT_COUNT {
+            let position = BufferPosition::line_col((i * 97 % LINE_COUNT) as _, 0);
+            lines.insert_text(position, "new line\n");
+        }
+    }));
+
+    timings.push(measure(|| {
+        for i in 0..SINGLE_EDIT_COUNT {
+            let from = BufferPosition::line_col((i * 89 % LINE_COUNT) as _, 0);
+            let to = BufferPosition::line_col(from.line_index + 1, 0);
+            lines.delete_range(BufferRange::between(from, to));
+        }
+    }));
+    assert_eq!(LINE_COUNT, lines.lines.len());
+
+    timings.push(measure_navigation(|| {
+        let len: usize = lines.lines.iter().map(|l| l.text.len()).sum();
+        assert!(len > 0);
+    }));
+
+    timings.push(measure_navigation(|| {
+        let mut len = 0;
+        for i in 0..LINE_COUNT {
+            len += lines.lines[i * 31 % LINE_COUNT].text.len();
+        }
+        assert!(len > 0);
+    }));
+
+    timings
+}
+
+fn main() {
+    let file_text = generate_text(LINE_COUNT);
+    let block_text = generate_text(BLOCK_LINE_COUNT);
+
+    // alternating runs keep one side from always starting with a warm allocator
+    fn keep_fastest(fastest: &mut Vec<Duration>, timings: Vec<Duration>) {
+        if fastest.is_empty() {
+            *fastest = timings;
+        } else {
+            for (fastest, timing) in fastest.iter_mut().zip(timings) {
+                *fastest = (*fastest).min(timing);
+            }
+        }
+    }
+    let mut content_timings = Vec::new();
+    let mut vec_timings = Vec::new();
+    for run in 0..RUN_COUNT {
+        if run % 2 == 0 {
+            keep_fastest(&mut content_timings, run_content(&file_text, &block_text));
+            keep_fastest(&mut vec_timings, run_vec(&file_text, &block_text));
+        } else {
+            keep_fastest(&mut vec_timings, run_vec(&file_text, &block_text));
+            keep_fastest(&mut content_timings, run_content(&file_text, &block_text));
+        }
+    }
+
+    println!("{:<24}{:>14}{:>14}", "", "BufferContent", "Vec baseline");
+    for ((name, content), vec) in MEASUREMENTS.iter().zip(content_timings).zip(vec_timings) {
+        println!(
+            "{:<24}{:>12.2}ms{:>12.2}ms",
+            name,
+            as_millis(content),
+            as_millis(vec)
+        );
+    }
+}
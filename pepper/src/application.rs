@@ -1,6 +1,7 @@
-use std::{env, fs, io, mem::ManuallyDrop, panic, path::Path, time::Duration};
+use std::{env, fs, io, mem::ManuallyDrop, panic, path::Path, thread, time::Duration};
 
 use crate::{
+    buffer::recoverable_swap_file_paths,
     client::ClientManager,
     editor::{Editor, EditorContext, EditorFlow},
     editor_utils::{load_config, MessageKind},
@@ -90,6 +91,22 @@ impl ServerApplication {
             }
         }
 
+        ctx.editor.load_state();
+
+        if !ctx.editor.config.recovery_directory.is_empty() {
+            let recovery_directory = Path::new(&ctx.editor.config.recovery_directory);
+            let count = recoverable_swap_file_paths(recovery_directory).count();
+            if count > 0 {
+                ctx.editor
+                    .status_bar
+                    .write(MessageKind::Info)
+                    .fmt(format_args!(
+                        "{} buffers have unsaved changes from a previous session. use `recover` to restore them",
+                        count
+                    ));
+            }
+        }
+
         Some(Self {
             ctx,
             client_event_receiver: ClientEventReceiver::default(),
@@ -238,6 +255,12 @@ impl ServerApplication {
     }
 }
 
+impl Drop for ServerApplication {
+    fn drop(&mut self) {
+        self.ctx.editor.on_exit(thread::panicking());
+    }
+}
+
 pub(crate) struct ClientApplication {
     target_client: TargetClient,
     server_read_buf: Vec<u8>,
@@ -282,6 +305,9 @@ impl ClientApplication {
             commands.push_str("open \"");
             commands.push_str(path);
             commands.push('"');
+            if args.read_only {
+                commands.push_str(" read-only");
+            }
             ClientEvent::Command(self.target_client, &commands)
                 .serialize(&mut self.server_write_buf);
         }
@@ -1,22 +1,27 @@
 use std::{
     fmt,
-    fs::File,
+    fs::{self, File},
     io,
-    ops::{Add, RangeBounds, Sub},
+    ops::{Add, Bound, Index, IndexMut, Range, RangeBounds, Sub},
     path::{Path, PathBuf},
     process::{Command, Stdio},
-    str::CharIndices,
+    str::{CharIndices, FromStr},
+    time::SystemTime,
 };
 
 use crate::{
     buffer_history::{BufferHistory, Edit, EditKind},
     buffer_position::{BufferPosition, BufferPositionIndex, BufferRange},
-    editor_utils::ResidualStrBytes,
+    directory::{directory_changes, read_directory_entries, DirectoryChange, DirectoryChangeError},
+    editor_utils::{hash_bytes, hash_more_bytes, write_file_atomically, ResidualStrBytes},
+    encoding::Encoding,
     events::{EditorEvent, EditorEventQueue},
+    glob::{Glob, InvalidGlobError},
     help,
     pattern::Pattern,
     platform::{Platform, PlatformProcessHandle, PlatformRequest, PooledBuf, ProcessTag},
     plugin::PluginHandle,
+    serialization::Serialize,
     syntax::{HighlightResult, HighlightedBuffer, SyntaxCollection, SyntaxHandle},
     word_database::{WordDatabase, WordIter, WordKind},
 };
@@ -46,6 +51,156 @@ pub fn find_delimiter_pair_at(text: &str, index: usize, delimiter: char) -> Opti
     None
 }
 
+// numbers that don't fit in 64 bits are not considered number literals
+// so they're never rewritten with a wrong value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberLiteral<'a> {
+    pub from: usize,
+    pub to: usize,
+    pub digits: &'a str,
+    pub radix: u32,
+    pub value: i64,
+}
+impl<'a> NumberLiteral<'a> {
+    pub fn write_added(&self, delta: i64, buf: &mut String) {
+        use fmt::Write;
+
+        let width = if self.radix != 10 || self.digits.starts_with('0') {
+            self.digits.len()
+        } else {
+            0
+        };
+        match self.radix {
+            16 => {
+                let value = (self.value as u64).wrapping_add(delta as u64);
+                buf.push_str("0x");
+                if self.digits.bytes().any(|b| b.is_ascii_uppercase()) {
+                    let _ = write!(buf, "{:0width$X}", value, width = width);
+                } else {
+                    let _ = write!(buf, "{:0width$x}", value, width = width);
+                }
+            }
+            2 => {
+                let value = (self.value as u64).wrapping_add(delta as u64);
+                let _ = write!(buf, "0b{:0width$b}", value, width = width);
+            }
+            _ => {
+                let value = self.value.saturating_add(delta);
+                if value < 0 {
+                    buf.push('-');
+                }
+                let _ = write!(buf, "{:0width$}", value.unsigned_abs(), width = width);
+            }
+        }
+    }
+}
+
+pub fn find_number_at(text: &str, index: usize) -> Option<NumberLiteral<'_>> {
+    let bytes = text.as_bytes();
+    let mut from = index.min(bytes.len());
+    if from < bytes.len() && bytes[from].is_ascii_alphanumeric() {
+        while from > 0 && bytes[from - 1].is_ascii_alphanumeric() {
+            from -= 1;
+        }
+    }
+    from += bytes[from..].iter().position(u8::is_ascii_digit)?;
+
+    let prefixed_digits_len = |prefix: u8, is_digit: fn(&u8) -> bool| match bytes.get(from + 1) {
+        Some(b) if bytes[from] == b'0' && b.to_ascii_lowercase() == prefix => {
+            bytes[from + 2..].iter().take_while(|b| is_digit(b)).count()
+        }
+        _ => 0,
+    };
+
+    let hex_len = prefixed_digits_len(b'x', u8::is_ascii_hexdigit);
+    if hex_len > 0 {
+        let digits = &text[from + 2..from + 2 + hex_len];
+        return Some(NumberLiteral {
+            from,
+            to: from + 2 + hex_len,
+            digits,
+            radix: 16,
+            value: u64::from_str_radix(digits, 16).ok()? as _,
+        });
+    }
+
+    let binary_len = prefixed_digits_len(b'b', |b| matches!(b, b'0' | b'1'));
+    if binary_len > 0 {
+        let digits = &text[from + 2..from + 2 + binary_len];
+        return Some(NumberLiteral {
+            from,
+            to: from + 2 + binary_len,
+            digits,
+            radix: 2,
+            value: u64::from_str_radix(digits, 2).ok()? as _,
+        });
+    }
+
+    let len = bytes[from..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    let is_negative = from > 0
+        && bytes[from - 1] == b'-'
+        && (from < 2 || !bytes[from - 2].is_ascii_alphanumeric());
+    let (from, digits_from) = if is_negative {
+        (from - 1, from)
+    } else {
+        (from, from)
+    };
+    let value = text[from..digits_from + len].parse().ok()?;
+    Some(NumberLiteral {
+        from,
+        to: digits_from + len,
+        digits: &text[digits_from..digits_from + len],
+        radix: 10,
+        value,
+    })
+}
+
+struct Tag<'a> {
+    from: usize,
+    to: usize,
+    name: &'a str,
+    is_closing: bool,
+}
+
+fn find_tags(line: &str) -> impl '_ + Iterator<Item = Tag<'_>> {
+    let mut index = 0;
+    std::iter::from_fn(move || loop {
+        let from = index + line[index..].find('<')?;
+        let is_closing = line[from + 1..].starts_with('/');
+        let name_from = if is_closing { from + 2 } else { from + 1 };
+        let name_len = line[name_from..]
+            .find(|c: char| !c.is_alphanumeric() && !matches!(c, '-' | '_' | ':' | '.'))
+            .unwrap_or(line.len() - name_from);
+        let to = name_from + line[name_from..].find('>')? + 1;
+
+        if name_len == 0 {
+            index = from + 1;
+            continue;
+        }
+        index = to;
+        if !is_closing && line[..to - 1].ends_with('/') {
+            continue;
+        }
+
+        return Some(Tag {
+            from,
+            to,
+            name: &line[name_from..name_from + name_len],
+            is_closing,
+        });
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurroundPair {
+    Balanced(char, char),
+    Delimiter(char),
+    Tag,
+}
+
 pub fn parse_path_and_position(text: &str) -> (&str, Option<BufferPosition>) {
     let text = text.trim();
     match text.rfind(':') {
@@ -303,7 +458,8 @@ impl BufferLinePool {
     pub fn acquire(&mut self) -> BufferLine {
         match self.pool.pop() {
             Some(mut line) => {
-                line.0.clear();
+                line.text.clear();
+                line.display_len = DisplayLen::zero();
                 line
             }
             None => BufferLine::new(),
@@ -315,15 +471,32 @@ impl BufferLinePool {
     }
 }
 
-pub struct BufferLine(String);
+pub struct BufferLine {
+    text: String,
+    display_len: DisplayLen,
+    ending: LineEnding,
+}
 
 impl BufferLine {
     fn new() -> Self {
-        Self(String::new())
+        Self {
+            text: String::new(),
+            display_len: DisplayLen::zero(),
+            ending: LineEnding::Lf,
+        }
     }
 
+    #[inline]
     pub fn as_str(&self) -> &str {
-        &self.0
+        &self.text
+    }
+
+    pub fn display_len(&self) -> DisplayLen {
+        self.display_len
+    }
+
+    pub fn ending(&self) -> LineEnding {
+        self.ending
     }
 
     pub fn chars_from(
@@ -333,7 +506,7 @@ impl BufferLine {
         impl '_ + Iterator<Item = (usize, char)>,
         impl '_ + Iterator<Item = (usize, char)>,
     ) {
-        let (left, right) = self.0.split_at(index);
+        let (left, right) = self.text.split_at(index);
         let left_chars = left.char_indices().rev();
         let right_chars = right.char_indices().map(move |(i, c)| (index + i, c));
         (left_chars, right_chars)
@@ -351,8 +524,8 @@ impl BufferLine {
         let mid_start_index = mid_word.index;
         let mid_end_index = mid_start_index + mid_word.text.len();
 
-        let left = &self.0[..mid_start_index];
-        let right = &self.0[mid_end_index..];
+        let left = &self.text[..mid_start_index];
+        let right = &self.text[mid_end_index..];
 
         let mut left_column_index = mid_start_index;
         let left_words = WordIter(left).rev().map(move |w| {
@@ -379,7 +552,7 @@ impl BufferLine {
     }
 
     pub fn word_at(&self, index: usize) -> WordRefWithIndex {
-        let (before, after) = self.0.split_at(index);
+        let (before, after) = self.text.split_at(index);
         match WordIter(after).next() {
             Some(right) => match WordIter(before).next_back() {
                 Some(left) => {
@@ -388,7 +561,7 @@ impl BufferLine {
                         let index = index - left.text.len();
                         WordRefWithIndex {
                             kind: left.kind,
-                            text: &self.0[index..end_index],
+                            text: &self.text[index..end_index],
                             index,
                         }
                     } else {
@@ -413,46 +586,296 @@ impl BufferLine {
         }
     }
 
-    pub fn split_off(
-        &mut self,
-        self_display_len: &mut DisplayLen,
-        other: &mut BufferLine,
-        other_display_len: &mut DisplayLen,
-        index: usize,
-    ) {
-        other.0.clear();
-        other.0.push_str(&self.0[index..]);
+    fn split_off(&mut self, other: &mut BufferLine, index: usize) {
+        other.text.clear();
+        other.text.push_str(&self.text[index..]);
 
-        if index < other.0.len() {
-            let display_len = DisplayLen::from(&self.0[..index]);
-            *other_display_len = *self_display_len - display_len;
-            *self_display_len = display_len;
+        if index < other.text.len() {
+            let display_len = DisplayLen::from(&self.text[..index]);
+            other.display_len = self.display_len - display_len;
+            self.display_len = display_len;
         } else {
-            *other_display_len = DisplayLen::from(&other.0[..]);
-            *self_display_len = *self_display_len - *other_display_len;
+            other.display_len = DisplayLen::from(&other.text[..]);
+            self.display_len = self.display_len - other.display_len;
+        }
+
+        self.text.truncate(index);
+    }
+
+    fn insert_text(&mut self, index: usize, text: &str) {
+        self.text.insert_str(index, text);
+        self.display_len = self.display_len + DisplayLen::from(text);
+    }
+
+    fn push_text(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.display_len = self.display_len + DisplayLen::from(text);
+    }
+
+    fn delete_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<usize>,
+    {
+        let deleted = self.text.drain(range);
+        self.display_len = self.display_len - DisplayLen::from(deleted.as_str());
+    }
+}
+
+/// Lines stored in a single `Vec` with a gap of empty lines at the last edit position.
+/// Inserting or removing lines only moves the lines between the gap and the edit, which are
+/// few since edits tend to happen close to each other, while indexing stays a single lookup.
+pub struct BufferLines {
+    lines: Vec<BufferLine>,
+    gap_start: usize,
+    gap_len: usize,
+}
+
+impl BufferLines {
+    const MIN_GAP_LEN: usize = 64;
+
+    const fn new() -> Self {
+        Self {
+            lines: Vec::new(),
+            gap_start: 0,
+            gap_len: 0,
         }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lines.len() - self.gap_len
+    }
 
-        self.0.truncate(index);
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
-    pub fn insert_text(&mut self, display_len: &mut DisplayLen, index: usize, text: &str) {
-        self.0.insert_str(index, text);
-        *display_len = *display_len + DisplayLen::from(text);
+    /// Maps a line index to its position in `lines`, which is past the end of it whenever the
+    /// line index is also out of bounds, so indexing `lines` is the only check needed.
+    #[inline]
+    fn physical_index(&self, index: usize) -> usize {
+        index + (index >= self.gap_start) as usize * self.gap_len
     }
 
-    pub fn push_text(&mut self, display_len: &mut DisplayLen, text: &str) {
-        self.0.push_str(text);
-        *display_len = *display_len + DisplayLen::from(text);
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&BufferLine> {
+        self.lines.get(self.physical_index(index))
     }
 
-    pub fn delete_range<R>(&mut self, display_len: &mut DisplayLen, range: R)
+    #[inline]
+    pub fn iter(&self) -> BufferLinesIter<'_> {
+        self.range(..)
+    }
+
+    #[inline]
+    pub fn range<R>(&self, range: R) -> BufferLinesIter<'_>
     where
         R: RangeBounds<usize>,
     {
-        let deleted = self.0.drain(range);
-        *display_len = *display_len - DisplayLen::from(deleted.as_str());
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i + 1,
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(
+            start <= end && end <= self.len(),
+            "line range out of bounds"
+        );
+
+        let gap_start = self.gap_start;
+        let gap_end = gap_start + self.gap_len;
+        BufferLinesIter {
+            before_gap: self.lines[start.min(gap_start)..end.min(gap_start)].iter(),
+            after_gap: self.lines
+                [self.physical_index(start).max(gap_end)..self.physical_index(end).max(gap_end)]
+                .iter(),
+        }
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut BufferLine> {
+        let (before_gap, rest) = self.lines.split_at_mut(self.gap_start);
+        before_gap.iter_mut().chain(rest[self.gap_len..].iter_mut())
+    }
+
+    fn move_gap_to(&mut self, index: usize) {
+        if self.gap_len == 0 {
+            self.gap_start = index;
+        } else if index < self.gap_start {
+            for i in (index..self.gap_start).rev() {
+                self.lines.swap(i, i + self.gap_len);
+            }
+            self.gap_start = index;
+        } else {
+            for i in self.gap_start..index {
+                self.lines.swap(i, i + self.gap_len);
+            }
+            self.gap_start = index;
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, line: BufferLine) {
+        if self.gap_len > 0 {
+            self.move_gap_to(self.len());
+            self.lines.truncate(self.gap_start);
+            self.gap_len = 0;
+        }
+        self.lines.push(line);
+        self.gap_start = self.lines.len();
+    }
+
+    /// Moves all `lines` into this collection starting at `index`.
+    fn insert_lines(&mut self, index: usize, lines: &mut Vec<BufferLine>) {
+        self.move_gap_to(index);
+        if self.gap_len < lines.len() {
+            let grow_len = lines.len() - self.gap_len + Self::MIN_GAP_LEN.max(self.len() / 64);
+            let gap_end = self.gap_start + self.gap_len;
+            self.lines.splice(
+                gap_end..gap_end,
+                std::iter::repeat_with(BufferLine::new).take(grow_len),
+            );
+            self.gap_len += grow_len;
+        }
+
+        let gap_start = self.gap_start;
+        for (slot, line) in self.lines[gap_start..].iter_mut().zip(lines.drain(..)) {
+            *slot = line;
+            self.gap_start += 1;
+            self.gap_len -= 1;
+        }
+    }
+
+    fn remove(&mut self, index: usize) -> BufferLine {
+        assert!(index < self.len(), "line index out of bounds");
+        self.move_gap_to(index);
+        let line = std::mem::replace(
+            &mut self.lines[self.gap_start + self.gap_len],
+            BufferLine::new(),
+        );
+        self.gap_len += 1;
+        line
+    }
+
+    fn remove_range<F>(&mut self, range: Range<usize>, mut f: F)
+    where
+        F: FnMut(BufferLine),
+    {
+        if range.start >= range.end {
+            return;
+        }
+        assert!(range.end <= self.len(), "line range out of bounds");
+
+        self.move_gap_to(range.start);
+        let gap_end = self.gap_start + self.gap_len;
+        let removed_len = range.end - range.start;
+        for line in &mut self.lines[gap_end..gap_end + removed_len] {
+            f(std::mem::replace(line, BufferLine::new()));
+        }
+        self.gap_len += removed_len;
+
+        if self.gap_len > Self::MIN_GAP_LEN.max(self.len()) {
+            let gap_end = self.gap_start + self.gap_len;
+            self.lines
+                .drain(self.gap_start + Self::MIN_GAP_LEN..gap_end);
+            self.gap_len = Self::MIN_GAP_LEN;
+        }
+    }
+
+    fn clear<F>(&mut self, f: F)
+    where
+        F: FnMut(BufferLine),
+    {
+        let gap_end = self.gap_start + self.gap_len;
+        self.lines.drain(self.gap_start..gap_end);
+        self.lines.drain(..).for_each(f);
+        self.gap_start = 0;
+        self.gap_len = 0;
+    }
+}
+
+impl Index<usize> for BufferLines {
+    type Output = BufferLine;
+    #[inline]
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.lines[self.physical_index(index)]
+    }
+}
+
+impl IndexMut<usize> for BufferLines {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        let index = self.physical_index(index);
+        &mut self.lines[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a BufferLines {
+    type Item = &'a BufferLine;
+    type IntoIter = BufferLinesIter<'a>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Clone)]
+pub struct BufferLinesIter<'a> {
+    before_gap: std::slice::Iter<'a, BufferLine>,
+    after_gap: std::slice::Iter<'a, BufferLine>,
+}
+impl<'a> Iterator for BufferLinesIter<'a> {
+    type Item = &'a BufferLine;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.before_gap.next() {
+            Some(line) => Some(line),
+            None => self.after_gap.next(),
+        }
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let before_gap_len = self.before_gap.len();
+        if n < before_gap_len {
+            self.before_gap.nth(n)
+        } else {
+            self.before_gap = [].iter();
+            self.after_gap.nth(n - before_gap_len)
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.before_gap.len() + self.after_gap.len();
+        (len, Some(len))
+    }
+
+    #[inline]
+    fn fold<B, F>(self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, Self::Item) -> B,
+    {
+        let acc = self.before_gap.fold(init, &mut f);
+        self.after_gap.fold(acc, f)
+    }
+}
+impl<'a> DoubleEndedIterator for BufferLinesIter<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self.after_gap.next_back() {
+            Some(line) => Some(line),
+            None => self.before_gap.next_back(),
+        }
     }
 }
+impl<'a> ExactSizeIterator for BufferLinesIter<'a> {}
 
 pub struct TextRangeIter<'a> {
     content: &'a BufferContent,
@@ -485,27 +908,88 @@ impl<'a> Iterator for TextRangeIter<'a> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "lf",
+            Self::CrLf => "crlf",
+        }
+    }
+}
+impl FromStr for LineEnding {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lf" => Ok(Self::Lf),
+            "crlf" => Ok(Self::CrLf),
+            _ => Err(()),
+        }
+    }
+}
+
+static BOM: &[u8] = b"\xef\xbb\xbf";
+
 pub struct BufferContent {
-    lines: Vec<BufferLine>,
-    line_display_lens: Vec<DisplayLen>,
+    lines: BufferLines,
     line_pool: BufferLinePool,
+    line_ending: LineEnding,
+    has_bom: bool,
 }
 
 impl BufferContent {
     pub fn new() -> Self {
+        let mut lines = BufferLines::new();
+        lines.push(BufferLine::new());
         Self {
-            lines: vec![BufferLine::new()],
-            line_display_lens: vec![DisplayLen::zero()],
+            lines,
             line_pool: BufferLinePool::new(),
+            line_ending: LineEnding::Lf,
+            has_bom: false,
         }
     }
 
-    pub fn lines(&self) -> &[BufferLine] {
+    #[inline]
+    pub fn lines(&self) -> &BufferLines {
         &self.lines
     }
 
-    pub fn line_display_lens(&self) -> &[DisplayLen] {
-        &self.line_display_lens
+    pub fn line_display_lens(&self) -> impl '_ + Iterator<Item = DisplayLen> {
+        self.lines.iter().map(BufferLine::display_len)
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    pub fn has_mixed_line_endings(&self) -> bool {
+        self.lines.iter().any(|l| l.ending != self.line_ending)
+    }
+
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.line_ending = line_ending;
+        for line in self.lines.iter_mut() {
+            line.ending = line_ending;
+        }
+    }
+
+    pub fn has_bom(&self) -> bool {
+        self.has_bom
+    }
+
+    pub fn set_bom(&mut self, has_bom: bool) {
+        self.has_bom = has_bom;
     }
 
     pub fn end(&self) -> BufferPosition {
@@ -520,64 +1004,123 @@ impl BufferContent {
     where
         R: io::BufRead,
     {
-        for line in self.lines.drain(..) {
-            self.line_pool.release(line);
-        }
-        self.line_display_lens.clear();
+        let line_pool = &mut self.line_pool;
+        self.lines.clear(|line| line_pool.release(line));
+
+        let mut lf_count = 0;
+        let mut crlf_count = 0;
+        let mut ends_with_new_line = true;
 
         loop {
             let mut line = self.line_pool.acquire();
-            match read.read_line(&mut line.0) {
+            match read.read_line(&mut line.text) {
                 Ok(0) => {
                     self.line_pool.release(line);
                     break;
                 }
                 Ok(_) => {
-                    if line.0.ends_with('\n') {
-                        line.0.pop();
-                    }
-                    if line.0.ends_with('\r') {
-                        line.0.pop();
+                    ends_with_new_line = line.text.ends_with('\n');
+                    if ends_with_new_line {
+                        line.text.pop();
                     }
-                    let display_len = DisplayLen::from(&line.0[..]);
+                    line.ending = if line.text.ends_with('\r') {
+                        line.text.pop();
+                        crlf_count += 1;
+                        LineEnding::CrLf
+                    } else {
+                        lf_count += 1;
+                        LineEnding::Lf
+                    };
+                    line.display_len = DisplayLen::from(&line.text[..]);
 
                     self.lines.push(line);
-                    self.line_display_lens.push(display_len);
                 }
                 Err(e) => {
-                    for line in self.lines.drain(..) {
-                        self.line_pool.release(line);
-                    }
-                    self.lines.push(self.line_pool.acquire());
-                    self.line_display_lens.clear();
-                    self.line_display_lens.push(DisplayLen::zero());
+                    self.clear();
                     return Err(e);
                 }
             }
         }
 
+        self.line_ending = if crlf_count > lf_count {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        };
+
         if self.lines.is_empty() {
-            self.lines.push(self.line_pool.acquire());
-            self.line_display_lens.push(DisplayLen::zero());
+            let mut line = self.line_pool.acquire();
+            line.ending = self.line_ending;
+            self.lines.push(line);
+        } else if !ends_with_new_line {
+            let last_index = self.lines.len() - 1;
+            self.lines[last_index].ending = self.line_ending;
         }
 
-        if self.lines[0]
-            .as_str()
-            .as_bytes()
-            .starts_with(b"\xef\xbb\xbf")
-        {
-            self.lines[0].delete_range(&mut self.line_display_lens[0], ..3);
+        self.has_bom = self.lines[0].as_str().as_bytes().starts_with(BOM);
+        if self.has_bom {
+            self.lines[0].delete_range(..BOM.len());
         }
 
         Ok(())
     }
 
+    pub fn read_encoded<R>(&mut self, read: &mut R, encoding: Encoding) -> io::Result<Encoding>
+    where
+        R: io::BufRead,
+    {
+        let encoding = match encoding {
+            Encoding::Utf8 => Encoding::detect(read.fill_buf()?).unwrap_or(encoding),
+            _ => encoding,
+        };
+        if let Encoding::Utf8 = encoding {
+            self.read(read)?;
+            return Ok(encoding);
+        }
+
+        let mut bytes = Vec::new();
+        read.read_to_end(&mut bytes)?;
+        let mut text = String::new();
+        if encoding.decode(&bytes, &mut text).is_err() {
+            self.clear();
+            return Err(io::Error::from(io::ErrorKind::InvalidData));
+        }
+        self.read(&mut text.as_bytes())?;
+        Ok(encoding)
+    }
+
+    pub fn write_encoded<W>(&self, write: &mut W, encoding: Encoding) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        if let Encoding::Utf8 = encoding {
+            return self.write(write);
+        }
+
+        let mut text = Vec::new();
+        self.write(&mut text)?;
+        let text =
+            String::from_utf8(text).map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+        let mut bytes = Vec::new();
+        if let Err(error) = encoding.encode(&text, &mut bytes) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                error.to_string(),
+            ));
+        }
+        write.write_all(&bytes)
+    }
+
     pub fn write<W>(&self, write: &mut W) -> io::Result<()>
     where
         W: io::Write,
     {
+        if self.has_bom {
+            write.write_all(BOM)?;
+        }
         for line in &self.lines {
-            write!(write, "{}\n", line.as_str())?;
+            write.write_all(line.as_str().as_bytes())?;
+            write.write_all(line.ending.as_str().as_bytes())?;
         }
         Ok(())
     }
@@ -617,10 +1160,9 @@ impl BufferContent {
     pub fn insert_text(&mut self, position: BufferPosition, text: &str) -> BufferRange {
         if !text.contains(&['\n', '\r'][..]) {
             let line = &mut self.lines[position.line_index as usize];
-            let display_len = &mut self.line_display_lens[position.line_index as usize];
 
             let previous_len = line.as_str().len();
-            line.insert_text(display_len, position.column_byte_index as _, text);
+            line.insert_text(position.column_byte_index as _, text);
             let len_diff = line.as_str().len() - previous_len;
 
             let end_position = BufferPosition::line_col(
@@ -630,56 +1172,52 @@ impl BufferContent {
             BufferRange::between(position, end_position)
         } else {
             let mut split_line = self.line_pool.acquire();
-            let mut split_display_len = DisplayLen::zero();
 
             let position_line = &mut self.lines[position.line_index as usize];
-            let position_display_len = &mut self.line_display_lens[position.line_index as usize];
-
-            position_line.split_off(
-                position_display_len,
-                &mut split_line,
-                &mut split_display_len,
-                position.column_byte_index as _,
-            );
+            position_line.split_off(&mut split_line, position.column_byte_index as _);
 
-            let mut line_count = 0 as BufferPositionIndex;
             let mut lines = text.lines();
             if let Some(line) = lines.next() {
-                position_line.push_text(position_display_len, &line);
+                position_line.push_text(line);
             }
-            for line_text in lines {
-                line_count += 1;
 
+            let mut new_lines = Vec::new();
+            for line_text in lines {
                 let mut line = self.line_pool.acquire();
-                let mut display_len = DisplayLen::zero();
-                line.push_text(&mut display_len, line_text);
-
-                let insert_index = (position.line_index + line_count) as _;
-                self.lines.insert(insert_index, line);
-                self.line_display_lens.insert(insert_index, display_len);
+                line.push_text(line_text);
+                line.ending = self.line_ending;
+                new_lines.push(line);
             }
 
             let end_position = if text.ends_with('\n') {
-                line_count += 1;
-
-                let insert_index = (position.line_index + line_count) as _;
-                self.lines.insert(insert_index, split_line);
-                self.line_display_lens
-                    .insert(insert_index, split_display_len);
-
-                BufferPosition::line_col(position.line_index + line_count, 0)
+                split_line.ending = self.line_ending;
+                new_lines.push(split_line);
+                BufferPosition::line_col(
+                    position.line_index + new_lines.len() as BufferPositionIndex,
+                    0,
+                )
             } else {
-                let index = (position.line_index + line_count) as usize;
-                let line = &mut self.lines[index];
-                let display_len = &mut self.line_display_lens[index];
-
+                let line = match new_lines.last_mut() {
+                    Some(line) => line,
+                    None => &mut self.lines[position.line_index as usize],
+                };
                 let column_byte_index = line.as_str().len() as _;
-                line.push_text(display_len, split_line.as_str());
+                line.push_text(split_line.as_str());
 
                 self.line_pool.release(split_line);
-                BufferPosition::line_col(position.line_index + line_count, column_byte_index)
+                BufferPosition::line_col(
+                    position.line_index + new_lines.len() as BufferPositionIndex,
+                    column_byte_index,
+                )
             };
 
+            if let Some(last_line) = new_lines.last_mut() {
+                let position_line = &mut self.lines[position.line_index as usize];
+                std::mem::swap(&mut position_line.ending, &mut last_line.ending);
+            }
+            self.lines
+                .insert_lines(position.line_index as usize + 1, &mut new_lines);
+
             BufferRange::between(position, end_position)
         }
     }
@@ -689,49 +1227,35 @@ impl BufferContent {
         let to = range.to;
 
         if from.line_index == to.line_index {
-            let line = &mut self.lines[from.line_index as usize];
-            let display_len = &mut self.line_display_lens[from.line_index as usize];
-
-            line.delete_range(
-                display_len,
-                from.column_byte_index as usize..to.column_byte_index as usize,
-            );
+            self.lines[from.line_index as usize]
+                .delete_range(from.column_byte_index as usize..to.column_byte_index as usize);
         } else {
-            let from_line = &mut self.lines[from.line_index as usize];
-            let from_display_len = &mut self.line_display_lens[from.line_index as usize];
-            from_line.delete_range(from_display_len, from.column_byte_index as usize..);
+            self.lines[from.line_index as usize].delete_range(from.column_byte_index as usize..);
 
+            let line_pool = &mut self.line_pool;
             let lines_range = (from.line_index as usize + 1)..to.line_index as usize;
-            if lines_range.start < lines_range.end {
-                for line in self.lines.drain(lines_range.clone()) {
-                    self.line_pool.release(line);
-                }
-                self.line_display_lens.drain(lines_range);
-            }
+            self.lines
+                .remove_range(lines_range, |line| line_pool.release(line));
 
             let to_line_index = from.line_index as usize + 1;
             if to_line_index < self.lines.len() {
                 let to_line = self.lines.remove(to_line_index);
-                self.line_display_lens.remove(to_line_index);
 
                 let from_line = &mut self.lines[from.line_index as usize];
-                let from_display_len = &mut self.line_display_lens[from.line_index as usize];
+                from_line.ending = to_line.ending;
+                from_line.push_text(&to_line.as_str()[to.column_byte_index as usize..]);
 
-                from_line.push_text(
-                    from_display_len,
-                    &to_line.as_str()[to.column_byte_index as usize..],
-                );
+                self.line_pool.release(to_line);
             }
         }
     }
 
     pub fn clear(&mut self) {
-        for line in self.lines.drain(..) {
-            self.line_pool.release(line);
-        }
+        let line_pool = &mut self.line_pool;
+        self.lines.clear(|line| line_pool.release(line));
         self.lines.push(self.line_pool.acquire());
-        self.line_display_lens.clear();
-        self.line_display_lens.push(DisplayLen::zero());
+        self.line_ending = LineEnding::Lf;
+        self.has_bom = false;
     }
 
     pub fn words_from(
@@ -887,30 +1411,338 @@ impl BufferContent {
 
         Some(BufferRange::between(left_position, right_position))
     }
-}
 
-impl fmt::Display for BufferContent {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let end_index = self.lines.len() - 1;
-        for line in &self.lines[..end_index] {
-            f.write_str(line.as_str())?;
-            f.write_str("\n")?;
+    pub fn find_surrounding_tags_at(
+        &self,
+        position: BufferPosition,
+    ) -> Option<(BufferRange, BufferRange)> {
+        let position = self.saturate_position(position);
+        let column_index = position.column_byte_index as usize;
+
+        let mut tags = Vec::new();
+        let mut closing_names = Vec::new();
+        for line_index in (0..=position.line_index).rev() {
+            let line = self.lines[line_index as usize].as_str();
+            tags.clear();
+            tags.extend(find_tags(line).filter(|t| {
+                line_index != position.line_index
+                    || (t.is_closing && t.to <= column_index)
+                    || (!t.is_closing && t.from < column_index)
+            }));
+
+            for tag in tags.iter().rev() {
+                if tag.is_closing {
+                    closing_names.push(tag.name);
+                    continue;
+                }
+                if let Some(i) = closing_names.iter().rposition(|&n| n == tag.name) {
+                    closing_names.truncate(i);
+                    continue;
+                }
+
+                if let Some(closing_range) = self.find_closing_tag(position, tag.name) {
+                    let opening_range = BufferRange::between(
+                        BufferPosition::line_col(line_index, tag.from as _),
+                        BufferPosition::line_col(line_index, tag.to as _),
+                    );
+                    return Some((opening_range, closing_range));
+                }
+            }
         }
-        f.write_str(self.lines[end_index].as_str())
+
+        None
     }
-}
 
-pub enum BufferReadError {
-    FileNotFound,
-    InvalidData,
-    Other,
-}
-impl fmt::Display for BufferReadError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::FileNotFound => f.write_str("file not found"),
-            Self::InvalidData => f.write_str("invalid data while reading from file"),
-            Self::Other => f.write_str("could not read from file"),
+    fn find_closing_tag(&self, position: BufferPosition, name: &str) -> Option<BufferRange> {
+        let column_index = position.column_byte_index as usize;
+        let mut depth = 0;
+        for line_index in position.line_index as usize..self.lines.len() {
+            let line = self.lines[line_index].as_str();
+            for tag in find_tags(line) {
+                if tag.name != name {
+                    continue;
+                }
+                if line_index == position.line_index as usize
+                    && ((tag.is_closing && tag.to <= column_index)
+                        || (!tag.is_closing && tag.from < column_index))
+                {
+                    continue;
+                }
+
+                if !tag.is_closing {
+                    depth += 1;
+                } else if depth > 0 {
+                    depth -= 1;
+                } else {
+                    return Some(BufferRange::between(
+                        BufferPosition::line_col(line_index as _, tag.from as _),
+                        BufferPosition::line_col(line_index as _, tag.to as _),
+                    ));
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn find_surround_pair_at(
+        &self,
+        position: BufferPosition,
+        pair: SurroundPair,
+    ) -> Option<(BufferRange, BufferRange)> {
+        fn delimiter_ranges(
+            range: BufferRange,
+            left: char,
+            right: char,
+        ) -> (BufferRange, BufferRange) {
+            let left_position = BufferPosition::line_col(
+                range.from.line_index,
+                range.from.column_byte_index - left.len_utf8() as BufferPositionIndex,
+            );
+            let right_position = BufferPosition::line_col(
+                range.to.line_index,
+                range.to.column_byte_index + right.len_utf8() as BufferPositionIndex,
+            );
+            (
+                BufferRange::between(left_position, range.from),
+                BufferRange::between(range.to, right_position),
+            )
+        }
+
+        match pair {
+            SurroundPair::Balanced(left, right) => {
+                let range = self.find_balanced_chars_at(position, left, right)?;
+                Some(delimiter_ranges(range, left, right))
+            }
+            SurroundPair::Delimiter(delimiter) => {
+                let range = self.find_delimiter_pair_at(position, delimiter)?;
+                Some(delimiter_ranges(range, delimiter, delimiter))
+            }
+            SurroundPair::Tag => self.find_surrounding_tags_at(position),
+        }
+    }
+
+    fn lines_range(&self, from_line_index: usize, to_line_index: usize) -> BufferRange {
+        let to_column_index = self.lines[to_line_index].as_str().len();
+        BufferRange::between(
+            BufferPosition::line_col(from_line_index as _, 0),
+            BufferPosition::line_col(to_line_index as _, to_column_index as _),
+        )
+    }
+
+    pub fn find_indentation_block_at(
+        &self,
+        line_index: BufferPositionIndex,
+        tab_size: u8,
+        include_header: bool,
+    ) -> BufferRange {
+        fn indentation(line: &str, tab_size: u8) -> Option<usize> {
+            let mut len = 0;
+            for c in line.chars() {
+                match c {
+                    ' ' => len += 1,
+                    '\t' => len += tab_size as usize,
+                    c if c.is_whitespace() => (),
+                    _ => return Some(len),
+                }
+            }
+            None
+        }
+
+        let lines = &self.lines;
+        let line_index = (line_index as usize).min(lines.len() - 1);
+        let base_indentation = lines
+            .range(line_index..)
+            .chain(lines.range(..line_index).rev())
+            .find_map(|l| indentation(l.as_str(), tab_size))
+            .unwrap_or(0);
+        let is_in_block = |i: usize| match indentation(lines[i].as_str(), tab_size) {
+            Some(indentation) => indentation >= base_indentation,
+            None => true,
+        };
+        let is_blank = |i: usize| indentation(lines[i].as_str(), tab_size).is_none();
+
+        let mut from = line_index;
+        while from > 0 && is_in_block(from - 1) {
+            from -= 1;
+        }
+        let mut to = line_index;
+        while to + 1 < lines.len() && is_in_block(to + 1) {
+            to += 1;
+        }
+
+        while from < line_index && is_blank(from) {
+            from += 1;
+        }
+        while to > line_index && is_blank(to) {
+            to -= 1;
+        }
+        if include_header && from > 0 {
+            from -= 1;
+        }
+
+        self.lines_range(from, to)
+    }
+
+    pub fn find_paragraph_at(
+        &self,
+        line_index: BufferPositionIndex,
+        include_blank_lines: bool,
+    ) -> BufferRange {
+        let lines = &self.lines;
+        let line_index = (line_index as usize).min(lines.len() - 1);
+        let is_blank = |i: usize| lines[i].as_str().trim().is_empty();
+        let blank = is_blank(line_index);
+
+        let mut from = line_index;
+        while from > 0 && is_blank(from - 1) == blank {
+            from -= 1;
+        }
+        let mut to = line_index;
+        while to + 1 < lines.len() && is_blank(to + 1) == blank {
+            to += 1;
+        }
+
+        if include_blank_lines && !blank {
+            if to + 1 < lines.len() {
+                while to + 1 < lines.len() && is_blank(to + 1) {
+                    to += 1;
+                }
+            } else {
+                while from > 0 && is_blank(from - 1) {
+                    from -= 1;
+                }
+            }
+        }
+
+        self.lines_range(from, to)
+    }
+
+    pub fn find_argument_at(
+        &self,
+        position: BufferPosition,
+        include_separator: bool,
+    ) -> Option<BufferRange> {
+        struct Argument {
+            from: Option<BufferPosition>,
+            to: BufferPosition,
+            separator: BufferPosition,
+        }
+
+        let position = self.saturate_position(position);
+        let range = [('(', ')'), ('[', ']'), ('{', '}')]
+            .iter()
+            .filter_map(|&(left, right)| self.find_balanced_chars_at(position, left, right))
+            .max_by_key(|r| r.from)?;
+
+        let mut arguments = Vec::new();
+        let mut argument = Argument {
+            from: None,
+            to: range.from,
+            separator: range.to,
+        };
+        let mut depth = 0;
+        let mut quote = None;
+        let mut is_escaping = false;
+
+        for line_index in range.from.line_index..=range.to.line_index {
+            let line = self.lines[line_index as usize].as_str();
+            let line_from = if line_index == range.from.line_index {
+                range.from.column_byte_index as usize
+            } else {
+                0
+            };
+            let line_to = if line_index == range.to.line_index {
+                range.to.column_byte_index as usize
+            } else {
+                line.len()
+            };
+
+            for (i, c) in line[line_from..line_to].char_indices() {
+                let i = line_from + i;
+                let position = BufferPosition::line_col(line_index, i as _);
+                let end_position = BufferPosition::line_col(line_index, (i + c.len_utf8()) as _);
+
+                if c.is_whitespace() {
+                    continue;
+                }
+                if argument.from.is_none() && !(c == ',' && depth == 0 && quote.is_none()) {
+                    argument.from = Some(position);
+                }
+
+                match quote {
+                    Some(_) if is_escaping => is_escaping = false,
+                    Some(_) if c == '\\' => is_escaping = true,
+                    Some(q) if c == q => quote = None,
+                    Some(_) => (),
+                    None => match c {
+                        '"' | '`' => quote = Some(c),
+                        '\'' if line[i + 1..].contains('\'') => quote = Some(c),
+                        '(' | '[' | '{' => depth += 1,
+                        ')' | ']' | '}' => depth -= 1,
+                        ',' if depth == 0 => {
+                            argument.separator = position;
+                            arguments.push(argument);
+                            argument = Argument {
+                                from: None,
+                                to: end_position,
+                                separator: range.to,
+                            };
+                            continue;
+                        }
+                        _ => (),
+                    },
+                }
+
+                argument.to = end_position;
+            }
+        }
+        arguments.push(argument);
+
+        let index = arguments
+            .iter()
+            .position(|a| position <= a.separator)
+            .unwrap_or(arguments.len() - 1);
+        let argument = &arguments[index];
+        let from = argument.from.unwrap_or(argument.to);
+
+        if !include_separator {
+            return Some(BufferRange::between(from, argument.to));
+        }
+
+        match (arguments.get(index + 1), index.checked_sub(1)) {
+            (Some(next), _) => Some(BufferRange::between(from, next.from.unwrap_or(next.to))),
+            (None, Some(previous_index)) => Some(BufferRange::between(
+                arguments[previous_index].to,
+                argument.to,
+            )),
+            (None, None) => Some(BufferRange::between(from, argument.to)),
+        }
+    }
+}
+
+impl fmt::Display for BufferContent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let end_index = self.lines.len() - 1;
+        for line in self.lines.range(..end_index) {
+            f.write_str(line.as_str())?;
+            f.write_str("\n")?;
+        }
+        f.write_str(self.lines[end_index].as_str())
+    }
+}
+
+pub enum BufferReadError {
+    FileNotFound,
+    InvalidData,
+    Other,
+}
+impl fmt::Display for BufferReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::FileNotFound => f.write_str("file not found"),
+            Self::InvalidData => f.write_str("invalid data while reading from file"),
+            Self::Other => f.write_str("could not read from file"),
         }
     }
 }
@@ -924,32 +1756,113 @@ impl From<io::Error> for BufferReadError {
     }
 }
 
-pub struct BufferWriteError;
+pub enum BufferWriteError {
+    PermissionDenied,
+    DirectoryNotFound,
+    StorageFull,
+    EntryAlreadyExists(PathBuf),
+    DirectoryNotEmpty(PathBuf),
+    InvalidDirectoryEntry(String),
+    Other(io::Error),
+}
 impl fmt::Display for BufferWriteError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str("could not write to file")
+        match self {
+            Self::PermissionDenied => f.write_str("could not write to file: permission denied"),
+            Self::DirectoryNotFound => f.write_str("could not write to file: directory not found"),
+            Self::StorageFull => f.write_str("could not write to file: disk is full"),
+            Self::EntryAlreadyExists(path) => {
+                write!(f, "could not write to file: {:?} already exists", path)
+            }
+            Self::DirectoryNotEmpty(path) => {
+                write!(f, "could not delete directory: {:?} is not empty", path)
+            }
+            Self::InvalidDirectoryEntry(entry) => write!(
+                f,
+                "invalid directory entry: {:?} must be inside the directory",
+                entry
+            ),
+            Self::Other(error) => write!(f, "could not write to file: {}", error),
+        }
     }
 }
 impl From<io::Error> for BufferWriteError {
-    fn from(_: io::Error) -> Self {
-        Self
+    fn from(other: io::Error) -> Self {
+        #[cfg(unix)]
+        static STORAGE_FULL_ERROR_CODES: &[i32] = &[libc::ENOSPC, libc::EDQUOT];
+        #[cfg(windows)]
+        static STORAGE_FULL_ERROR_CODES: &[i32] = &[
+            winapi::shared::winerror::ERROR_DISK_FULL as _,
+            winapi::shared::winerror::ERROR_HANDLE_DISK_FULL as _,
+        ];
+        #[cfg(not(any(unix, windows)))]
+        static STORAGE_FULL_ERROR_CODES: &[i32] = &[];
+
+        match other.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            io::ErrorKind::NotFound => Self::DirectoryNotFound,
+            _ => match other.raw_os_error() {
+                Some(code) if STORAGE_FULL_ERROR_CODES.contains(&code) => Self::StorageFull,
+                _ => Self::Other(other),
+            },
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_file_writable(path: &Path, _: &fs::Metadata) -> bool {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+    // write permission bits alone do not tell whether the current user may write the file
+    match CString::new(path.as_os_str().as_bytes()) {
+        Ok(path) => unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 },
+        Err(_) => true,
+    }
+}
+#[cfg(not(unix))]
+fn is_file_writable(_: &Path, metadata: &fs::Metadata) -> bool {
+    !metadata.permissions().readonly()
+}
+
+// same as hashing the content's text but without building it
+fn hash_content(content: &BufferContent) -> u64 {
+    let mut hash = hash_bytes(b"");
+    for (i, line) in content.lines().iter().enumerate() {
+        if i > 0 {
+            hash = hash_more_bytes(hash, b"\n");
+        }
+        hash = hash_more_bytes(hash, line.as_str().as_bytes());
     }
+    hash
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BufferProvider {
+    #[default]
+    None,
+    File,
+    Plugin(PluginHandle),
 }
 
 #[derive(Default)]
 pub struct BufferProperties {
     pub history_enabled: bool,
     pub saving_enabled: bool,
-    pub is_file: bool,
+    pub provider: BufferProvider,
     pub word_database_enabled: bool,
+    pub syntax_enabled: bool,
+    pub read_only: bool,
+    pub encoding: Encoding,
 }
 impl BufferProperties {
     pub fn text() -> Self {
         Self {
             history_enabled: true,
             saving_enabled: true,
-            is_file: true,
+            provider: BufferProvider::File,
             word_database_enabled: true,
+            syntax_enabled: true,
+            read_only: false,
+            encoding: Encoding::Utf8,
         }
     }
 
@@ -957,9 +1870,161 @@ impl BufferProperties {
         Self {
             history_enabled: false,
             saving_enabled: false,
-            is_file: true,
+            provider: BufferProvider::File,
             word_database_enabled: false,
+            syntax_enabled: true,
+            read_only: false,
+            encoding: Encoding::Utf8,
+        }
+    }
+}
+
+// properties a buffer had before entering large file mode
+#[derive(Clone, Copy)]
+struct LargeFileProperties {
+    word_database_enabled: bool,
+    syntax_enabled: bool,
+    history_enabled: bool,
+}
+
+// properties a buffer had before switching to the hex encoding
+#[derive(Clone, Copy)]
+struct TextProperties {
+    word_database_enabled: bool,
+    syntax_enabled: bool,
+}
+
+#[derive(Clone, Copy)]
+struct ProviderWrite {
+    new_path: bool,
+    edited: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+impl FileStamp {
+    fn from_metadata(metadata: &fs::Metadata) -> Option<Self> {
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        Self::from_metadata(&fs::metadata(path).ok()?)
+    }
+}
+
+const HISTORY_FILE_VERSION: u32 = 2;
+const SWAP_FILE_VERSION: u32 = 2;
+
+pub fn swap_file_path(
+    current_directory: &Path,
+    recovery_directory: &Path,
+    buffer_path: &Path,
+) -> PathBuf {
+    let path = current_directory.join(buffer_path);
+    let path_hash = hash_bytes(path.to_string_lossy().as_bytes());
+    recovery_directory.join(format!("{:016x}.swap", path_hash))
+}
+
+pub fn swap_file_paths(recovery_directory: &Path) -> impl Iterator<Item = PathBuf> {
+    fs::read_dir(recovery_directory)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|e| e == "swap").unwrap_or(false))
+}
+
+/// Swap files in `recovery_directory` whose session is no longer running.
+pub fn recoverable_swap_file_paths(recovery_directory: &Path) -> impl Iterator<Item = PathBuf> {
+    swap_file_paths(recovery_directory).filter(|path| {
+        let mut header = [0; 8];
+        let pid = match File::open(path)
+            .and_then(|mut file| io::Read::read_exact(&mut file, &mut header))
+        {
+            Ok(()) => {
+                let mut deserializer = &header[..];
+                match u32::deserialize(&mut deserializer) {
+                    Ok(SWAP_FILE_VERSION) => u32::deserialize(&mut deserializer).ok(),
+                    _ => None,
+                }
+            }
+            Err(_) => None,
+        };
+        match pid {
+            Some(pid) => !is_process_alive(pid),
+            None => false,
+        }
+    })
+}
+
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    // signal 0 only checks whether the process exists and may be signaled
+    let pid = match libc::pid_t::try_from(pid) {
+        Ok(pid) if pid > 0 => pid,
+        _ => return false,
+    };
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+#[cfg(windows)]
+fn is_process_alive(pid: u32) -> bool {
+    use winapi::{
+        shared::minwindef::FALSE,
+        um::{
+            handleapi::CloseHandle,
+            minwinbase::STILL_ACTIVE,
+            processthreadsapi::{GetExitCodeProcess, OpenProcess},
+            winnt::PROCESS_QUERY_LIMITED_INFORMATION,
+        },
+    };
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid);
+        if handle.is_null() {
+            return false;
         }
+        let mut exit_code = 0;
+        let alive =
+            GetExitCodeProcess(handle, &mut exit_code) != FALSE && exit_code == STILL_ACTIVE;
+        CloseHandle(handle);
+        alive
+    }
+}
+#[cfg(not(any(unix, windows)))]
+fn is_process_alive(_: u32) -> bool {
+    false
+}
+
+pub struct SwapFile {
+    pub pid: u32,
+    pub buffer_path: PathBuf,
+    pub text: String,
+}
+impl SwapFile {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut deserializer = &bytes[..];
+
+        let invalid_data = |_| io::Error::from(io::ErrorKind::InvalidData);
+        let version = u32::deserialize(&mut deserializer).map_err(invalid_data)?;
+        if version != SWAP_FILE_VERSION {
+            return Err(io::Error::from(io::ErrorKind::InvalidData));
+        }
+        let pid = u32::deserialize(&mut deserializer).map_err(invalid_data)?;
+        let buffer_path = <&str>::deserialize(&mut deserializer).map_err(invalid_data)?;
+        let text = <&str>::deserialize(&mut deserializer).map_err(invalid_data)?;
+
+        Ok(Self {
+            pid,
+            buffer_path: buffer_path.into(),
+            text: text.into(),
+        })
     }
 }
 
@@ -974,6 +2039,17 @@ pub struct Buffer {
     pub lints: BufferLintCollection,
     search_ranges: Vec<BufferRange>,
     needs_save: bool,
+    needs_swap: bool,
+    has_swap_file: bool,
+    file_stamp: Option<FileStamp>,
+    notified_file_stamp: Option<FileStamp>,
+    // whether `properties.read_only` came from the file permissions rather than the user
+    read_only_file: bool,
+    large_file: Option<LargeFileProperties>,
+    text_properties: Option<TextProperties>,
+    directory_entries: Option<Vec<String>>,
+    provider_read_pending: bool,
+    provider_write: Option<ProviderWrite>,
     pub properties: BufferProperties,
 }
 
@@ -990,6 +2066,16 @@ impl Buffer {
             lints: BufferLintCollection::default(),
             search_ranges: Vec::new(),
             needs_save: false,
+            needs_swap: false,
+            has_swap_file: false,
+            file_stamp: None,
+            notified_file_stamp: None,
+            read_only_file: false,
+            large_file: None,
+            text_properties: None,
+            directory_entries: None,
+            provider_read_pending: false,
+            provider_write: None,
             properties: BufferProperties::default(),
         }
     }
@@ -1005,9 +2091,43 @@ impl Buffer {
         self.history.clear();
         self.search_ranges.clear();
         self.needs_save = false;
+        self.needs_swap = false;
+        self.has_swap_file = false;
+        self.file_stamp = None;
+        self.notified_file_stamp = None;
+        self.read_only_file = false;
+        self.large_file = None;
+        self.text_properties = None;
+        self.directory_entries = None;
+        self.provider_read_pending = false;
+        self.provider_write = None;
         self.properties = BufferProperties::default();
     }
 
+    fn add_all_words_to_database(&mut self, word_database: &mut WordDatabase) {
+        if self.properties.word_database_enabled {
+            for line in &self.content.lines {
+                for word in WordIter(line.as_str()).of_kind(WordKind::Identifier) {
+                    word_database.add(word);
+                }
+            }
+        }
+    }
+
+    // for buffers read with the word database disabled. while in large file mode,
+    // the words are only added once that mode is left
+    pub(crate) fn enable_word_database(&mut self, word_database: &mut WordDatabase) {
+        match &mut self.large_file {
+            Some(properties) => properties.word_database_enabled = true,
+            None => {
+                if !self.properties.word_database_enabled {
+                    self.properties.word_database_enabled = true;
+                    self.add_all_words_to_database(word_database);
+                }
+            }
+        }
+    }
+
     fn remove_all_words_from_database(&mut self, word_database: &mut WordDatabase) {
         if self.properties.word_database_enabled {
             for line in &self.content.lines {
@@ -1026,11 +2146,28 @@ impl Buffer {
         &self.highlighted
     }
 
+    pub fn syntax_handle(&self) -> SyntaxHandle {
+        self.syntax_handle
+    }
+
     pub fn update_highlighting(&mut self, syntaxes: &SyntaxCollection) -> HighlightResult {
+        if !self.properties.syntax_enabled {
+            return HighlightResult::Complete;
+        }
         self.highlighted
             .highlight_dirty_lines(syntaxes.get(self.syntax_handle), &self.content)
     }
 
+    fn reset_highlighting(&mut self) {
+        self.highlighted.clear();
+        if self.properties.syntax_enabled {
+            self.highlighted.insert_range(BufferRange::between(
+                BufferPosition::zero(),
+                BufferPosition::line_col((self.content.lines.len() - 1) as _, 0),
+            ));
+        }
+    }
+
     pub fn refresh_syntax(&mut self, syntaxes: &SyntaxCollection) {
         let path = self.path.to_str().unwrap_or("");
         if path.is_empty() {
@@ -1041,11 +2178,129 @@ impl Buffer {
 
         if self.syntax_handle != syntax_handle {
             self.syntax_handle = syntax_handle;
-            self.highlighted.clear();
-            self.highlighted.insert_range(BufferRange::between(
-                BufferPosition::zero(),
-                BufferPosition::line_col((self.content.lines.len() - 1) as _, 0),
-            ));
+            self.reset_highlighting();
+        }
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.directory_entries.is_some()
+    }
+
+    pub fn directory_changes<'a>(&'a self, changes: &mut Vec<DirectoryChange<'a>>) {
+        changes.clear();
+        if let Some(entries) = &self.directory_entries {
+            let current_entries = self
+                .content
+                .lines()
+                .iter()
+                .map(BufferLine::as_str)
+                .filter(|l| !l.is_empty());
+            directory_changes(entries, current_entries, changes);
+        }
+    }
+
+    fn apply_directory_changes(&mut self) -> Result<(), BufferWriteError> {
+        fn write_error(error: DirectoryChangeError) -> BufferWriteError {
+            match error {
+                DirectoryChangeError::AlreadyExists(path) => {
+                    BufferWriteError::EntryAlreadyExists(path)
+                }
+                DirectoryChangeError::NotEmpty(path) => BufferWriteError::DirectoryNotEmpty(path),
+                DirectoryChangeError::InvalidEntry(entry) => {
+                    BufferWriteError::InvalidDirectoryEntry(entry)
+                }
+                DirectoryChangeError::Io(error) => error.into(),
+            }
+        }
+
+        let mut changes = Vec::new();
+        self.directory_changes(&mut changes);
+        for change in &changes {
+            change.validate().map_err(write_error)?;
+        }
+
+        // changes that were already applied when one fails are kept in `directory_entries`
+        // so that they're not applied again on the next save
+        let mut applied = Vec::new();
+        let mut result = Ok(());
+        for change in &changes {
+            match change.apply(&self.path) {
+                Ok(()) => applied.push(match *change {
+                    DirectoryChange::Create(entry) => (None, Some(entry.to_string())),
+                    DirectoryChange::Rename { from, to } => {
+                        (Some(from.to_string()), Some(to.to_string()))
+                    }
+                    DirectoryChange::Delete(entry) => (Some(entry.to_string()), None),
+                }),
+                Err(error) => {
+                    result = Err(write_error(error));
+                    break;
+                }
+            }
+        }
+
+        if let Some(entries) = &mut self.directory_entries {
+            if result.is_ok() {
+                entries.clear();
+                for line in self.content.lines() {
+                    if !line.as_str().is_empty() {
+                        entries.push(line.as_str().into());
+                    }
+                }
+            } else {
+                for (from, to) in applied {
+                    let index = from.and_then(|from| entries.iter().position(|e| *e == from));
+                    match (index, to) {
+                        (Some(i), Some(to)) => entries[i] = to,
+                        (Some(i), None) => {
+                            entries.remove(i);
+                        }
+                        (None, Some(to)) => entries.push(to),
+                        (None, None) => (),
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub fn is_large_file(&self) -> bool {
+        self.large_file.is_some()
+    }
+
+    pub fn set_large_file(
+        &mut self,
+        large_file: bool,
+        history_enabled: bool,
+        word_database: &mut WordDatabase,
+    ) {
+        if self.is_large_file() == large_file {
+            return;
+        }
+
+        self.remove_all_words_from_database(word_database);
+        match self.large_file.take() {
+            Some(properties) => {
+                self.properties.word_database_enabled = properties.word_database_enabled;
+                self.properties.syntax_enabled = properties.syntax_enabled;
+                self.properties.history_enabled = properties.history_enabled;
+            }
+            None => {
+                self.large_file = Some(LargeFileProperties {
+                    word_database_enabled: self.properties.word_database_enabled,
+                    syntax_enabled: self.properties.syntax_enabled,
+                    history_enabled: self.properties.history_enabled,
+                });
+                self.properties.word_database_enabled = false;
+                self.properties.syntax_enabled = false;
+                self.properties.history_enabled &= history_enabled;
+            }
+        }
+        self.add_all_words_to_database(word_database);
+        self.reset_highlighting();
+
+        if !self.properties.history_enabled {
+            self.history.clear();
         }
     }
 
@@ -1057,6 +2312,293 @@ impl Buffer {
         self.properties.saving_enabled && self.needs_save
     }
 
+    fn mark_needs_save(&mut self) {
+        self.needs_save = true;
+        self.needs_swap = true;
+        if let Some(write) = &mut self.provider_write {
+            write.edited = true;
+        }
+    }
+
+    fn reject_read_only_edit(&self, events: &mut EditorEventQueue) -> bool {
+        if self.properties.read_only {
+            events.enqueue(EditorEvent::BufferEditRejected {
+                handle: self.handle,
+            });
+        }
+        self.properties.read_only
+    }
+
+    pub fn file_changed_on_disk(&self) -> bool {
+        match self.file_stamp {
+            Some(stamp) => matches!(FileStamp::from_path(&self.path), Some(s) if s != stamp),
+            None => false,
+        }
+    }
+
+    pub(crate) fn poll_file_change(&mut self) -> bool {
+        let stamp = match self.file_stamp {
+            Some(stamp) => stamp,
+            None => return false,
+        };
+        let current_stamp = match FileStamp::from_path(&self.path) {
+            Some(stamp) => stamp,
+            None => return false,
+        };
+        if current_stamp == stamp || self.notified_file_stamp == Some(current_stamp) {
+            return false;
+        }
+
+        self.notified_file_stamp = Some(current_stamp);
+        true
+    }
+
+    fn history_file_path(
+        &self,
+        current_directory: &Path,
+        undo_directory: &Path,
+    ) -> Option<PathBuf> {
+        if !self.properties.history_enabled
+            || !self.properties.saving_enabled
+            || self.properties.provider != BufferProvider::File
+            || self.path.as_os_str().is_empty()
+        {
+            return None;
+        }
+
+        let path = current_directory.join(&self.path);
+        let path_hash = hash_bytes(path.to_string_lossy().as_bytes());
+        Some(undo_directory.join(format!("{:016x}.undo", path_hash)))
+    }
+
+    pub fn save_history_to_file(
+        &mut self,
+        current_directory: &Path,
+        undo_directory: &Path,
+    ) -> io::Result<()> {
+        let path = match self.history_file_path(current_directory, undo_directory) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        self.history.commit_edits();
+
+        let mut bytes = Vec::new();
+        HISTORY_FILE_VERSION.serialize(&mut bytes);
+        hash_content(&self.content).serialize(&mut bytes);
+        self.history.serialize(&mut bytes);
+
+        fs::create_dir_all(undo_directory)?;
+        write_file_atomically(&path, false, |writer| io::Write::write_all(writer, &bytes))
+    }
+
+    pub fn load_history_from_file(&mut self, current_directory: &Path, undo_directory: &Path) {
+        let path = match self.history_file_path(current_directory, undo_directory) {
+            Some(path) => path,
+            None => return,
+        };
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(_) => return,
+        };
+
+        let mut deserializer = &bytes[..];
+        if !matches!(
+            u32::deserialize(&mut deserializer),
+            Ok(HISTORY_FILE_VERSION)
+        ) {
+            return;
+        }
+        let content_hash = hash_content(&self.content);
+        if !matches!(u64::deserialize(&mut deserializer), Ok(hash) if hash == content_hash) {
+            return;
+        }
+        // the content hash already tells whether the edits still fit it
+        if let Ok(history) = BufferHistory::deserialize(&mut deserializer) {
+            self.history = history;
+        }
+    }
+
+    fn swap_file_path(
+        &self,
+        current_directory: &Path,
+        recovery_directory: &Path,
+    ) -> Option<PathBuf> {
+        if !self.properties.saving_enabled
+            || self.properties.provider != BufferProvider::File
+            || self.path.as_os_str().is_empty()
+        {
+            return None;
+        }
+        Some(swap_file_path(
+            current_directory,
+            recovery_directory,
+            &self.path,
+        ))
+    }
+
+    /// Whether there's a swap file for this buffer that was not written by this session.
+    pub fn has_foreign_swap_file(
+        &self,
+        current_directory: &Path,
+        recovery_directory: &Path,
+    ) -> bool {
+        !self.has_swap_file
+            && matches!(
+                self.swap_file_path(current_directory, recovery_directory),
+                Some(path) if path.exists()
+            )
+    }
+
+    pub fn write_swap_file(
+        &mut self,
+        current_directory: &Path,
+        recovery_directory: &Path,
+    ) -> io::Result<()> {
+        if self.is_large_file() {
+            return Ok(());
+        }
+        let path = match self.swap_file_path(current_directory, recovery_directory) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let buffer_path = current_directory.join(&self.path);
+        let buffer_path = match buffer_path.to_str() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        // the text is serialized as a str but written line by line
+        let lines = self.content.lines();
+        let text_len = lines.iter().map(|l| l.as_str().len()).sum::<usize>() + lines.len() - 1;
+        let mut header = Vec::new();
+        SWAP_FILE_VERSION.serialize(&mut header);
+        std::process::id().serialize(&mut header);
+        buffer_path.serialize(&mut header);
+        (text_len as u32).serialize(&mut header);
+
+        fs::create_dir_all(recovery_directory)?;
+        write_file_atomically(&path, false, |writer| {
+            io::Write::write_all(writer, &header)?;
+            for (i, line) in lines.iter().enumerate() {
+                if i > 0 {
+                    io::Write::write_all(writer, b"\n")?;
+                }
+                io::Write::write_all(writer, line.as_str().as_bytes())?;
+            }
+            Ok(())
+        })?;
+
+        self.needs_swap = false;
+        self.has_swap_file = true;
+        Ok(())
+    }
+
+    pub fn remove_swap_file(&mut self, current_directory: &Path, recovery_directory: &Path) {
+        if !self.has_swap_file {
+            return;
+        }
+        if let Some(path) = self.swap_file_path(current_directory, recovery_directory) {
+            let _ = fs::remove_file(path);
+        }
+        self.has_swap_file = false;
+    }
+
+    /// Writes a swap file for unsaved changes made since the last call
+    /// and removes it once the buffer no longer needs saving or becomes a large file.
+    pub(crate) fn update_swap_file(
+        &mut self,
+        current_directory: &Path,
+        recovery_directory: &Path,
+    ) -> io::Result<()> {
+        if !self.needs_save() || self.is_large_file() {
+            self.remove_swap_file(current_directory, recovery_directory);
+            Ok(())
+        } else if self.needs_swap {
+            self.write_swap_file(current_directory, recovery_directory)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn recover_from_swap_file(
+        &mut self,
+        word_database: &mut WordDatabase,
+        swap_file: &SwapFile,
+        events: &mut EditorEventQueue,
+    ) {
+        let range = BufferRange::between(BufferPosition::zero(), self.content.end());
+        self.delete_range(word_database, range, events);
+        self.insert_text(
+            word_database,
+            BufferPosition::zero(),
+            &swap_file.text,
+            events,
+        );
+        self.commit_edits();
+        self.has_swap_file = true;
+    }
+
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.content.line_ending() != line_ending || self.content.has_mixed_line_endings() {
+            self.content.set_line_ending(line_ending);
+            self.mark_needs_save();
+        }
+    }
+
+    pub fn set_bom(&mut self, has_bom: bool) {
+        if self.content.has_bom() != has_bom {
+            self.content.set_bom(has_bom);
+            self.mark_needs_save();
+        }
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        if self.properties.encoding != encoding {
+            self.properties.encoding = encoding;
+            self.mark_needs_save();
+        }
+    }
+
+    // a hex dump and a text are not the same content, so switching between them reads the
+    // file again. like opening a buffer as hex, it also leaves out words and syntax
+    pub fn reopen_with_encoding(
+        &mut self,
+        encoding: Encoding,
+        word_database: &mut WordDatabase,
+        events: &mut EditorEventQueue,
+    ) -> Result<(), BufferReadError> {
+        self.remove_all_words_from_database(word_database);
+        self.content.clear();
+
+        let (word_database_enabled, syntax_enabled) = match &mut self.large_file {
+            Some(properties) => (
+                &mut properties.word_database_enabled,
+                &mut properties.syntax_enabled,
+            ),
+            None => (
+                &mut self.properties.word_database_enabled,
+                &mut self.properties.syntax_enabled,
+            ),
+        };
+        if encoding == Encoding::Hex {
+            if self.text_properties.is_none() {
+                self.text_properties = Some(TextProperties {
+                    word_database_enabled: *word_database_enabled,
+                    syntax_enabled: *syntax_enabled,
+                });
+            }
+            *word_database_enabled = false;
+            *syntax_enabled = false;
+        } else if let Some(properties) = self.text_properties.take() {
+            *word_database_enabled = properties.word_database_enabled;
+            *syntax_enabled = properties.syntax_enabled;
+        }
+
+        self.properties.encoding = encoding;
+        self.read_from_file(word_database, events)
+    }
+
     pub fn insert_text(
         &mut self,
         word_database: &mut WordDatabase,
@@ -1067,14 +2609,18 @@ impl Buffer {
         self.search_ranges.clear();
         let position = self.content.saturate_position(position);
 
-        if text.is_empty() {
+        if text.is_empty() || self.reject_read_only_edit(events) {
             return BufferRange::between(position, position);
         }
-        self.needs_save = true;
+        self.mark_needs_save();
 
         let range = Self::insert_text_no_history(
             &mut self.content,
-            &mut self.highlighted,
+            if self.properties.syntax_enabled {
+                Some(&mut self.highlighted)
+            } else {
+                None
+            },
             &mut self.lints,
             self.properties.word_database_enabled,
             word_database,
@@ -1097,7 +2643,7 @@ impl Buffer {
 
     fn insert_text_no_history(
         content: &mut BufferContent,
-        highlighted: &mut HighlightedBuffer,
+        highlighted: Option<&mut HighlightedBuffer>,
         lints: &mut BufferLintCollection,
         uses_word_database: bool,
         word_database: &mut WordDatabase,
@@ -1113,12 +2659,15 @@ impl Buffer {
         }
 
         let range = content.insert_text(position, text);
-        highlighted.insert_range(range);
+        if let Some(highlighted) = highlighted {
+            highlighted.insert_range(range);
+        }
         lints.insert_range(range);
 
         if uses_word_database {
-            for line in
-                &content.lines()[range.from.line_index as usize..=range.to.line_index as usize]
+            for line in content
+                .lines()
+                .range(range.from.line_index as usize..=range.to.line_index as usize)
             {
                 for word in WordIter(line.as_str()).of_kind(WordKind::Identifier) {
                     word_database.add(word);
@@ -1139,10 +2688,10 @@ impl Buffer {
         range.from = self.content.saturate_position(range.from);
         range.to = self.content.saturate_position(range.to);
 
-        if range.from == range.to {
+        if range.from == range.to || self.reject_read_only_edit(events) {
             return;
         }
-        self.needs_save = true;
+        self.mark_needs_save();
 
         events.enqueue(EditorEvent::BufferDeleteText {
             handle: self.handle,
@@ -1196,7 +2745,11 @@ impl Buffer {
 
         Self::delete_range_no_history(
             &mut self.content,
-            &mut self.highlighted,
+            if self.properties.syntax_enabled {
+                Some(&mut self.highlighted)
+            } else {
+                None
+            },
             &mut self.lints,
             self.properties.word_database_enabled,
             word_database,
@@ -1206,15 +2759,16 @@ impl Buffer {
 
     fn delete_range_no_history(
         content: &mut BufferContent,
-        highlighted: &mut HighlightedBuffer,
+        highlighted: Option<&mut HighlightedBuffer>,
         lints: &mut BufferLintCollection,
         uses_word_database: bool,
         word_database: &mut WordDatabase,
         range: BufferRange,
     ) {
         if uses_word_database {
-            for line in
-                &content.lines()[range.from.line_index as usize..=range.to.line_index as usize]
+            for line in content
+                .lines()
+                .range(range.from.line_index as usize..=range.to.line_index as usize)
             {
                 for word in WordIter(line.as_str()).of_kind(WordKind::Identifier) {
                     word_database.remove(word);
@@ -1232,7 +2786,9 @@ impl Buffer {
             content.delete_range(range);
         }
 
-        highlighted.delete_range(range);
+        if let Some(highlighted) = highlighted {
+            highlighted.delete_range(range);
+        }
         lints.delete_range(range);
     }
 
@@ -1240,12 +2796,20 @@ impl Buffer {
         self.history.commit_edits();
     }
 
+    pub fn history(&self) -> &BufferHistory {
+        &self.history
+    }
+
+    pub fn history_jump_path(&mut self, group_index: Option<usize>) -> (usize, usize) {
+        self.history.jump_path(group_index)
+    }
+
     pub fn undo(
         &mut self,
         word_database: &mut WordDatabase,
         events: &mut EditorEventQueue,
     ) -> impl '_ + ExactSizeIterator<Item = Edit<'_>> + DoubleEndedIterator<Item = Edit<'_>> {
-        self.apply_history_edits(word_database, events, BufferHistory::undo_edits)
+        self.apply_history_edits(word_database, events, BufferHistory::undo_edits_if)
     }
 
     pub fn redo(
@@ -1253,7 +2817,7 @@ impl Buffer {
         word_database: &mut WordDatabase,
         events: &mut EditorEventQueue,
     ) -> impl '_ + ExactSizeIterator<Item = Edit<'_>> + DoubleEndedIterator<Item = Edit<'_>> {
-        self.apply_history_edits(word_database, events, BufferHistory::redo_edits)
+        self.apply_history_edits(word_database, events, BufferHistory::redo_edits_if)
     }
 
     fn apply_history_edits<'a, F, I>(
@@ -1263,24 +2827,31 @@ impl Buffer {
         selector: F,
     ) -> I
     where
-        F: FnOnce(&'a mut BufferHistory) -> I,
+        F: FnOnce(&'a mut BufferHistory, bool) -> I,
         I: 'a + Clone + ExactSizeIterator<Item = Edit<'a>>,
     {
+        if self.reject_read_only_edit(events) {
+            return selector(&mut self.history, false);
+        }
         self.search_ranges.clear();
-        self.needs_save = true;
+        self.mark_needs_save();
 
         let content = &mut self.content;
-        let highlighted = &mut self.highlighted;
+        let mut highlighted = if self.properties.syntax_enabled {
+            Some(&mut self.highlighted)
+        } else {
+            None
+        };
         let lints = &mut self.lints;
         let uses_word_database = self.properties.word_database_enabled;
 
-        let edits = selector(&mut self.history);
+        let edits = selector(&mut self.history, true);
         for edit in edits.clone() {
             match edit.kind {
                 EditKind::Insert => {
                     Self::insert_text_no_history(
                         content,
-                        highlighted,
+                        highlighted.as_deref_mut(),
                         lints,
                         uses_word_database,
                         word_database,
@@ -1292,7 +2863,7 @@ impl Buffer {
                 EditKind::Delete => {
                     Self::delete_range_no_history(
                         content,
-                        highlighted,
+                        highlighted.as_deref_mut(),
                         lints,
                         uses_word_database,
                         word_database,
@@ -1325,6 +2896,8 @@ impl Buffer {
         events: &mut EditorEventQueue,
     ) -> Result<(), BufferReadError> {
         self.needs_save = false;
+        self.file_stamp = None;
+        self.notified_file_stamp = None;
         self.history.clear();
         self.search_ranges.clear();
 
@@ -1332,8 +2905,16 @@ impl Buffer {
             handle: self.handle,
         });
 
+        if let BufferProvider::Plugin(_) = self.properties.provider {
+            self.remove_all_words_from_database(word_database);
+            self.content.clear();
+            self.reset_highlighting();
+            self.provider_read_pending = true;
+            return Ok(());
+        }
+
         if !self.path.starts_with(help::HELP_PREFIX)
-            && (!self.properties.saving_enabled || !self.properties.is_file)
+            && (!self.properties.saving_enabled || self.properties.provider != BufferProvider::File)
         {
             return Ok(());
         }
@@ -1346,37 +2927,150 @@ impl Buffer {
             return Err(BufferReadError::FileNotFound);
         } else if let Some(mut reader) = help::open(&self.path) {
             self.content.read(&mut reader)?;
+        } else if self.path.is_dir() {
+            let mut entries = self.directory_entries.take().unwrap_or_default();
+            read_directory_entries(&self.path, &mut entries)?;
+            let mut text = String::new();
+            for entry in &entries {
+                text.push_str(entry);
+                text.push('\n');
+            }
+            self.content.read(&mut text.as_bytes())?;
+            self.directory_entries = Some(entries);
         } else {
+            self.directory_entries = None;
             let file = File::open(&self.path)?;
+            if let Ok(metadata) = file.metadata() {
+                self.file_stamp = FileStamp::from_metadata(&metadata);
+                if self.read_only_file || !self.properties.read_only {
+                    self.read_only_file = !is_file_writable(&self.path, &metadata);
+                    self.properties.read_only = self.read_only_file;
+                }
+            }
             let mut reader = io::BufReader::new(file);
-            self.content.read(&mut reader)?;
+            self.properties.encoding = self
+                .content
+                .read_encoded(&mut reader, self.properties.encoding)?;
         }
 
-        self.highlighted.insert_range(BufferRange::between(
-            BufferPosition::zero(),
-            BufferPosition::line_col((self.content.lines.len() - 1) as _, 0),
-        ));
+        self.reset_highlighting();
+        self.add_all_words_to_database(word_database);
+
+        Ok(())
+    }
+
+    /// Reads the file again as one undoable edit that replaces the whole content.
+    /// Unlike `read_from_file`, the history is kept.
+    pub fn reload_from_file(
+        &mut self,
+        word_database: &mut WordDatabase,
+        events: &mut EditorEventQueue,
+    ) -> Result<(), BufferReadError> {
+        let file = File::open(&self.path)?;
+        let metadata = file.metadata()?;
+        let mut content = BufferContent::new();
+        let mut reader = io::BufReader::new(file);
+        let encoding = content.read_encoded(&mut reader, self.properties.encoding)?;
+        let text = content.to_string();
+
+        // it's the file that changed, so even read-only buffers follow it
+        let read_only = std::mem::replace(&mut self.properties.read_only, false);
+        self.commit_edits();
+        let range = BufferRange::between(BufferPosition::zero(), self.content.end());
+        self.delete_range(word_database, range, events);
+        self.insert_text(word_database, BufferPosition::zero(), &text, events);
+        self.commit_edits();
+        self.properties.read_only = read_only;
+
+        self.content.line_ending = content.line_ending;
+        self.content.has_bom = content.has_bom;
+        for (line, read_line) in self.content.lines.iter_mut().zip(content.lines.iter()) {
+            line.ending = read_line.ending;
+        }
+        self.properties.encoding = encoding;
+        self.file_stamp = FileStamp::from_metadata(&metadata);
+        self.notified_file_stamp = None;
+        self.needs_save = false;
+
+        Ok(())
+    }
+
+    pub(crate) fn take_provider_read(&mut self) -> Option<PluginHandle> {
+        match self.properties.provider {
+            BufferProvider::Plugin(handle) if self.provider_read_pending => {
+                self.provider_read_pending = false;
+                Some(handle)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn provide_content<R>(
+        &mut self,
+        word_database: &mut WordDatabase,
+        read: &mut R,
+        events: &mut EditorEventQueue,
+    ) -> Result<(), BufferReadError>
+    where
+        R: io::BufRead,
+    {
+        self.needs_save = false;
+        self.history.clear();
+        self.search_ranges.clear();
+        self.provider_read_pending = false;
+
+        events.enqueue(EditorEvent::BufferRead {
+            handle: self.handle,
+        });
+
+        self.remove_all_words_from_database(word_database);
+        self.content.clear();
+        self.highlighted.clear();
+
+        self.properties.encoding = self.content.read_encoded(read, self.properties.encoding)?;
+
+        self.reset_highlighting();
+        self.add_all_words_to_database(word_database);
 
-        if self.properties.word_database_enabled {
-            for line in &self.content.lines {
-                for word in WordIter(line.as_str()).of_kind(WordKind::Identifier) {
-                    word_database.add(word);
-                }
-            }
+        Ok(())
+    }
+
+    /// Called by the plugin providing this buffer once it's done writing the buffer content.
+    /// If the write failed, the buffer keeps its unsaved changes.
+    /// Otherwise they're only cleared if the buffer was not edited while it was being written.
+    pub fn finish_provider_write(&mut self, written: bool, events: &mut EditorEventQueue) {
+        let write = match self.provider_write.take() {
+            Some(write) => write,
+            None => return,
+        };
+
+        if !written {
+            self.needs_save = true;
+            return;
         }
 
-        Ok(())
+        if !write.edited {
+            self.needs_save = false;
+        }
+        events.enqueue(EditorEvent::BufferWrite {
+            handle: self.handle,
+            new_path: write.new_path,
+        });
     }
 
     pub fn write_to_file(
         &mut self,
         new_path: Option<&Path>,
+        backup: bool,
         events: &mut EditorEventQueue,
     ) -> Result<(), BufferWriteError> {
         let new_path = match new_path {
             Some(path) => {
                 self.properties.saving_enabled = true;
-                self.properties.is_file = true;
+                if let BufferProvider::None = self.properties.provider {
+                    self.properties.provider = BufferProvider::File;
+                }
+                self.directory_entries = None;
                 self.path.clear();
                 self.path.push(path);
                 true
@@ -1388,9 +3082,26 @@ impl Buffer {
             return Ok(());
         }
 
-        if self.properties.is_file {
-            let file = File::create(&self.path)?;
-            self.content.write(&mut io::BufWriter::new(file))?;
+        if let BufferProvider::Plugin(_) = self.properties.provider {
+            let new_path = new_path || matches!(self.provider_write, Some(w) if w.new_path);
+            self.provider_write = Some(ProviderWrite {
+                new_path,
+                edited: false,
+            });
+            events.enqueue(EditorEvent::BufferProviderWrite {
+                handle: self.handle,
+            });
+            return Ok(());
+        }
+
+        if self.is_directory() {
+            self.apply_directory_changes()?;
+        } else if let BufferProvider::File = self.properties.provider {
+            write_file_atomically(&self.path, backup, |writer| {
+                self.content.write_encoded(writer, self.properties.encoding)
+            })?;
+            self.file_stamp = FileStamp::from_path(&self.path);
+            self.notified_file_stamp = None;
         }
 
         self.needs_save = false;
@@ -1415,13 +3126,53 @@ pub struct InsertProcess {
     pub output_residual_bytes: ResidualStrBytes,
 }
 
+struct BufferProviderEntry {
+    scheme: String,
+    glob: Glob,
+    plugin_handle: PluginHandle,
+}
+
 #[derive(Default)]
 pub struct BufferCollection {
     buffers: Vec<Buffer>,
     insert_processes: Vec<InsertProcess>,
+    providers: Vec<BufferProviderEntry>,
 }
 
 impl BufferCollection {
+    pub fn add_provider(
+        &mut self,
+        plugin_handle: PluginHandle,
+        pattern: &str,
+    ) -> Result<(), InvalidGlobError> {
+        let mut entry = BufferProviderEntry {
+            scheme: String::new(),
+            glob: Glob::default(),
+            plugin_handle,
+        };
+        if pattern.ends_with("://") {
+            entry.scheme.push_str(pattern);
+        } else {
+            entry.glob.compile(pattern)?;
+        }
+        self.providers.push(entry);
+        Ok(())
+    }
+
+    pub fn find_provider(&self, path: &Path) -> Option<PluginHandle> {
+        let path = path.to_str()?;
+        self.providers
+            .iter()
+            .find(|p| {
+                if p.scheme.is_empty() {
+                    p.glob.matches(path)
+                } else {
+                    path.starts_with(&p.scheme)
+                }
+            })
+            .map(|p| p.plugin_handle)
+    }
+
     pub fn add_new(&mut self) -> &mut Buffer {
         let mut handle = None;
         for (i, buffer) in self.buffers.iter_mut().enumerate() {
@@ -1452,6 +3203,13 @@ impl BufferCollection {
         &mut self.buffers[handle.0 as usize]
     }
 
+    // `None` if the buffer was closed in the meantime
+    pub fn try_get_mut(&mut self, handle: BufferHandle) -> Option<&mut Buffer> {
+        self.buffers
+            .get_mut(handle.0 as usize)
+            .filter(|buffer| buffer.alive)
+    }
+
     pub fn find_with_path(&self, buffers_root: &Path, path: &Path) -> Option<BufferHandle> {
         if path.as_os_str().is_empty() {
             return None;
@@ -1808,137 +3566,718 @@ mod tests {
         let mut buffer = buffer_from_str("abd");
         let range = buffer.insert_text(BufferPosition::line_col(0, 2), "ç");
         assert_eq!(
-            BufferRange::between(
-                BufferPosition::line_col(0, 2),
-                BufferPosition::line_col(0, (2 + 'ç'.len_utf8()) as _)
-            ),
-            range
+            BufferRange::between(
+                BufferPosition::line_col(0, 2),
+                BufferPosition::line_col(0, (2 + 'ç'.len_utf8()) as _)
+            ),
+            range
+        );
+    }
+
+    #[test]
+    fn buffer_content_insert_text() {
+        let mut buffer = BufferContent::new();
+
+        assert_eq!(1, buffer.lines().len());
+        assert_eq!("", buffer.to_string());
+
+        buffer.insert_text(BufferPosition::line_col(0, 0), "hold");
+        buffer.insert_text(BufferPosition::line_col(0, 2), "r");
+        buffer.insert_text(BufferPosition::line_col(0, 1), "ello w");
+        assert_eq!(1, buffer.lines().len());
+        assert_eq!("hello world", buffer.to_string());
+
+        buffer.insert_text(BufferPosition::line_col(0, 5), "\n");
+        buffer.insert_text(
+            BufferPosition::line_col(1, 6),
+            " appending more\nand more\nand even more\nlines",
+        );
+        assert_eq!(5, buffer.lines().len());
+        assert_eq!(
+            "hello\n world appending more\nand more\nand even more\nlines",
+            buffer.to_string()
+        );
+
+        let mut buffer = buffer_from_str("this is content");
+        buffer.insert_text(BufferPosition::line_col(0, 8), "some\nmultiline ");
+        assert_eq!(2, buffer.lines().len());
+        assert_eq!("this is some\nmultiline content", buffer.to_string());
+
+        let mut buffer = buffer_from_str("this is content");
+        buffer.insert_text(
+            BufferPosition::line_col(0, 8),
+            "some\nmore\nextensive\nmultiline ",
+        );
+        assert_eq!(4, buffer.lines().len());
+        assert_eq!(
+            "this is some\nmore\nextensive\nmultiline content",
+            buffer.to_string()
+        );
+
+        let mut buffer = buffer_from_str("abc");
+        let range = buffer.insert_text(BufferPosition::line_col(0, 3), "\n");
+        assert_eq!(
+            BufferRange::between(
+                BufferPosition::line_col(0, 3),
+                BufferPosition::line_col(1, 0)
+            ),
+            range
+        );
+    }
+
+    #[test]
+    fn buffer_content_delete_range() {
+        let mut buffer = buffer_from_str("abc");
+        buffer.delete_range(BufferRange::between(
+            BufferPosition::line_col(0, 1),
+            BufferPosition::line_col(0, 1),
+        ));
+        assert_eq!("abc", buffer.to_string());
+        buffer.delete_range(BufferRange::between(
+            BufferPosition::line_col(0, 1),
+            BufferPosition::line_col(0, 2),
+        ));
+        assert_eq!("ac", buffer.to_string());
+
+        let mut buffer = buffer_from_str("this is the initial\ncontent of the buffer");
+
+        assert_eq!(2, buffer.lines().len());
+        assert_eq!(
+            "this is the initial\ncontent of the buffer",
+            buffer.to_string()
+        );
+
+        buffer.delete_range(BufferRange::between(
+            BufferPosition::zero(),
+            BufferPosition::zero(),
+        ));
+        assert_eq!(2, buffer.lines().len());
+        assert_eq!(
+            "this is the initial\ncontent of the buffer",
+            buffer.to_string()
+        );
+
+        buffer.delete_range(BufferRange::between(
+            BufferPosition::line_col(0, 11),
+            BufferPosition::line_col(0, 19),
+        ));
+        assert_eq!(2, buffer.lines().len());
+        assert_eq!("this is the\ncontent of the buffer", buffer.to_string());
+
+        buffer.delete_range(BufferRange::between(
+            BufferPosition::line_col(0, 8),
+            BufferPosition::line_col(1, 15),
+        ));
+        assert_eq!(1, buffer.lines().len());
+        assert_eq!("this is buffer", buffer.to_string());
+
+        let mut buffer = buffer_from_str("this\nbuffer\ncontains\nmultiple\nlines\nyes");
+        assert_eq!(6, buffer.lines().len());
+        buffer.delete_range(BufferRange::between(
+            BufferPosition::line_col(1, 4),
+            BufferPosition::line_col(4, 1),
+        ));
+        assert_eq!("this\nbuffines\nyes", buffer.to_string());
+    }
+
+    #[test]
+    fn buffer_content_delete_lines() {
+        let mut buffer = buffer_from_str("first line\nsecond line\nthird line");
+        assert_eq!(3, buffer.lines().len());
+        buffer.delete_range(BufferRange::between(
+            BufferPosition::line_col(1, 0),
+            BufferPosition::line_col(2, 0),
+        ));
+        assert_eq!("first line\nthird line", buffer.to_string());
+
+        let mut buffer = buffer_from_str("first line\nsecond line\nthird line");
+        assert_eq!(3, buffer.lines().len());
+        buffer.delete_range(BufferRange::between(
+            BufferPosition::line_col(1, 0),
+            BufferPosition::line_col(1, 11),
+        ));
+        assert_eq!("first line\n\nthird line", buffer.to_string());
+    }
+
+    #[test]
+    fn buffer_content_many_lines() {
+        fn assert_lines(content: &BufferContent, expected: &[String]) {
+            let lines = &content.lines;
+            assert_eq!(expected.len(), lines.len());
+            assert!(expected
+                .iter()
+                .enumerate()
+                .all(|(i, l)| lines[i].as_str() == l && lines.get(i).is_some()));
+            assert!(lines.get(expected.len()).is_none());
+            let middle = expected.len() / 2;
+            assert!(lines
+                .range(middle / 2..middle + middle / 2)
+                .map(|l| l.as_str())
+                .eq(expected[middle / 2..middle + middle / 2].iter()));
+            assert_eq!(expected.join("\n"), content.to_string());
+            assert!(lines
+                .iter()
+                .rev()
+                .map(|l| l.as_str())
+                .eq(expected.iter().rev()));
+        }
+
+        let mut expected: Vec<String> = (0..3000).map(|i| i.to_string()).collect();
+        let mut text = expected.join("\n");
+        text.push('\n');
+        let mut content = BufferContent::new();
+        content.read(&mut text.as_bytes()).unwrap();
+        assert_lines(&content, &expected);
+        assert_eq!(
+            Some("2500"),
+            content.lines().iter().nth(2500).map(|l| l.as_str())
+        );
+        assert_eq!(
+            vec!["1023", "1024", "1025"],
+            content
+                .lines()
+                .range(1023..=1025)
+                .map(|l| l.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let block: Vec<String> = (0..2500).map(|i| format!("b{}", i)).collect();
+        let mut block_text = block.join("\n");
+        block_text.push('\n');
+        content.insert_text(BufferPosition::line_col(1000, 0), &block_text);
+        expected.splice(1000..1000, block.iter().cloned());
+        assert_lines(&content, &expected);
+
+        content.delete_range(BufferRange::between(
+            BufferPosition::line_col(500, 1),
+            BufferPosition::line_col(3200, 1),
+        ));
+        let joined = format!("{}{}", &expected[500][..1], &expected[3200][1..]);
+        expected.splice(500..=3200, [joined]);
+        assert_lines(&content, &expected);
+
+        let mut seed = 7usize;
+        for _ in 0..200 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345) % (1 << 31);
+            let line_index = seed % (expected.len() - 1);
+            if seed % 3 == 1 {
+                content.insert_text(BufferPosition::line_col(line_index as _, 0), "x\ny\n");
+                expected.splice(line_index..line_index, ["x".into(), "y".into()]);
+            } else {
+                let line_count = (seed / 7 % 40).min(expected.len() - 1 - line_index);
+                let range = BufferRange::between(
+                    BufferPosition::line_col(line_index as _, 0),
+                    BufferPosition::line_col((line_index + line_count) as _, 0),
+                );
+                content.delete_range(range);
+                expected.drain(line_index..line_index + line_count);
+            }
+        }
+        assert_lines(&content, &expected);
+
+        content.delete_range(BufferRange::between(BufferPosition::zero(), content.end()));
+        assert_lines(&content, &[String::new()]);
+    }
+
+    #[test]
+    fn buffer_content_read_write_line_endings() {
+        fn read_write(text: &[u8]) -> (BufferContent, Vec<u8>) {
+            let mut buffer = BufferContent::new();
+            buffer.read(&mut io::Cursor::new(text)).unwrap();
+            let mut written = Vec::new();
+            buffer.write(&mut written).unwrap();
+            (buffer, written)
+        }
+
+        let (buffer, written) = read_write(b"first\nsecond\n");
+        assert_eq!(LineEnding::Lf, buffer.line_ending());
+        assert!(!buffer.has_mixed_line_endings());
+        assert!(!buffer.has_bom());
+        assert_eq!(b"first\nsecond\n", &written[..]);
+
+        let (buffer, written) = read_write(b"\xef\xbb\xbffirst\r\nsecond\r\n");
+        assert_eq!(LineEnding::CrLf, buffer.line_ending());
+        assert!(buffer.has_bom());
+        assert_eq!("first\nsecond", buffer.to_string());
+        assert_eq!(b"\xef\xbb\xbffirst\r\nsecond\r\n", &written[..]);
+
+        let (mut buffer, written) = read_write(b"first\r\nsecond\nthird\r\n");
+        assert_eq!(LineEnding::CrLf, buffer.line_ending());
+        assert!(buffer.has_mixed_line_endings());
+        assert_eq!(b"first\r\nsecond\nthird\r\n", &written[..]);
+
+        buffer.set_line_ending(LineEnding::Lf);
+        buffer.set_bom(true);
+        let mut written = Vec::new();
+        buffer.write(&mut written).unwrap();
+        assert!(!buffer.has_mixed_line_endings());
+        assert_eq!(b"\xef\xbb\xbffirst\nsecond\nthird\n", &written[..]);
+    }
+
+    #[test]
+    fn buffer_content_read_write_encoded() {
+        let mut buffer = BufferContent::new();
+        let encoding = buffer
+            .read_encoded(
+                &mut &b"\xff\xfea\x00\r\x00\n\x00\xe7\x00\r\x00\n\x00"[..],
+                Encoding::Utf8,
+            )
+            .unwrap();
+        assert_eq!(Encoding::Utf16Le, encoding);
+        assert!(buffer.has_bom());
+        assert_eq!(LineEnding::CrLf, buffer.line_ending());
+        assert_eq!("a\nç", buffer.to_string());
+
+        let mut written = Vec::new();
+        buffer.write_encoded(&mut written, encoding).unwrap();
+        assert_eq!(
+            b"\xff\xfea\x00\r\x00\n\x00\xe7\x00\r\x00\n\x00",
+            &written[..]
+        );
+
+        let encoding = buffer
+            .read_encoded(&mut &b"a\xe7\n"[..], Encoding::Latin1)
+            .unwrap();
+        assert_eq!(Encoding::Latin1, encoding);
+        assert_eq!("aç", buffer.to_string());
+
+        assert!(buffer
+            .read_encoded(&mut &b"a\xe7\n"[..], Encoding::Utf8)
+            .is_err());
+        assert_eq!("", buffer.to_string());
+
+        buffer.insert_text(BufferPosition::zero(), "€");
+        let mut written = Vec::new();
+        assert!(buffer
+            .write_encoded(&mut written, Encoding::Latin1)
+            .is_err());
+    }
+
+    #[test]
+    fn buffer_content_edit_mixed_line_endings() {
+        let mut buffer = BufferContent::new();
+        buffer
+            .read(&mut io::Cursor::new(b"aa\r\nbb\ncc\r\n"))
+            .unwrap();
+
+        buffer.insert_text(BufferPosition::line_col(1, 1), "x\ny\n");
+        assert_eq!("aa\nbx\ny\nb\ncc", buffer.to_string());
+        let mut written = Vec::new();
+        buffer.write(&mut written).unwrap();
+        assert_eq!(b"aa\r\nbx\r\ny\r\nb\ncc\r\n", &written[..]);
+
+        buffer.delete_range(BufferRange::between(
+            BufferPosition::line_col(1, 1),
+            BufferPosition::line_col(3, 1),
+        ));
+        assert_eq!("aa\nb\ncc", buffer.to_string());
+        let mut written = Vec::new();
+        buffer.write(&mut written).unwrap();
+        assert_eq!(b"aa\r\nb\ncc\r\n", &written[..]);
+    }
+
+    #[test]
+    fn buffer_write_to_file_atomically() {
+        let dir = std::env::temp_dir().join(format!("pepper-test-{}", std::process::id()));
+        let _ = fs::create_dir_all(&dir);
+        let path = dir.join("file.txt");
+        let backup_path = dir.join("file.txt~");
+        fs::write(&path, "old content\n").unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        let mut word_database = WordDatabase::new();
+        let mut events = EditorEventQueue::default();
+        let mut buffer = Buffer::new(BufferHandle(0));
+        buffer.properties = BufferProperties::text();
+        buffer.path = path.clone();
+        buffer.insert_text(
+            &mut word_database,
+            BufferPosition::zero(),
+            "new content",
+            &mut events,
+        );
+
+        assert!(buffer.write_to_file(None, true, &mut events).is_ok());
+        assert_eq!("new content\n", fs::read_to_string(&path).unwrap());
+        assert_eq!("old content\n", fs::read_to_string(&backup_path).unwrap());
+        assert_eq!(2, fs::read_dir(&dir).unwrap().count());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(0o640, mode & 0o777);
+        }
+
+        let missing_dir_path = dir.join("missing").join("file.txt");
+        assert!(matches!(
+            buffer.write_to_file(Some(&missing_dir_path), false, &mut events),
+            Err(BufferWriteError::DirectoryNotFound)
+        ));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn buffer_detect_file_changed_on_disk() {
+        let dir = std::env::temp_dir().join(format!("pepper-test-changed-{}", std::process::id()));
+        let _ = fs::create_dir_all(&dir);
+        let path = dir.join("file.txt");
+        fs::write(&path, "content\n").unwrap();
+
+        let mut word_database = WordDatabase::new();
+        let mut events = EditorEventQueue::default();
+        let mut buffer = Buffer::new(BufferHandle(0));
+        buffer.properties = BufferProperties::text();
+        buffer.path = path.clone();
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+
+        assert!(!buffer.file_changed_on_disk());
+        assert!(!buffer.poll_file_change());
+
+        fs::write(&path, "changed content\n").unwrap();
+        assert!(buffer.file_changed_on_disk());
+        assert!(buffer.poll_file_change());
+        assert!(!buffer.poll_file_change());
+
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+        assert!(!buffer.file_changed_on_disk());
+        assert_eq!("changed content", buffer.content().to_string());
+
+        fs::write(&path, "reloaded\r\ncontent\r\n").unwrap();
+        assert!(buffer.poll_file_change());
+        assert!(buffer
+            .reload_from_file(&mut word_database, &mut events)
+            .is_ok());
+        assert!(!buffer.file_changed_on_disk());
+        assert!(!buffer.needs_save());
+        assert_eq!("reloaded\ncontent", buffer.content().to_string());
+        assert_eq!(LineEnding::CrLf, buffer.content().line_ending());
+        assert!(!buffer.content().has_mixed_line_endings());
+
+        assert_eq!(2, buffer.undo(&mut word_database, &mut events).count());
+        assert_eq!("changed content", buffer.content().to_string());
+        assert!(buffer.needs_save());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn buffer_persistent_history() {
+        let dir = std::env::temp_dir().join(format!("pepper-test-undo-{}", std::process::id()));
+        let undo_dir = dir.join("undo");
+        let _ = fs::create_dir_all(&dir);
+        fs::write(dir.join("file.txt"), "content\n").unwrap();
+
+        let mut word_database = WordDatabase::new();
+        let mut events = EditorEventQueue::default();
+        let mut buffer = Buffer::new(BufferHandle(0));
+        buffer.properties = BufferProperties::text();
+        buffer.path = dir.join("file.txt");
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+
+        buffer.insert_text(
+            &mut word_database,
+            BufferPosition::zero(),
+            "new ",
+            &mut events,
+        );
+        assert!(buffer.write_to_file(None, false, &mut events).is_ok());
+        assert!(buffer.save_history_to_file(&dir, &undo_dir).is_ok());
+
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+        assert_eq!(0, buffer.undo(&mut word_database, &mut events).count());
+
+        buffer.load_history_from_file(&dir, &undo_dir);
+        assert_eq!(1, buffer.undo(&mut word_database, &mut events).count());
+        assert_eq!("content", buffer.content().to_string());
+
+        fs::write(dir.join("file.txt"), "changed\n").unwrap();
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+        buffer.load_history_from_file(&dir, &undo_dir);
+        assert_eq!(0, buffer.undo(&mut word_database, &mut events).count());
+
+        let content_text = buffer.content().to_string();
+        assert_eq!(
+            hash_bytes(content_text.as_bytes()),
+            hash_content(buffer.content())
         );
+
+        let _ = fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn buffer_content_insert_text() {
-        let mut buffer = BufferContent::new();
+    fn buffer_swap_file() {
+        let dir = std::env::temp_dir().join(format!("pepper-test-swap-{}", std::process::id()));
+        let recovery_dir = dir.join("recovery");
+        let _ = fs::create_dir_all(&dir);
+        fs::write(dir.join("file.txt"), "content\n").unwrap();
 
-        assert_eq!(1, buffer.lines().len());
-        assert_eq!("", buffer.to_string());
+        let mut word_database = WordDatabase::new();
+        let mut events = EditorEventQueue::default();
+        let mut buffer = Buffer::new(BufferHandle(0));
+        buffer.properties = BufferProperties::text();
+        buffer.path = dir.join("file.txt");
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
 
-        buffer.insert_text(BufferPosition::line_col(0, 0), "hold");
-        buffer.insert_text(BufferPosition::line_col(0, 2), "r");
-        buffer.insert_text(BufferPosition::line_col(0, 1), "ello w");
-        assert_eq!(1, buffer.lines().len());
-        assert_eq!("hello world", buffer.to_string());
+        assert!(buffer.update_swap_file(&dir, &recovery_dir).is_ok());
+        assert_eq!(0, swap_file_paths(&recovery_dir).count());
 
-        buffer.insert_text(BufferPosition::line_col(0, 5), "\n");
         buffer.insert_text(
-            BufferPosition::line_col(1, 6),
-            " appending more\nand more\nand even more\nlines",
+            &mut word_database,
+            BufferPosition::zero(),
+            "new ",
+            &mut events,
         );
-        assert_eq!(5, buffer.lines().len());
+        assert!(buffer.update_swap_file(&dir, &recovery_dir).is_ok());
+        let paths: Vec<_> = swap_file_paths(&recovery_dir).collect();
         assert_eq!(
-            "hello\n world appending more\nand more\nand even more\nlines",
-            buffer.to_string()
+            vec![swap_file_path(&dir, &recovery_dir, &buffer.path)],
+            paths
         );
-
-        let mut buffer = buffer_from_str("this is content");
-        buffer.insert_text(BufferPosition::line_col(0, 8), "some\nmultiline ");
-        assert_eq!(2, buffer.lines().len());
-        assert_eq!("this is some\nmultiline content", buffer.to_string());
-
-        let mut buffer = buffer_from_str("this is content");
+        assert!(!buffer.has_foreign_swap_file(&dir, &recovery_dir));
+
+        let swap_file = SwapFile::read(&paths[0]).unwrap();
+        assert_eq!(std::process::id(), swap_file.pid);
+        assert_eq!(dir.join("file.txt"), swap_file.buffer_path);
+        assert_eq!("new content", swap_file.text);
+        assert_eq!(0, recoverable_swap_file_paths(&recovery_dir).count());
+
+        let mut bytes = fs::read(&paths[0]).unwrap();
+        bytes[4..8].copy_from_slice(&(i32::MAX as u32).to_le_bytes());
+        fs::write(&paths[0], bytes).unwrap();
+        assert_eq!(1, recoverable_swap_file_paths(&recovery_dir).count());
+
+        let mut other = Buffer::new(BufferHandle(1));
+        other.properties = BufferProperties::text();
+        other.path = dir.join("file.txt");
+        assert!(other
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+        assert!(other.has_foreign_swap_file(&dir, &recovery_dir));
+        other.recover_from_swap_file(&mut word_database, &swap_file, &mut events);
+        assert_eq!("new content", other.content().to_string());
+        assert!(other.needs_save());
+        assert_eq!(2, other.undo(&mut word_database, &mut events).count());
+        assert_eq!("content", other.content().to_string());
+
+        buffer.set_large_file(true, false, &mut word_database);
+        assert!(buffer.update_swap_file(&dir, &recovery_dir).is_ok());
+        assert_eq!(0, swap_file_paths(&recovery_dir).count());
         buffer.insert_text(
-            BufferPosition::line_col(0, 8),
-            "some\nmore\nextensive\nmultiline ",
-        );
-        assert_eq!(4, buffer.lines().len());
-        assert_eq!(
-            "this is some\nmore\nextensive\nmultiline content",
-            buffer.to_string()
+            &mut word_database,
+            BufferPosition::zero(),
+            "large ",
+            &mut events,
         );
+        assert!(buffer.update_swap_file(&dir, &recovery_dir).is_ok());
+        assert_eq!(0, swap_file_paths(&recovery_dir).count());
+        buffer.set_large_file(false, false, &mut word_database);
 
-        let mut buffer = buffer_from_str("abc");
-        let range = buffer.insert_text(BufferPosition::line_col(0, 3), "\n");
-        assert_eq!(
-            BufferRange::between(
-                BufferPosition::line_col(0, 3),
-                BufferPosition::line_col(1, 0)
-            ),
-            range
-        );
+        assert!(buffer.write_to_file(None, false, &mut events).is_ok());
+        assert!(buffer.update_swap_file(&dir, &recovery_dir).is_ok());
+        assert_eq!(0, swap_file_paths(&recovery_dir).count());
+
+        let _ = fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn buffer_content_delete_range() {
-        let mut buffer = buffer_from_str("abc");
-        buffer.delete_range(BufferRange::between(
-            BufferPosition::line_col(0, 1),
-            BufferPosition::line_col(0, 1),
-        ));
-        assert_eq!("abc", buffer.to_string());
-        buffer.delete_range(BufferRange::between(
-            BufferPosition::line_col(0, 1),
-            BufferPosition::line_col(0, 2),
-        ));
-        assert_eq!("ac", buffer.to_string());
+    fn buffer_large_file_mode() {
+        let mut word_database = WordDatabase::new();
+        let mut events = EditorEventQueue::default();
+        let mut buffer = Buffer::new(BufferHandle(0));
+        buffer.properties = BufferProperties::text();
+        buffer.insert_text(
+            &mut word_database,
+            BufferPosition::zero(),
+            "first\nsecond",
+            &mut events,
+        );
+        buffer.commit_edits();
+        assert_eq!(2, word_database.word_indices().count());
 
-        let mut buffer = buffer_from_str("this is the initial\ncontent of the buffer");
+        buffer.set_large_file(true, false, &mut word_database);
+        assert!(buffer.is_large_file());
+        assert!(!buffer.properties.word_database_enabled);
+        assert!(!buffer.properties.syntax_enabled);
+        assert!(!buffer.properties.history_enabled);
+        assert_eq!(0, word_database.word_indices().count());
+        assert_eq!(0, buffer.history().group_count());
 
-        assert_eq!(2, buffer.lines().len());
-        assert_eq!(
-            "this is the initial\ncontent of the buffer",
-            buffer.to_string()
+        buffer.insert_text(
+            &mut word_database,
+            BufferPosition::line_col(1, 0),
+            "third\n",
+            &mut events,
         );
+        assert_eq!(0, word_database.word_indices().count());
+        assert!(buffer.highlighted().line_tokens(1).is_empty());
 
-        buffer.delete_range(BufferRange::between(
-            BufferPosition::zero(),
-            BufferPosition::zero(),
-        ));
-        assert_eq!(2, buffer.lines().len());
-        assert_eq!(
-            "this is the initial\ncontent of the buffer",
-            buffer.to_string()
-        );
+        buffer.set_large_file(false, false, &mut word_database);
+        assert!(!buffer.is_large_file());
+        assert!(buffer.properties.word_database_enabled);
+        assert!(buffer.properties.syntax_enabled);
+        assert!(buffer.properties.history_enabled);
+        assert_eq!(3, word_database.word_indices().count());
 
-        buffer.delete_range(BufferRange::between(
-            BufferPosition::line_col(0, 11),
-            BufferPosition::line_col(0, 19),
-        ));
-        assert_eq!(2, buffer.lines().len());
-        assert_eq!("this is the\ncontent of the buffer", buffer.to_string());
+        buffer.set_large_file(true, true, &mut word_database);
+        assert!(buffer.properties.history_enabled);
+        buffer.set_large_file(false, true, &mut word_database);
+        assert_eq!(3, word_database.word_indices().count());
 
-        buffer.delete_range(BufferRange::between(
-            BufferPosition::line_col(0, 8),
-            BufferPosition::line_col(1, 15),
-        ));
-        assert_eq!(1, buffer.lines().len());
-        assert_eq!("this is buffer", buffer.to_string());
+        let mut word_database = WordDatabase::new();
+        buffer.properties = BufferProperties::scratch();
+        buffer.set_large_file(true, true, &mut word_database);
+        assert!(!buffer.properties.syntax_enabled);
+        assert!(!buffer.properties.history_enabled);
+        buffer.set_large_file(false, true, &mut word_database);
+        assert!(!buffer.properties.word_database_enabled);
+        assert!(buffer.properties.syntax_enabled);
+        assert!(!buffer.properties.history_enabled);
+        assert_eq!(0, word_database.word_indices().count());
+    }
 
-        let mut buffer = buffer_from_str("this\nbuffer\ncontains\nmultiple\nlines\nyes");
-        assert_eq!(6, buffer.lines().len());
-        buffer.delete_range(BufferRange::between(
-            BufferPosition::line_col(1, 4),
-            BufferPosition::line_col(4, 1),
-        ));
-        assert_eq!("this\nbuffines\nyes", buffer.to_string());
+    #[test]
+    fn buffer_reopen_with_hex_encoding() {
+        let dir = std::env::temp_dir().join(format!("pepper-test-hex-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        fs::write(&path, "first second").unwrap();
+
+        let mut word_database = WordDatabase::new();
+        let mut events = EditorEventQueue::default();
+        let mut buffer = Buffer::new(BufferHandle(0));
+        buffer.properties = BufferProperties::text();
+        buffer.path = path;
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+        assert_eq!(2, word_database.word_indices().count());
+
+        assert!(buffer
+            .reopen_with_encoding(Encoding::Hex, &mut word_database, &mut events)
+            .is_ok());
+        assert_eq!(Encoding::Hex, buffer.properties.encoding);
+        assert!(!buffer.properties.word_database_enabled);
+        assert!(!buffer.properties.syntax_enabled);
+        assert_eq!(0, word_database.word_indices().count());
+
+        assert!(buffer
+            .reopen_with_encoding(Encoding::Utf8, &mut word_database, &mut events)
+            .is_ok());
+        assert_eq!(Encoding::Utf8, buffer.properties.encoding);
+        assert!(buffer.properties.word_database_enabled);
+        assert!(buffer.properties.syntax_enabled);
+        assert_eq!(2, word_database.word_indices().count());
+        assert_eq!("first second", buffer.content().to_string());
+
+        let _ = fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn buffer_content_delete_lines() {
-        let mut buffer = buffer_from_str("first line\nsecond line\nthird line");
-        assert_eq!(3, buffer.lines().len());
-        buffer.delete_range(BufferRange::between(
+    fn buffer_directory() {
+        let dir = std::env::temp_dir().join(format!("pepper-test-dir-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let mut word_database = WordDatabase::new();
+        let mut events = EditorEventQueue::default();
+        let mut buffer = Buffer::new(BufferHandle(0));
+        buffer.properties = BufferProperties::text();
+        buffer.path = dir.clone();
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+        assert!(buffer.is_directory());
+        assert_eq!("sub/\na.txt\nb.txt", buffer.content().to_string());
+
+        let range = BufferRange::between(
             BufferPosition::line_col(1, 0),
             BufferPosition::line_col(2, 0),
-        ));
-        assert_eq!("first line\nthird line", buffer.to_string());
+        );
+        buffer.delete_range(&mut word_database, range, &mut events);
+        buffer.insert_text(
+            &mut word_database,
+            BufferPosition::line_col(1, 0),
+            "c",
+            &mut events,
+        );
+        buffer.insert_text(
+            &mut word_database,
+            BufferPosition::line_col(1, 6),
+            "\nnew/",
+            &mut events,
+        );
+        assert_eq!("sub/\ncb.txt\nnew/", buffer.content().to_string());
 
-        let mut buffer = buffer_from_str("first line\nsecond line\nthird line");
-        assert_eq!(3, buffer.lines().len());
-        buffer.delete_range(BufferRange::between(
+        let mut changes = Vec::new();
+        buffer.directory_changes(&mut changes);
+        assert_eq!(
+            vec![
+                DirectoryChange::Delete("b.txt"),
+                DirectoryChange::Rename {
+                    from: "a.txt",
+                    to: "cb.txt"
+                },
+                DirectoryChange::Create("new/"),
+            ],
+            changes
+        );
+
+        assert!(buffer.write_to_file(None, false, &mut events).is_ok());
+        assert!(!buffer.needs_save());
+        assert_eq!("a", fs::read_to_string(dir.join("cb.txt")).unwrap());
+        assert!(!dir.join("a.txt").exists());
+        assert!(!dir.join("b.txt").exists());
+        assert!(dir.join("new").is_dir());
+
+        let mut changes = Vec::new();
+        buffer.directory_changes(&mut changes);
+        assert!(changes.is_empty());
+
+        fs::write(dir.join("x.txt"), "x").unwrap();
+        let range = BufferRange::between(
+            BufferPosition::line_col(0, 0),
             BufferPosition::line_col(1, 0),
-            BufferPosition::line_col(1, 11),
+        );
+        buffer.delete_range(&mut word_database, range, &mut events);
+        buffer.insert_text(
+            &mut word_database,
+            BufferPosition::line_col(1, 4),
+            "\nx.txt",
+            &mut events,
+        );
+        assert_eq!("cb.txt\nnew/\nx.txt", buffer.content().to_string());
+        assert!(matches!(
+            buffer.write_to_file(None, false, &mut events),
+            Err(BufferWriteError::EntryAlreadyExists(_))
         ));
-        assert_eq!("first line\n\nthird line", buffer.to_string());
+        assert!(!dir.join("sub").exists());
+        let mut changes = Vec::new();
+        buffer.directory_changes(&mut changes);
+        assert_eq!(vec![DirectoryChange::Create("x.txt")], changes);
+
+        let _ = fs::remove_dir_all(&dir);
     }
 
     #[test]
@@ -2147,7 +4486,7 @@ mod tests {
     #[test]
     fn buffer_display_len() {
         fn len(buffer: &BufferContent, line: usize) -> usize {
-            buffer.line_display_lens()[line].total_len(4)
+            buffer.lines()[line].display_len().total_len(4)
         }
 
         let mut buffer = buffer_from_str("abc\tdef");
@@ -2180,4 +4519,298 @@ mod tests {
         assert_eq!(6, len(&buffer, 1));
         assert_eq!(3, len(&buffer, 2));
     }
+
+    #[test]
+    fn buffer_provider() {
+        let mut buffers = BufferCollection::default();
+        assert!(buffers.add_provider(PluginHandle(0), "remote://").is_ok());
+        assert!(buffers.add_provider(PluginHandle(1), "**/*.zip").is_ok());
+        assert_eq!(
+            Some(PluginHandle(0)),
+            buffers.find_provider(Path::new("remote://host/file.txt"))
+        );
+        assert_eq!(
+            Some(PluginHandle(1)),
+            buffers.find_provider(Path::new("dir/archive.zip"))
+        );
+        assert_eq!(None, buffers.find_provider(Path::new("dir/file.txt")));
+
+        let mut word_database = WordDatabase::new();
+        let mut events = EditorEventQueue::default();
+        let mut buffer = Buffer::new(BufferHandle(0));
+        buffer.properties = BufferProperties::text();
+        buffer.properties.provider = BufferProvider::Plugin(PluginHandle(0));
+        buffer.path = "remote://host/file.txt".into();
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+        assert_eq!(Some(PluginHandle(0)), buffer.take_provider_read());
+        assert_eq!(None, buffer.take_provider_read());
+
+        assert!(buffer
+            .provide_content(&mut word_database, &mut &b"first\nsecond"[..], &mut events)
+            .is_ok());
+        assert_eq!("first\nsecond", buffer.content().to_string());
+        assert!(!buffer.needs_save());
+        assert_eq!(0, buffer.history().group_count());
+
+        buffer.insert_text(&mut word_database, BufferPosition::zero(), "x", &mut events);
+        assert!(buffer.write_to_file(None, false, &mut events).is_ok());
+        assert!(buffer.needs_save());
+        buffer.finish_provider_write(false, &mut events);
+        assert!(buffer.needs_save());
+
+        assert!(buffer.write_to_file(None, false, &mut events).is_ok());
+        buffer.insert_text(&mut word_database, BufferPosition::zero(), "y", &mut events);
+        buffer.finish_provider_write(true, &mut events);
+        assert!(buffer.needs_save());
+
+        assert!(buffer.write_to_file(None, false, &mut events).is_ok());
+        buffer.finish_provider_write(true, &mut events);
+        assert!(!buffer.needs_save());
+    }
+
+    #[test]
+    fn buffer_read_only_file() {
+        let dir = std::env::temp_dir().join(format!("pepper-test-ro-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        fs::write(&path, "content").unwrap();
+
+        let mut word_database = WordDatabase::new();
+        let mut events = EditorEventQueue::default();
+        let mut buffer = Buffer::new(BufferHandle(0));
+        buffer.properties = BufferProperties::text();
+        buffer.path = path.clone();
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+        assert!(!buffer.properties.read_only);
+
+        // permissions do not stop root from writing so only check detection as other users
+        #[cfg(unix)]
+        let is_root = unsafe { libc::geteuid() } == 0;
+        #[cfg(not(unix))]
+        let is_root = false;
+        if !is_root {
+            let previous_permissions = fs::metadata(&path).unwrap().permissions();
+            let mut permissions = previous_permissions.clone();
+            permissions.set_readonly(true);
+            fs::set_permissions(&path, permissions).unwrap();
+            assert!(buffer
+                .read_from_file(&mut word_database, &mut events)
+                .is_ok());
+            assert!(buffer.properties.read_only);
+            assert_eq!("content", buffer.content().to_string());
+
+            fs::set_permissions(&path, previous_permissions).unwrap();
+            assert!(buffer
+                .read_from_file(&mut word_database, &mut events)
+                .is_ok());
+            assert!(!buffer.properties.read_only);
+        }
+
+        buffer.properties.read_only = true;
+        assert!(buffer
+            .read_from_file(&mut word_database, &mut events)
+            .is_ok());
+        assert!(buffer.properties.read_only);
+        let _ = fs::remove_dir_all(&dir);
+
+        buffer.properties.read_only = true;
+        events.flip();
+        let range =
+            buffer.insert_text(&mut word_database, BufferPosition::zero(), "x", &mut events);
+        assert_eq!(BufferRange::zero(), range);
+        let range = BufferRange::between(BufferPosition::zero(), BufferPosition::line_col(0, 3));
+        buffer.delete_range(&mut word_database, range, &mut events);
+        assert_eq!("content", buffer.content().to_string());
+        assert!(!buffer.needs_save());
+
+        events.flip();
+        let mut events_iter = crate::events::EditorEventIter::new();
+        assert!(matches!(
+            events_iter.next(&events),
+            Some(EditorEvent::BufferEditRejected { .. })
+        ));
+
+        buffer.properties.read_only = false;
+        buffer.insert_text(&mut word_database, BufferPosition::zero(), "x", &mut events);
+        assert_eq!("xcontent", buffer.content().to_string());
+        assert_eq!(1, buffer.undo(&mut word_database, &mut events).count());
+        buffer.properties.read_only = true;
+        assert_eq!(0, buffer.redo(&mut word_database, &mut events).count());
+        assert!(buffer.history().current_group().is_none());
+        assert_eq!("content", buffer.content().to_string());
+        buffer.properties.read_only = false;
+        assert_eq!(1, buffer.redo(&mut word_database, &mut events).count());
+        assert_eq!("xcontent", buffer.content().to_string());
+    }
+
+    #[test]
+    fn buffer_content_find_surround_pair_at() {
+        fn pair_at(
+            buffer: &BufferContent,
+            position: BufferPosition,
+            pair: SurroundPair,
+        ) -> Option<((usize, usize), (usize, usize))> {
+            let (open, close) = buffer.find_surround_pair_at(position, pair)?;
+            let position = |p: BufferPosition| (p.line_index as _, p.column_byte_index as _);
+            assert_eq!(open.from.line_index, open.to.line_index);
+            assert_eq!(close.from.line_index, close.to.line_index);
+            Some((position(open.from), position(close.to)))
+        }
+
+        let mut buffer = BufferContent::new();
+        buffer.insert_text(BufferPosition::zero(), "a(b [c] d)\n\"e\"");
+        let paren = SurroundPair::Balanced('(', ')');
+        let bracket = SurroundPair::Balanced('[', ']');
+        let quote = SurroundPair::Delimiter('"');
+
+        assert_eq!(
+            Some(((0, 1), (0, 10))),
+            pair_at(&buffer, BufferPosition::line_col(0, 2), paren)
+        );
+        assert_eq!(
+            Some(((0, 1), (0, 10))),
+            pair_at(&buffer, BufferPosition::line_col(0, 5), paren)
+        );
+        assert_eq!(
+            Some(((0, 4), (0, 7))),
+            pair_at(&buffer, BufferPosition::line_col(0, 5), bracket)
+        );
+        assert_eq!(
+            None,
+            pair_at(&buffer, BufferPosition::line_col(0, 2), bracket)
+        );
+        assert_eq!(
+            Some(((1, 0), (1, 3))),
+            pair_at(&buffer, BufferPosition::line_col(1, 1), quote)
+        );
+
+        let mut buffer = BufferContent::new();
+        buffer.insert_text(
+            BufferPosition::zero(),
+            "<div class=\"a\">\n<p>x</p><br>\n<img/>y</div>",
+        );
+        assert_eq!(
+            Some(((1, 0), (1, 8))),
+            pair_at(&buffer, BufferPosition::line_col(1, 3), SurroundPair::Tag)
+        );
+        assert_eq!(
+            Some(((1, 0), (1, 8))),
+            pair_at(&buffer, BufferPosition::line_col(1, 1), SurroundPair::Tag)
+        );
+        assert_eq!(
+            Some(((1, 0), (1, 8))),
+            pair_at(&buffer, BufferPosition::line_col(1, 6), SurroundPair::Tag)
+        );
+        assert_eq!(
+            Some(((0, 0), (2, 13))),
+            pair_at(&buffer, BufferPosition::line_col(2, 6), SurroundPair::Tag)
+        );
+        assert_eq!(
+            Some(((0, 0), (2, 13))),
+            pair_at(&buffer, BufferPosition::line_col(1, 10), SurroundPair::Tag)
+        );
+        assert_eq!(
+            None,
+            pair_at(&buffer, BufferPosition::line_col(0, 0), SurroundPair::Tag)
+        );
+    }
+
+    #[test]
+    fn buffer_content_find_text_objects() {
+        fn lines(range: BufferRange) -> (BufferPositionIndex, BufferPositionIndex) {
+            assert_eq!(0, range.from.column_byte_index);
+            (range.from.line_index, range.to.line_index)
+        }
+
+        let mut buffer = BufferContent::new();
+        buffer.insert_text(
+            BufferPosition::zero(),
+            "def f():\n    a\n\n    if b:\n        c\n    d\ne",
+        );
+        assert_eq!((1, 5), lines(buffer.find_indentation_block_at(2, 4, false)));
+        assert_eq!((0, 5), lines(buffer.find_indentation_block_at(3, 4, true)));
+        assert_eq!((4, 4), lines(buffer.find_indentation_block_at(4, 4, false)));
+        assert_eq!((3, 4), lines(buffer.find_indentation_block_at(4, 4, true)));
+        assert_eq!((0, 6), lines(buffer.find_indentation_block_at(6, 4, false)));
+
+        assert_eq!((0, 1), lines(buffer.find_paragraph_at(1, false)));
+        assert_eq!((0, 2), lines(buffer.find_paragraph_at(0, true)));
+        assert_eq!((2, 2), lines(buffer.find_paragraph_at(2, false)));
+        assert_eq!((3, 6), lines(buffer.find_paragraph_at(5, false)));
+        assert_eq!((2, 6), lines(buffer.find_paragraph_at(5, true)));
+
+        fn argument_at(
+            buffer: &BufferContent,
+            column_index: BufferPositionIndex,
+            include_separator: bool,
+        ) -> Option<String> {
+            let position = BufferPosition::line_col(0, column_index);
+            let range = buffer.find_argument_at(position, include_separator)?;
+            Some(buffer.text_range(range).collect())
+        }
+
+        let mut buffer = BufferContent::new();
+        buffer.insert_text(BufferPosition::zero(), "f(a, g(b, c), \"d, e\", ',')");
+        assert_eq!(Some("a".into()), argument_at(&buffer, 2, false));
+        assert_eq!(Some("a, ".into()), argument_at(&buffer, 2, true));
+        assert_eq!(Some("g(b, c)".into()), argument_at(&buffer, 5, false));
+        assert_eq!(Some("c".into()), argument_at(&buffer, 10, false));
+        assert_eq!(Some(", c".into()), argument_at(&buffer, 10, true));
+        assert_eq!(Some("\"d, e\"".into()), argument_at(&buffer, 16, false));
+        assert_eq!(Some("','".into()), argument_at(&buffer, 24, false));
+        assert_eq!(Some(", ','".into()), argument_at(&buffer, 24, true));
+        assert_eq!(None, argument_at(&buffer, 0, false));
+
+        let mut buffer = BufferContent::new();
+        buffer.insert_text(BufferPosition::zero(), "f(\n    a,\n    b,\n)");
+        let range = buffer.find_argument_at(BufferPosition::line_col(2, 4), false);
+        assert_eq!(
+            Some(BufferRange::between(
+                BufferPosition::line_col(2, 4),
+                BufferPosition::line_col(2, 5)
+            )),
+            range
+        );
+    }
+
+    #[test]
+    fn test_find_number_at() {
+        fn add(text: &str, index: usize, delta: i64) -> Option<String> {
+            let number = find_number_at(text, index)?;
+            let mut result = String::new();
+            result.push_str(&text[..number.from]);
+            number.write_added(delta, &mut result);
+            result.push_str(&text[number.to..]);
+            Some(result)
+        }
+
+        assert_eq!(None, add("abc", 0, 1));
+        assert_eq!(Some("a 2".into()), add("a 1", 0, 1));
+        assert_eq!(Some("a 1 3".into()), add("a 1 2", 3, 1));
+        assert_eq!(Some("x10".into()), add("x9", 0, 1));
+        assert_eq!(Some("-1".into()), add("1", 0, -2));
+        assert_eq!(Some("(-3)".into()), add("(-5)", 2, 2));
+        assert_eq!(Some("5-2".into()), add("5-3", 2, -1));
+        assert_eq!(Some("010".into()), add("009", 0, 1));
+        assert_eq!(Some("0x0a".into()), add("0x09", 3, 1));
+        assert_eq!(Some("0xFF".into()), add("0x0F", 0, 240));
+        assert_eq!(Some("0b0110".into()), add("0b0101", 1, 1));
+        assert_eq!(Some("0xffffffffffffffff".into()), add("0x00", 0, -1));
+        assert_eq!(
+            Some("0x0000000000000000002".into()),
+            add("0x0000000000000000001", 0, 1)
+        );
+        assert_eq!(None, add("9223372036854775808", 0, 1));
+        assert_eq!(
+            Some("-9223372036854775807".into()),
+            add("-9223372036854775808", 0, 1)
+        );
+        assert_eq!(None, add("0x10000000000000000", 0, 1));
+        assert_eq!(None, add(&format!("0b1{}", "0".repeat(64)), 0, 1));
+    }
 }
@@ -1,6 +1,14 @@
-use std::ops::Range;
-
-use crate::buffer_position::{BufferPosition, BufferRange};
+use std::{
+    fmt,
+    ops::Range,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    buffer_position::{BufferPosition, BufferRange},
+    serialization::{DeserializeError, Deserializer, Serialize, Serializer},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EditKind {
@@ -36,15 +44,70 @@ impl EditInternal {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryTravel {
+    Groups(usize),
+    Time(Duration),
+}
+impl fmt::Display for HistoryTravel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Groups(count) => write!(f, "{}", count),
+            Self::Time(duration) => {
+                let secs = duration.as_secs();
+                if secs < 60 {
+                    write!(f, "{}s", secs)
+                } else if secs < 60 * 60 {
+                    write!(f, "{}m", secs / 60)
+                } else if secs < 24 * 60 * 60 {
+                    write!(f, "{}h", secs / (60 * 60))
+                } else {
+                    write!(f, "{}d", secs / (24 * 60 * 60))
+                }
+            }
+        }
+    }
+}
+impl FromStr for HistoryTravel {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (count, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+            Some(i) => s.split_at(i),
+            None => (s, ""),
+        };
+        let count: u64 = count.parse().map_err(|_| ())?;
+        let unit_secs = match unit {
+            "" => return Ok(Self::Groups(count as _)),
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => return Err(()),
+        };
+        Ok(Self::Time(Duration::from_secs(
+            count.saturating_mul(unit_secs),
+        )))
+    }
+}
+
+struct HistoryGroup {
+    edit_range: Range<usize>,
+    parent: Option<usize>,
+    last_child: Option<usize>,
+    time: SystemTime,
+}
+
 enum HistoryState {
-    IterIndex { group_index: usize },
+    Idle,
     InsertGroup { edit_index: usize },
 }
 
 pub struct BufferHistory {
     texts: String,
     edits: Vec<EditInternal>,
-    group_ranges: Vec<Range<usize>>,
+    groups: Vec<HistoryGroup>,
+    current_group: Option<usize>,
+    root_last_child: Option<usize>,
     state: HistoryState,
 }
 
@@ -53,32 +116,159 @@ impl BufferHistory {
         Self {
             texts: String::new(),
             edits: Vec::new(),
-            group_ranges: Vec::new(),
-            state: HistoryState::IterIndex { group_index: 0 },
+            groups: Vec::new(),
+            current_group: None,
+            root_last_child: None,
+            state: HistoryState::Idle,
         }
     }
 
     pub fn clear(&mut self) {
         self.texts.clear();
         self.edits.clear();
-        self.group_ranges.clear();
-        self.state = HistoryState::IterIndex { group_index: 0 };
+        self.groups.clear();
+        self.current_group = None;
+        self.root_last_child = None;
+        self.state = HistoryState::Idle;
+    }
+
+    /// Index of the last applied group or `None` when at the original state.
+    /// Pending edits only become a group after `commit_edits`.
+    pub fn current_group(&self) -> Option<usize> {
+        self.current_group
+    }
+
+    pub fn group_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn group_time(&self, group_index: usize) -> SystemTime {
+        self.groups[group_index].time
+    }
+
+    pub fn group_depth(&self, mut group_index: Option<usize>) -> usize {
+        let mut depth = 0;
+        while let Some(index) = group_index {
+            depth += 1;
+            group_index = self.groups[index].parent;
+        }
+        depth
+    }
+
+    /// Groups without children, in the order they were created.
+    /// Each one is the tip of a branch in the history tree.
+    pub fn branches(&self) -> impl '_ + Iterator<Item = usize> {
+        self.groups
+            .iter()
+            .enumerate()
+            .filter(|(_, g)| g.last_child.is_none())
+            .map(|(i, _)| i)
+    }
+
+    /// Tip of the branch that redo currently follows.
+    pub fn current_branch(&self) -> Option<usize> {
+        let mut branch = self.current_group;
+        let mut child = match self.current_group {
+            Some(index) => self.groups[index].last_child,
+            None => self.root_last_child,
+        };
+        while let Some(index) = child {
+            branch = Some(index);
+            child = self.groups[index].last_child;
+        }
+        branch
+    }
+
+    /// Group reached by moving back in creation order, either by a number of groups
+    /// or to the last group created at least that long before the current one.
+    pub fn earlier_group(&self, travel: HistoryTravel) -> Option<usize> {
+        let current = self.current_group?;
+        match travel {
+            HistoryTravel::Groups(count) => current.checked_sub(count),
+            HistoryTravel::Time(duration) => {
+                let time = self.groups[current].time.checked_sub(duration)?;
+                self.groups[..=current].iter().rposition(|g| g.time <= time)
+            }
+        }
+    }
+
+    /// Group reached by moving forward in creation order, either by a number of groups
+    /// or to the last group created at most that long after the current one.
+    pub fn later_group(&self, travel: HistoryTravel) -> Option<usize> {
+        let start = match self.current_group {
+            Some(index) => index,
+            None if self.groups.is_empty() => return None,
+            None => match travel {
+                HistoryTravel::Groups(0) => return None,
+                HistoryTravel::Groups(count) => {
+                    return Some((count - 1).min(self.groups.len() - 1))
+                }
+                HistoryTravel::Time(_) => 0,
+            },
+        };
+        match travel {
+            HistoryTravel::Groups(count) => {
+                Some(start.saturating_add(count).min(self.groups.len() - 1))
+            }
+            HistoryTravel::Time(duration) => {
+                let time = match self.groups[start].time.checked_add(duration) {
+                    Some(time) => time,
+                    None => return Some(self.groups.len() - 1),
+                };
+                let count = self.groups[start + 1..]
+                    .iter()
+                    .take_while(|g| g.time <= time)
+                    .count();
+                Some(start + count)
+            }
+        }
+    }
+
+    /// Makes redo follow the path to `group_index` and returns how many undos
+    /// followed by how many redos take the buffer from its current state there.
+    pub fn jump_path(&mut self, group_index: Option<usize>) -> (usize, usize) {
+        self.commit_edits();
+
+        let mut from = self.current_group;
+        let mut to = group_index;
+        let mut from_depth = self.group_depth(from);
+        let mut to_depth = self.group_depth(to);
+        let mut undo_count = 0;
+        let mut redo_count = 0;
+
+        while from_depth > to_depth {
+            from = from.and_then(|i| self.groups[i].parent);
+            from_depth -= 1;
+            undo_count += 1;
+        }
+        while to_depth > from_depth || from != to {
+            if to_depth == from_depth {
+                from = from.and_then(|i| self.groups[i].parent);
+                from_depth -= 1;
+                undo_count += 1;
+            }
+            let child = to;
+            to = to.and_then(|i| self.groups[i].parent);
+            *self.last_child_mut(to) = child;
+            to_depth -= 1;
+            redo_count += 1;
+        }
+
+        (undo_count, redo_count)
+    }
+
+    fn last_child_mut(&mut self, group_index: Option<usize>) -> &mut Option<usize> {
+        match group_index {
+            Some(index) => &mut self.groups[index].last_child,
+            None => &mut self.root_last_child,
+        }
     }
 
     pub fn add_edit(&mut self, edit: Edit) {
         let current_group_start = match self.state {
-            HistoryState::IterIndex { group_index } => {
-                let edit_index = match self.group_ranges.get(group_index) {
-                    Some(range) => range.start,
-                    None => self.edits.len(),
-                };
-                self.edits.truncate(edit_index);
-                match self.edits.last() {
-                    Some(edit) => self.texts.truncate(edit.text_range.end as _),
-                    None => self.texts.clear(),
-                }
+            HistoryState::Idle => {
+                let edit_index = self.edits.len();
                 self.state = HistoryState::InsertGroup { edit_index };
-                self.group_ranges.truncate(group_index);
                 edit_index
             }
             HistoryState::InsertGroup { edit_index } => edit_index,
@@ -364,31 +554,45 @@ impl BufferHistory {
     }
 
     pub fn commit_edits(&mut self) {
+        self.commit_edits_at(SystemTime::now());
+    }
+
+    fn commit_edits_at(&mut self, time: SystemTime) {
         if let HistoryState::InsertGroup { edit_index } = self.state {
-            self.group_ranges.push(edit_index..self.edits.len());
-            self.state = HistoryState::IterIndex {
-                group_index: self.group_ranges.len(),
-            };
+            let group_index = self.groups.len();
+            *self.last_child_mut(self.current_group) = Some(group_index);
+            self.groups.push(HistoryGroup {
+                edit_range: edit_index..self.edits.len(),
+                parent: self.current_group,
+                last_child: None,
+                time,
+            });
+            self.current_group = Some(group_index);
+            self.state = HistoryState::Idle;
         }
     }
 
     pub fn undo_edits(
         &mut self,
     ) -> impl Clone + ExactSizeIterator<Item = Edit> + DoubleEndedIterator<Item = Edit> {
+        self.undo_edits_if(true)
+    }
+
+    /// Same as `undo_edits` but yields no edits and stays at the current group unless `apply`.
+    pub fn undo_edits_if(
+        &mut self,
+        apply: bool,
+    ) -> impl Clone + ExactSizeIterator<Item = Edit<'_>> + DoubleEndedIterator<Item = Edit<'_>>
+    {
         self.commit_edits();
 
-        let range = match self.state {
-            HistoryState::IterIndex {
-                ref mut group_index,
-            } => {
-                if *group_index > 0 {
-                    *group_index -= 1;
-                    self.group_ranges[*group_index].clone()
-                } else {
-                    0..0
-                }
+        let range = match self.current_group {
+            Some(index) if apply => {
+                let group = &self.groups[index];
+                self.current_group = group.parent;
+                group.edit_range.clone()
             }
-            _ => unreachable!(),
+            _ => 0..0,
         };
 
         let texts = &self.texts;
@@ -405,21 +609,27 @@ impl BufferHistory {
     pub fn redo_edits(
         &mut self,
     ) -> impl Clone + ExactSizeIterator<Item = Edit> + DoubleEndedIterator<Item = Edit> {
+        self.redo_edits_if(true)
+    }
+
+    /// Same as `redo_edits` but yields no edits and stays at the current group unless `apply`.
+    pub fn redo_edits_if(
+        &mut self,
+        apply: bool,
+    ) -> impl Clone + ExactSizeIterator<Item = Edit<'_>> + DoubleEndedIterator<Item = Edit<'_>>
+    {
         self.commit_edits();
 
-        let range = match self.state {
-            HistoryState::IterIndex {
-                ref mut group_index,
-            } => {
-                if *group_index < self.group_ranges.len() {
-                    let range = self.group_ranges[*group_index].clone();
-                    *group_index += 1;
-                    range
-                } else {
-                    0..0
-                }
+        let child = match self.current_group {
+            Some(index) => self.groups[index].last_child,
+            None => self.root_last_child,
+        };
+        let range = match child {
+            Some(index) if apply => {
+                self.current_group = Some(index);
+                self.groups[index].edit_range.clone()
             }
-            _ => unreachable!(),
+            _ => 0..0,
         };
 
         let texts = &self.texts;
@@ -427,11 +637,178 @@ impl BufferHistory {
     }
 }
 
+impl<'de> Serialize<'de> for BufferHistory {
+    fn serialize<S>(&self, serializer: &mut S)
+    where
+        S: Serializer,
+    {
+        fn serialize_position<S>(position: BufferPosition, serializer: &mut S)
+        where
+            S: Serializer,
+        {
+            position.line_index.serialize(serializer);
+            position.column_byte_index.serialize(serializer);
+        }
+
+        fn serialize_group_index<S>(group_index: Option<usize>, serializer: &mut S)
+        where
+            S: Serializer,
+        {
+            let index = match group_index {
+                Some(index) => index as u32 + 1,
+                None => 0,
+            };
+            index.serialize(serializer);
+        }
+
+        let edit_count = match self.state {
+            HistoryState::Idle => self.edits.len(),
+            HistoryState::InsertGroup { edit_index } => edit_index,
+        };
+
+        let texts_len = self.edits[..edit_count]
+            .iter()
+            .map(|e| e.text_range.end as usize)
+            .max()
+            .unwrap_or(0);
+        (&self.texts[..texts_len]).serialize(serializer);
+
+        (edit_count as u32).serialize(serializer);
+        for edit in &self.edits[..edit_count] {
+            let kind: u8 = match edit.kind {
+                EditKind::Insert => 0,
+                EditKind::Delete => 1,
+            };
+            kind.serialize(serializer);
+            serialize_position(edit.buffer_range.from, serializer);
+            serialize_position(edit.buffer_range.to, serializer);
+            edit.text_range.start.serialize(serializer);
+            edit.text_range.end.serialize(serializer);
+        }
+
+        (self.groups.len() as u32).serialize(serializer);
+        for group in &self.groups {
+            (group.edit_range.start as u32).serialize(serializer);
+            (group.edit_range.end as u32).serialize(serializer);
+            serialize_group_index(group.parent, serializer);
+            serialize_group_index(group.last_child, serializer);
+            let time = group
+                .time
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default();
+            time.as_secs().serialize(serializer);
+            time.subsec_nanos().serialize(serializer);
+        }
+
+        serialize_group_index(self.current_group, serializer);
+        serialize_group_index(self.root_last_child, serializer);
+    }
+
+    fn deserialize<D>(deserializer: &mut D) -> Result<Self, DeserializeError>
+    where
+        D: Deserializer<'de>,
+    {
+        fn deserialize_position<'de, D>(
+            deserializer: &mut D,
+        ) -> Result<BufferPosition, DeserializeError>
+        where
+            D: Deserializer<'de>,
+        {
+            let line_index = u32::deserialize(deserializer)?;
+            let column_byte_index = u32::deserialize(deserializer)?;
+            Ok(BufferPosition::line_col(line_index, column_byte_index))
+        }
+
+        fn deserialize_group_index<'de, D>(
+            deserializer: &mut D,
+            valid_range: Range<usize>,
+        ) -> Result<Option<usize>, DeserializeError>
+        where
+            D: Deserializer<'de>,
+        {
+            match u32::deserialize(deserializer)? {
+                0 => Ok(None),
+                index if valid_range.contains(&(index as usize - 1)) => {
+                    Ok(Some(index as usize - 1))
+                }
+                _ => Err(DeserializeError::InvalidData),
+            }
+        }
+
+        let mut history = Self::new();
+        history.texts.push_str(<&str>::deserialize(deserializer)?);
+
+        let edit_count = u32::deserialize(deserializer)?;
+        for _ in 0..edit_count {
+            let kind = match u8::deserialize(deserializer)? {
+                0 => EditKind::Insert,
+                1 => EditKind::Delete,
+                _ => return Err(DeserializeError::InvalidData),
+            };
+            let from = deserialize_position(deserializer)?;
+            let to = deserialize_position(deserializer)?;
+            let text_range = u32::deserialize(deserializer)?..u32::deserialize(deserializer)?;
+            if text_range.start > text_range.end
+                || history
+                    .texts
+                    .get(text_range.start as usize..text_range.end as usize)
+                    .is_none()
+            {
+                return Err(DeserializeError::InvalidData);
+            }
+            history.edits.push(EditInternal {
+                kind,
+                buffer_range: BufferRange::between(from, to),
+                text_range,
+            });
+        }
+
+        let group_count = u32::deserialize(deserializer)? as usize;
+        for group_index in 0..group_count {
+            let start = u32::deserialize(deserializer)? as usize;
+            let end = u32::deserialize(deserializer)? as usize;
+            if start > end || end > history.edits.len() {
+                return Err(DeserializeError::InvalidData);
+            }
+            let parent = deserialize_group_index(deserializer, 0..group_index)?;
+            let last_child = deserialize_group_index(deserializer, group_index + 1..group_count)?;
+            let secs = u64::deserialize(deserializer)?;
+            let nanos = u32::deserialize(deserializer)?;
+            let time = SystemTime::UNIX_EPOCH
+                .checked_add(Duration::new(secs, nanos))
+                .ok_or(DeserializeError::InvalidData)?;
+            history.groups.push(HistoryGroup {
+                edit_range: start..end,
+                parent,
+                last_child,
+                time,
+            });
+        }
+
+        history.current_group = deserialize_group_index(deserializer, 0..group_count)?;
+        history.root_last_child = deserialize_group_index(deserializer, 0..group_count)?;
+
+        Ok(history)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::buffer_position::BufferPosition;
 
+    fn jump(history: &mut BufferHistory, group_index: Option<usize>) -> (usize, usize) {
+        let (undo_count, redo_count) = history.jump_path(group_index);
+        for _ in 0..undo_count {
+            history.undo_edits().count();
+        }
+        for _ in 0..redo_count {
+            history.redo_edits().count();
+        }
+        assert_eq!(group_index, history.current_group());
+        (undo_count, redo_count)
+    }
+
     fn buffer_range(from: (usize, usize), to: (usize, usize)) -> BufferRange {
         BufferRange::between(
             BufferPosition::line_col(from.0 as _, from.1 as _),
@@ -1258,4 +1635,146 @@ mod tests {
             assert!(edits.next().is_none());
         }
     }
+
+    #[test]
+    fn keep_undone_branches() {
+        fn insert(history: &mut BufferHistory, text: &str) {
+            history.add_edit(Edit {
+                kind: EditKind::Insert,
+                range: buffer_range((0, 0), (0, text.len())),
+                text,
+            });
+            history.commit_edits();
+        }
+
+        let mut history = BufferHistory::new();
+        insert(&mut history, "a");
+        insert(&mut history, "b");
+        assert_eq!(1, history.undo_edits().count());
+        insert(&mut history, "c");
+
+        assert_eq!(3, history.group_count());
+        assert_eq!(vec![1, 2], history.branches().collect::<Vec<_>>());
+        assert_eq!(Some(2), history.current_branch());
+        assert_eq!(0, history.redo_edits().count());
+
+        assert_eq!((1, 1), jump(&mut history, Some(1)));
+        assert_eq!(Some(1), history.current_branch());
+        assert_eq!(1, history.undo_edits().count());
+        {
+            let mut edits = history.redo_edits();
+            let edit = edits.next().unwrap();
+            assert_eq!("b", edit.text);
+            assert!(edits.next().is_none());
+        }
+
+        assert_eq!((2, 0), jump(&mut history, None));
+        assert_eq!((0, 2), jump(&mut history, Some(2)));
+        assert_eq!(Some(2), history.current_branch());
+        assert_eq!(2, history.group_depth(Some(2)));
+    }
+
+    #[test]
+    fn travel_history_chronologically() {
+        fn insert(history: &mut BufferHistory, secs: u64) {
+            history.add_edit(Edit {
+                kind: EditKind::Insert,
+                range: buffer_range((0, 0), (0, 1)),
+                text: "a",
+            });
+            history.commit_edits_at(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        }
+
+        let mut history = BufferHistory::new();
+        assert_eq!(None, history.later_group(HistoryTravel::Groups(1)));
+
+        insert(&mut history, 10);
+        insert(&mut history, 20);
+        assert_eq!(1, history.undo_edits().count());
+        insert(&mut history, 30);
+        insert(&mut history, 100);
+
+        assert_eq!(Some(3), history.current_group());
+        assert_eq!(Some(2), history.earlier_group(HistoryTravel::Groups(1)));
+        assert_eq!(Some(1), history.earlier_group(HistoryTravel::Groups(2)));
+        assert_eq!(None, history.earlier_group(HistoryTravel::Groups(4)));
+        assert_eq!(Some(3), history.later_group(HistoryTravel::Groups(1)));
+
+        let secs = |secs| HistoryTravel::Time(Duration::from_secs(secs));
+        assert_eq!(Some(2), history.earlier_group(secs(70)));
+        assert_eq!(Some(1), history.earlier_group(secs(75)));
+        assert_eq!(None, history.earlier_group(secs(95)));
+
+        assert_eq!((2, 1), jump(&mut history, Some(1)));
+        assert_eq!(Some(1), history.current_group());
+        assert_eq!(Some(2), history.later_group(secs(10)));
+        assert_eq!(Some(1), history.later_group(secs(5)));
+        assert_eq!(Some(3), history.later_group(HistoryTravel::Groups(5)));
+
+        assert_eq!(Ok(HistoryTravel::Groups(3)), "3".parse());
+        assert_eq!(Ok(secs(120)), "2m".parse());
+        assert_eq!(Err(()), "2x".parse::<HistoryTravel>());
+        assert_eq!(Err(()), "m".parse::<HistoryTravel>());
+        assert_eq!("2h", secs(2 * 60 * 60 + 5).to_string());
+    }
+
+    #[test]
+    fn serialize_history() {
+        let mut history = BufferHistory::new();
+        history.add_edit(Edit {
+            kind: EditKind::Insert,
+            range: buffer_range((0, 0), (0, 3)),
+            text: "abc",
+        });
+        history.commit_edits();
+        history.add_edit(Edit {
+            kind: EditKind::Delete,
+            range: buffer_range((0, 1), (0, 2)),
+            text: "b",
+        });
+        history.commit_edits();
+        assert_eq!(1, history.undo_edits().count());
+        history.add_edit(Edit {
+            kind: EditKind::Insert,
+            range: buffer_range((0, 3), (0, 4)),
+            text: "d",
+        });
+        history.commit_edits();
+        assert_eq!(1, history.undo_edits().count());
+        assert_eq!((0, 1), jump(&mut history, Some(1)));
+        assert_eq!(1, history.undo_edits().count());
+
+        let mut bytes = Vec::new();
+        history.serialize(&mut bytes);
+
+        let mut deserializer = &bytes[..];
+        let mut history = match BufferHistory::deserialize(&mut deserializer) {
+            Ok(history) => history,
+            Err(_) => panic!("could not deserialize history"),
+        };
+        assert!(deserializer.is_empty());
+        assert_eq!(3, history.group_count());
+        assert_eq!(vec![1, 2], history.branches().collect::<Vec<_>>());
+
+        {
+            let mut edits = history.redo_edits();
+            let edit = edits.next().unwrap();
+            assert_eq!(EditKind::Delete, edit.kind);
+            assert_eq!("b", edit.text);
+            assert_eq!(buffer_range((0, 1), (0, 2)), edit.range);
+            assert!(edits.next().is_none());
+        }
+        assert_eq!(1, history.undo_edits().count());
+        {
+            let mut edits = history.undo_edits();
+            let edit = edits.next().unwrap();
+            assert_eq!(EditKind::Delete, edit.kind);
+            assert_eq!("abc", edit.text);
+            assert!(edits.next().is_none());
+        }
+        assert_eq!(0, history.undo_edits().count());
+
+        let mut deserializer = &bytes[..bytes.len() - 1];
+        assert!(BufferHistory::deserialize(&mut deserializer).is_err());
+    }
 }
@@ -1,12 +1,15 @@
 use std::num::NonZeroU8;
 
 use crate::{
-    buffer::{Buffer, BufferCollection, BufferHandle, CharDisplayDistances},
+    buffer::{
+        find_number_at, Buffer, BufferCollection, BufferHandle, CharDisplayDistances, SurroundPair,
+    },
     buffer_history::EditKind,
     buffer_position::{BufferPosition, BufferPositionIndex, BufferRange},
     client::ClientHandle,
     cursor::{Cursor, CursorCollection},
     events::EditorEventQueue,
+    snippet::Snippet,
     word_database::{WordDatabase, WordIter, WordKind},
 };
 
@@ -382,6 +385,65 @@ impl BufferView {
         }
     }
 
+    pub fn insert_char_with_pairs(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        c: char,
+        pairs: &[(char, char)],
+        events: &mut EditorEventQueue,
+    ) {
+        let buffer = buffers.get_mut(self.buffer_handle);
+        let cursor_count = self.cursors[..].len();
+        let mut fixed_cursors = [Cursor::zero(); CursorCollection::capacity()];
+        let mut text = String::new();
+
+        let pair_close = pairs.iter().find(|&&(o, _)| o == c).map(|&(_, cl)| cl);
+        let is_close = pairs.iter().any(|&(_, cl)| cl == c);
+
+        for i in (0..cursor_count).rev() {
+            let position = self.cursors[i].position;
+            let line = buffer.content().lines()[position.line_index as usize].as_str();
+            let (before, after) = line.split_at(position.column_byte_index as usize);
+            let previous_char = before.chars().next_back();
+            let next_char = after.chars().next();
+
+            if !is_close || next_char != Some(c) {
+                text.clear();
+                text.push(c);
+                if let Some(close) = pair_close {
+                    let can_close = match next_char {
+                        Some(n) => n.is_whitespace() || pairs.iter().any(|&(_, cl)| cl == n),
+                        None => true,
+                    };
+                    let is_after_word = match previous_char {
+                        Some(p) => p.is_alphanumeric() || p == c,
+                        None => false,
+                    };
+                    if can_close && (close != c || !is_after_word) {
+                        text.push(close);
+                    }
+                }
+
+                let range = buffer.insert_text(word_database, position, &text, events);
+                for fixed_cursor in &mut fixed_cursors[i + 1..cursor_count] {
+                    fixed_cursor.insert(range);
+                }
+            }
+
+            let position = BufferPosition::line_col(
+                position.line_index,
+                position.column_byte_index + c.len_utf8() as BufferPositionIndex,
+            );
+            fixed_cursors[i] = Cursor {
+                anchor: position,
+                position,
+            };
+        }
+
+        events.enqueue_fix_cursors(self.handle, &fixed_cursors[..cursor_count]);
+    }
+
     pub fn delete_text_in_cursor_ranges(
         &self,
         buffers: &mut BufferCollection,
@@ -394,6 +456,434 @@ impl BufferView {
         }
     }
 
+    pub fn transform_selections<F>(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        events: &mut EditorEventQueue,
+        mut transform: F,
+    ) where
+        F: FnMut(usize, &str, &mut String),
+    {
+        let buffer = buffers.get_mut(self.buffer_handle);
+        let cursor_count = self.cursors[..].len();
+        let mut fixed_cursors = [Cursor::zero(); CursorCollection::capacity()];
+        let mut selection = String::new();
+        let mut text = String::new();
+
+        for i in (0..cursor_count).rev() {
+            let cursor = self.cursors[i];
+            let range = cursor.to_range();
+
+            selection.clear();
+            for t in buffer.content().text_range(range) {
+                selection.push_str(t);
+            }
+            text.clear();
+            transform(i, &selection, &mut text);
+
+            buffer.delete_range(word_database, range, events);
+            let inserted_range = buffer.insert_text(word_database, range.from, &text, events);
+            for fixed_cursor in &mut fixed_cursors[i + 1..cursor_count] {
+                fixed_cursor.delete(range);
+                fixed_cursor.insert(inserted_range);
+            }
+
+            fixed_cursors[i] = if cursor.anchor <= cursor.position {
+                Cursor {
+                    anchor: inserted_range.from,
+                    position: inserted_range.to,
+                }
+            } else {
+                Cursor {
+                    anchor: inserted_range.to,
+                    position: inserted_range.from,
+                }
+            };
+        }
+
+        events.enqueue_fix_cursors(self.handle, &fixed_cursors[..cursor_count]);
+    }
+
+    // merged ranges of line indexes touched by each cursor (both inclusive)
+    fn selected_line_ranges(&self) -> Vec<(usize, usize)> {
+        let mut line_ranges: Vec<(usize, usize)> = Vec::new();
+        for cursor in &self.cursors[..] {
+            let range = cursor.to_range();
+            let from = range.from.line_index as usize;
+            let to = range.to.line_index as usize;
+            match line_ranges.last_mut() {
+                Some((_, last_to)) if from <= *last_to => *last_to = (*last_to).max(to),
+                _ => line_ranges.push((from, to)),
+            }
+        }
+        line_ranges
+    }
+
+    pub fn transform_lines<F>(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        events: &mut EditorEventQueue,
+        mut transform: F,
+    ) where
+        F: FnMut(&mut Vec<&str>),
+    {
+        let buffer = buffers.get_mut(self.buffer_handle);
+        let line_ranges = self.selected_line_ranges();
+        let mut fixed_cursors = [Cursor::zero(); CursorCollection::capacity()];
+        let mut text = String::new();
+
+        for (i, &(from, to)) in line_ranges.iter().enumerate().rev() {
+            let lines = buffer.content().lines();
+            let range = BufferRange::between(
+                BufferPosition::line_col(from as _, 0),
+                BufferPosition::line_col(to as _, lines[to].as_str().len() as _),
+            );
+            fixed_cursors[i] = Cursor {
+                anchor: range.from,
+                position: range.to,
+            };
+
+            let original_lines: Vec<_> = (from..=to).map(|l| lines[l].as_str()).collect();
+            let mut transformed_lines = original_lines.clone();
+            transform(&mut transformed_lines);
+            if transformed_lines == original_lines {
+                continue;
+            }
+
+            text.clear();
+            for (j, line) in transformed_lines.iter().enumerate() {
+                if j > 0 {
+                    text.push('\n');
+                }
+                text.push_str(line);
+            }
+
+            buffer.delete_range(word_database, range, events);
+            let inserted_range = buffer.insert_text(word_database, range.from, &text, events);
+            for fixed_cursor in &mut fixed_cursors[i + 1..line_ranges.len()] {
+                fixed_cursor.delete(range);
+                fixed_cursor.insert(inserted_range);
+            }
+            fixed_cursors[i] = Cursor {
+                anchor: inserted_range.from,
+                position: inserted_range.to,
+            };
+        }
+
+        events.enqueue_fix_cursors(self.handle, &fixed_cursors[..line_ranges.len()]);
+    }
+
+    pub fn toggle_comments(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        line_comment: &str,
+        block_comment: (&str, &str),
+        events: &mut EditorEventQueue,
+    ) {
+        fn is_blank(line: &str) -> bool {
+            line.trim_start().is_empty()
+        }
+        fn indentation_len(line: &str) -> usize {
+            line.len() - line.trim_start().len()
+        }
+
+        let buffer = buffers.get_mut(self.buffer_handle);
+        let line_ranges = self.selected_line_ranges();
+
+        let lines = buffer.content().lines();
+        let mut text = String::new();
+
+        if !line_comment.is_empty() {
+            let mut has_lines = false;
+            let mut is_commented = true;
+            for &(from, to) in &line_ranges {
+                for line_index in from..=to {
+                    let line = lines[line_index].as_str();
+                    if !is_blank(line) {
+                        has_lines = true;
+                        is_commented &= line.trim_start().starts_with(line_comment);
+                    }
+                }
+            }
+            if !has_lines {
+                return;
+            }
+
+            text.push_str(line_comment);
+            text.push(' ');
+
+            for &(from, to) in line_ranges.iter().rev() {
+                let lines = buffer.content().lines();
+                let indentation = (from..=to)
+                    .map(|i| lines[i].as_str())
+                    .filter(|l| !is_blank(l))
+                    .map(indentation_len)
+                    .min()
+                    .unwrap_or(0);
+
+                for line_index in (from..=to).rev() {
+                    let line = buffer.content().lines()[line_index].as_str();
+                    if is_blank(line) {
+                        continue;
+                    }
+
+                    if is_commented {
+                        let column = indentation_len(line);
+                        let mut len = line_comment.len();
+                        if line[column + len..].starts_with(' ') {
+                            len += 1;
+                        }
+                        let range = BufferRange::between(
+                            BufferPosition::line_col(line_index as _, column as _),
+                            BufferPosition::line_col(line_index as _, (column + len) as _),
+                        );
+                        buffer.delete_range(word_database, range, events);
+                    } else {
+                        let position = BufferPosition::line_col(line_index as _, indentation as _);
+                        buffer.insert_text(word_database, position, &text, events);
+                    }
+                }
+            }
+        } else if !block_comment.0.is_empty() && !block_comment.1.is_empty() {
+            let (open, close) = block_comment;
+
+            let mut blocks = Vec::new();
+            for &(from, to) in &line_ranges {
+                let first = (from..=to).find(|&i| !is_blank(lines[i].as_str()));
+                let last = (from..=to).rev().find(|&i| !is_blank(lines[i].as_str()));
+                if let (Some(first), Some(last)) = (first, last) {
+                    blocks.push((first, last));
+                }
+            }
+            if blocks.is_empty() {
+                return;
+            }
+
+            let is_commented = blocks.iter().all(|&(first, last)| {
+                let first_line = lines[first].as_str().trim();
+                let last_line = lines[last].as_str().trim();
+                let min_len = if first == last {
+                    open.len() + close.len()
+                } else {
+                    0
+                };
+                first_line.len() >= min_len
+                    && first_line.starts_with(open)
+                    && last_line.ends_with(close)
+            });
+
+            for &(first, last) in blocks.iter().rev() {
+                let lines = buffer.content().lines();
+                let first_line = lines[first].as_str();
+                let last_line = lines[last].as_str();
+                let first_column = indentation_len(first_line);
+                let last_column = last_line.trim_end().len();
+
+                if is_commented {
+                    let mut close_column = last_column - close.len();
+                    if last_line[..close_column].ends_with(' ')
+                        && (first != last || close_column > first_column + open.len())
+                    {
+                        close_column -= 1;
+                    }
+                    let mut open_len = open.len();
+                    if first_line[first_column + open_len..].starts_with(' ')
+                        && (first != last || first_column + open_len < close_column)
+                    {
+                        open_len += 1;
+                    }
+
+                    let close_range = BufferRange::between(
+                        BufferPosition::line_col(last as _, close_column as _),
+                        BufferPosition::line_col(last as _, last_column as _),
+                    );
+                    let open_range = BufferRange::between(
+                        BufferPosition::line_col(first as _, first_column as _),
+                        BufferPosition::line_col(first as _, (first_column + open_len) as _),
+                    );
+                    buffer.delete_range(word_database, close_range, events);
+                    buffer.delete_range(word_database, open_range, events);
+                } else {
+                    text.clear();
+                    text.push(' ');
+                    text.push_str(close);
+                    let position = BufferPosition::line_col(last as _, last_column as _);
+                    buffer.insert_text(word_database, position, &text, events);
+
+                    text.clear();
+                    text.push_str(open);
+                    text.push(' ');
+                    let position = BufferPosition::line_col(first as _, first_column as _);
+                    buffer.insert_text(word_database, position, &text, events);
+                }
+            }
+        }
+    }
+
+    pub fn add_surround(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        open: &str,
+        close: &str,
+        events: &mut EditorEventQueue,
+    ) {
+        let buffer = buffers.get_mut(self.buffer_handle);
+        let cursor_count = self.cursors[..].len();
+        let mut fixed_cursors = [Cursor::zero(); CursorCollection::capacity()];
+
+        for i in (0..cursor_count).rev() {
+            let cursor = self.cursors[i];
+            let range = cursor.to_range();
+            let close_range = buffer.insert_text(word_database, range.to, close, events);
+            let open_range = buffer.insert_text(word_database, range.from, open, events);
+
+            for fixed_cursor in &mut fixed_cursors[i + 1..cursor_count] {
+                fixed_cursor.insert(close_range);
+                fixed_cursor.insert(open_range);
+            }
+
+            let from = open_range.to;
+            let to = close_range.from.insert(open_range);
+            fixed_cursors[i] = if cursor.anchor <= cursor.position {
+                Cursor {
+                    anchor: from,
+                    position: to,
+                }
+            } else {
+                Cursor {
+                    anchor: to,
+                    position: from,
+                }
+            };
+        }
+
+        events.enqueue_fix_cursors(self.handle, &fixed_cursors[..cursor_count]);
+    }
+
+    pub fn change_surround(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        pair: SurroundPair,
+        open: &str,
+        close: &str,
+        events: &mut EditorEventQueue,
+    ) {
+        let buffer = buffers.get_mut(self.buffer_handle);
+        let mut ranges = Vec::new();
+        for cursor in &self.cursors[..] {
+            if let Some((open_range, close_range)) = buffer
+                .content()
+                .find_surround_pair_at(cursor.position, pair)
+            {
+                ranges.push((open_range, open));
+                ranges.push((close_range, close));
+            }
+        }
+        ranges.sort_unstable_by_key(|(r, _)| r.from);
+        ranges.dedup_by_key(|(r, _)| r.from);
+
+        for &(range, text) in ranges.iter().rev() {
+            buffer.delete_range(word_database, range, events);
+            buffer.insert_text(word_database, range.from, text, events);
+        }
+    }
+
+    pub fn delete_surround(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        pair: SurroundPair,
+        events: &mut EditorEventQueue,
+    ) {
+        self.change_surround(buffers, word_database, pair, "", "", events);
+    }
+
+    pub fn add_to_numbers(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        delta: i64,
+        is_sequence: bool,
+        events: &mut EditorEventQueue,
+    ) {
+        let buffer = buffers.get_mut(self.buffer_handle);
+        let cursor_count = self.cursors[..].len();
+        let mut fixed_cursors = [Cursor::zero(); CursorCollection::capacity()];
+        let mut text = String::new();
+
+        // only distinct numbers take a place in the sequence so it has no gaps
+        let mut sequence_indices = [0; CursorCollection::capacity()];
+        if is_sequence {
+            let mut sequence_index = 0;
+            let mut last_position = None;
+            for (i, cursor) in self.cursors[..].iter().enumerate() {
+                let position = cursor.position;
+                let line = buffer.content().lines()[position.line_index as usize].as_str();
+                if let Some(number) = find_number_at(line, position.column_byte_index as _) {
+                    let from = BufferPosition::line_col(position.line_index, number.from as _);
+                    if last_position != Some(from) {
+                        last_position = Some(from);
+                        sequence_index += 1;
+                    }
+                }
+                sequence_indices[i] = sequence_index;
+            }
+        }
+
+        let mut last_position = None;
+        for i in (0..cursor_count).rev() {
+            let cursor = self.cursors[i];
+            let position = cursor.position;
+            fixed_cursors[i] = cursor;
+
+            let line = buffer.content().lines()[position.line_index as usize].as_str();
+            let number = match find_number_at(line, position.column_byte_index as _) {
+                Some(number) => number,
+                None => continue,
+            };
+            let range = BufferRange::between(
+                BufferPosition::line_col(position.line_index, number.from as _),
+                BufferPosition::line_col(position.line_index, number.to as _),
+            );
+            if last_position == Some(range.from) {
+                fixed_cursors[i] = fixed_cursors[i + 1];
+                continue;
+            }
+            last_position = Some(range.from);
+
+            let delta = if is_sequence {
+                delta.saturating_mul(sequence_indices[i])
+            } else {
+                delta
+            };
+            text.clear();
+            number.write_added(delta, &mut text);
+
+            buffer.delete_range(word_database, range, events);
+            let inserted_range = buffer.insert_text(word_database, range.from, &text, events);
+            for fixed_cursor in &mut fixed_cursors[i + 1..cursor_count] {
+                fixed_cursor.delete(range);
+                fixed_cursor.insert(inserted_range);
+            }
+
+            let position = BufferPosition::line_col(
+                inserted_range.to.line_index,
+                inserted_range.to.column_byte_index - 1,
+            );
+            fixed_cursors[i] = Cursor {
+                anchor: position,
+                position,
+            };
+        }
+
+        events.enqueue_fix_cursors(self.handle, &fixed_cursors[..cursor_count]);
+    }
+
     pub fn find_completion_positions(
         &self,
         buffers: &mut BufferCollection,
@@ -428,6 +918,44 @@ impl BufferView {
         }
     }
 
+    // replaces the text between each position and its cursor with the snippet (keeping the line's
+    // indentation) and outputs the buffer ranges of all its stops
+    pub fn insert_snippet(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        snippet: &Snippet,
+        positions: &[BufferPosition],
+        stop_ranges: &mut Vec<(u32, BufferRange)>,
+        events: &mut EditorEventQueue,
+    ) {
+        stop_ranges.clear();
+        let buffer = buffers.get_mut(self.buffer_handle);
+        let mut text = String::new();
+
+        for (cursor, &position) in self.cursors[..].iter().zip(positions.iter()).rev() {
+            let line = buffer.content().lines()[position.line_index as usize].as_str();
+            let indentation_len =
+                (line.len() - line.trim_start().len()).min(position.column_byte_index as _);
+            text.clear();
+            snippet.write_indented(&line[..indentation_len], &mut text);
+
+            let range = BufferRange::between(position, cursor.position);
+            buffer.delete_range(word_database, range, events);
+            let inserted_range = buffer.insert_text(word_database, position, &text, events);
+            for (_, stop_range) in stop_ranges.iter_mut() {
+                stop_range.from = stop_range.from.delete(range).insert(inserted_range);
+                stop_range.to = stop_range.to.delete(range).insert(inserted_range);
+            }
+
+            for (stop, stop_range) in snippet.stops() {
+                let from = snippet.position_at(stop_range.start, position, indentation_len);
+                let to = snippet.position_at(stop_range.end, position, indentation_len);
+                stop_ranges.push((*stop, BufferRange::between(from, to)));
+            }
+        }
+    }
+
     pub fn undo(
         &mut self,
         buffers: &mut BufferCollection,
@@ -497,6 +1025,24 @@ impl BufferView {
         events.enqueue_fix_cursors(self.handle, &cursors[..]);
         cursors.clear();
     }
+
+    pub fn jump_history(
+        &mut self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        group_index: Option<usize>,
+        events: &mut EditorEventQueue,
+    ) {
+        let (undo_count, redo_count) = buffers
+            .get_mut(self.buffer_handle)
+            .history_jump_path(group_index);
+        for _ in 0..undo_count {
+            self.undo(buffers, word_database, events);
+        }
+        for _ in 0..redo_count {
+            self.redo(buffers, word_database, events);
+        }
+    }
 }
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -614,7 +1160,11 @@ mod tests {
 
     use std::ops::Range;
 
-    use crate::{buffer::BufferProperties, buffer_position::BufferPosition};
+    use crate::{
+        buffer::BufferProperties,
+        buffer_position::BufferPosition,
+        events::{EditorEvent, EditorEventIter},
+    };
 
     struct TestContext {
         pub buffers: BufferCollection,
@@ -735,4 +1285,448 @@ mod tests {
         assert_movement(&mut ctx, 1..2, 1..0, CursorMovement::WordsBackward(1));
         assert_movement(&mut ctx, 2..0, 1..9, CursorMovement::WordsBackward(1));
     }
+
+    #[test]
+    fn buffer_view_surround() {
+        fn buffer_text(ctx: &TestContext) -> String {
+            let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+            ctx.buffers
+                .get(buffer_view.buffer_handle)
+                .content()
+                .to_string()
+        }
+
+        let mut ctx = TestContext::with_buffer("abc def");
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 0),
+            position: BufferPosition::line_col(0, 3),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 7),
+            position: BufferPosition::line_col(0, 4),
+        });
+        drop(cursors);
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        events.flip();
+        buffer_view.add_surround(&mut ctx.buffers, &mut word_database, "(", ")", &mut events);
+        assert_eq!("(abc) (def)", buffer_text(&ctx));
+
+        events.flip();
+        let mut fixed_cursors = None;
+        let mut events_iter = EditorEventIter::new();
+        while let Some(event) = events_iter.next(&events) {
+            if let EditorEvent::FixCursors { cursors, .. } = event {
+                fixed_cursors = Some(cursors.as_cursors(&events).to_vec());
+            }
+        }
+        assert_eq!(
+            Some(vec![
+                Cursor {
+                    anchor: BufferPosition::line_col(0, 1),
+                    position: BufferPosition::line_col(0, 4),
+                },
+                Cursor {
+                    anchor: BufferPosition::line_col(0, 10),
+                    position: BufferPosition::line_col(0, 7),
+                },
+            ]),
+            fixed_cursors,
+        );
+
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 2),
+            position: BufferPosition::line_col(0, 2),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 8),
+            position: BufferPosition::line_col(0, 8),
+        });
+        drop(cursors);
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.change_surround(
+            &mut ctx.buffers,
+            &mut word_database,
+            SurroundPair::Balanced('(', ')'),
+            "<b>",
+            "</b>",
+            &mut events,
+        );
+        assert_eq!("<b>abc</b> <b>def</b>", buffer_text(&ctx));
+
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 4),
+            position: BufferPosition::line_col(0, 4),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 15),
+            position: BufferPosition::line_col(0, 15),
+        });
+        drop(cursors);
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.delete_surround(
+            &mut ctx.buffers,
+            &mut word_database,
+            SurroundPair::Tag,
+            &mut events,
+        );
+        assert_eq!("abc def", buffer_text(&ctx));
+    }
+
+    #[test]
+    fn buffer_view_add_to_numbers() {
+        let mut ctx = TestContext::with_buffer("a = 0,\nb = 0,\nc = 0, 0x1");
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        for line_index in 0..3 {
+            let position = BufferPosition::line_col(line_index, 0);
+            cursors.add(Cursor {
+                anchor: position,
+                position,
+            });
+        }
+        drop(cursors);
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.add_to_numbers(&mut ctx.buffers, &mut word_database, 1, true, &mut events);
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!("a = 1,\nb = 2,\nc = 3, 0x1", buffer.content().to_string());
+
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(2, 4),
+            position: BufferPosition::line_col(2, 4),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(2, 5),
+            position: BufferPosition::line_col(2, 5),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(2, 8),
+            position: BufferPosition::line_col(2, 8),
+        });
+        drop(cursors);
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.add_to_numbers(
+            &mut ctx.buffers,
+            &mut word_database,
+            -10,
+            false,
+            &mut events,
+        );
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!(
+            "a = 1,\nb = 2,\nc = -7, 0xfffffffffffffff7",
+            buffer.content().to_string()
+        );
+
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        for &(line_index, column_index) in &[(0, 0), (0, 4), (1, 4), (2, 4)] {
+            let position = BufferPosition::line_col(line_index, column_index);
+            cursors.add(Cursor {
+                anchor: position,
+                position,
+            });
+        }
+        drop(cursors);
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.add_to_numbers(&mut ctx.buffers, &mut word_database, 1, true, &mut events);
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!(
+            "a = 2,\nb = 4,\nc = -4, 0xfffffffffffffff7",
+            buffer.content().to_string()
+        );
+    }
+
+    #[test]
+    fn buffer_view_transform_selections() {
+        let mut ctx = TestContext::with_buffer("foo bar\nbaz");
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 0),
+            position: BufferPosition::line_col(0, 3),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(1, 3),
+            position: BufferPosition::line_col(0, 4),
+        });
+        drop(cursors);
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.transform_selections(
+            &mut ctx.buffers,
+            &mut word_database,
+            &mut events,
+            |_, text, output| {
+                output.push('<');
+                output.push_str(text);
+                output.push('>');
+            },
+        );
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!("<foo> <bar\nbaz>", buffer.content().to_string());
+    }
+
+    #[test]
+    fn buffer_view_toggle_comments() {
+        fn set_cursors(ctx: &mut TestContext, ranges: &[(u32, u32, u32, u32)]) {
+            let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+            let mut cursors = buffer_view.cursors.mut_guard();
+            cursors.clear();
+            for &(al, ac, pl, pc) in ranges {
+                cursors.add(Cursor {
+                    anchor: BufferPosition::line_col(al as _, ac as _),
+                    position: BufferPosition::line_col(pl as _, pc as _),
+                });
+            }
+        }
+
+        let mut ctx = TestContext::with_buffer("fn f() {\n    a();\n\n        b();\n}");
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+        let buffer_handle = ctx.buffer_views.get(ctx.buffer_view_handle).buffer_handle;
+
+        set_cursors(&mut ctx, &[(1, 0, 3, 0)]);
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.toggle_comments(
+            &mut ctx.buffers,
+            &mut word_database,
+            "//",
+            ("/*", "*/"),
+            &mut events,
+        );
+        assert_eq!(
+            "fn f() {\n    // a();\n\n    //     b();\n}",
+            ctx.buffers.get(buffer_handle).content().to_string()
+        );
+
+        set_cursors(&mut ctx, &[(1, 0, 1, 0), (3, 0, 3, 0)]);
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.toggle_comments(
+            &mut ctx.buffers,
+            &mut word_database,
+            "//",
+            ("/*", "*/"),
+            &mut events,
+        );
+        assert_eq!(
+            "fn f() {\n    a();\n\n        b();\n}",
+            ctx.buffers.get(buffer_handle).content().to_string()
+        );
+
+        set_cursors(&mut ctx, &[(0, 0, 1, 0), (3, 2, 3, 2)]);
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.toggle_comments(
+            &mut ctx.buffers,
+            &mut word_database,
+            "",
+            ("/*", "*/"),
+            &mut events,
+        );
+        assert_eq!(
+            "/* fn f() {\n    a(); */\n\n        /* b(); */\n}",
+            ctx.buffers.get(buffer_handle).content().to_string()
+        );
+
+        set_cursors(&mut ctx, &[(0, 0, 1, 0), (3, 2, 3, 2)]);
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.toggle_comments(
+            &mut ctx.buffers,
+            &mut word_database,
+            "",
+            ("/*", "*/"),
+            &mut events,
+        );
+        assert_eq!(
+            "fn f() {\n    a();\n\n        b();\n}",
+            ctx.buffers.get(buffer_handle).content().to_string()
+        );
+    }
+
+    #[test]
+    fn buffer_view_insert_char_with_pairs() {
+        fn insert(text: &str, columns: &[u32], c: char) -> String {
+            let mut ctx = TestContext::with_buffer(text);
+            let mut events = EditorEventQueue::default();
+            let mut word_database = WordDatabase::new();
+
+            let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+            let mut cursors = buffer_view.cursors.mut_guard();
+            cursors.clear();
+            for &column_index in columns {
+                let position = BufferPosition::line_col(0, column_index as _);
+                cursors.add(Cursor {
+                    anchor: position,
+                    position,
+                });
+            }
+            drop(cursors);
+
+            let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+            buffer_view.insert_char_with_pairs(
+                &mut ctx.buffers,
+                &mut word_database,
+                c,
+                &[('(', ')'), ('"', '"')],
+                &mut events,
+            );
+            let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+            buffer.content().to_string()
+        }
+
+        assert_eq!("a() b()", insert("a b", &[1, 3], '('));
+        assert_eq!("(a", insert("a", &[0], '('));
+        assert_eq!("f(())", insert("f()", &[2], '('));
+        assert_eq!("f() g()", insert("f() g()", &[2, 6], ')'));
+        assert_eq!("f() g)", insert("f() g", &[2, 5], ')'));
+        assert_eq!("x = \"\"", insert("x = ", &[4], '"'));
+        assert_eq!("x\"", insert("x", &[1], '"'));
+        assert_eq!("\"x\"", insert("\"x\"", &[2], '"'));
+        assert_eq!("[", insert("", &[0], '['));
+    }
+
+    #[test]
+    fn buffer_view_transform_lines() {
+        let mut ctx = TestContext::with_buffer("c\nb\na\n\nz\ny\nx");
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 0),
+            position: BufferPosition::line_col(1, 0),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(2, 0),
+            position: BufferPosition::line_col(2, 0),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(6, 1),
+            position: BufferPosition::line_col(4, 0),
+        });
+        drop(cursors);
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        events.flip();
+        buffer_view.transform_lines(&mut ctx.buffers, &mut word_database, &mut events, |lines| {
+            lines.sort()
+        });
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!("b\nc\na\n\nx\ny\nz", buffer.content().to_string());
+
+        events.flip();
+        let mut fixed_cursors = None;
+        let mut events_iter = EditorEventIter::new();
+        while let Some(event) = events_iter.next(&events) {
+            if let EditorEvent::FixCursors { cursors, .. } = event {
+                fixed_cursors = Some(cursors.as_cursors(&events).to_vec());
+            }
+        }
+        assert_eq!(
+            Some(vec![
+                Cursor {
+                    anchor: BufferPosition::line_col(0, 0),
+                    position: BufferPosition::line_col(1, 1),
+                },
+                Cursor {
+                    anchor: BufferPosition::line_col(2, 0),
+                    position: BufferPosition::line_col(2, 1),
+                },
+                Cursor {
+                    anchor: BufferPosition::line_col(4, 0),
+                    position: BufferPosition::line_col(6, 1),
+                },
+            ]),
+            fixed_cursors
+        );
+    }
+
+    #[test]
+    fn buffer_view_insert_snippet() {
+        let mut ctx = TestContext::with_buffer("  ab\n  c");
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 4),
+            position: BufferPosition::line_col(0, 4),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(1, 3),
+            position: BufferPosition::line_col(1, 3),
+        });
+        drop(cursors);
+
+        let snippet = Snippet::parse("f(${1:x}) {\n$0\n}", false);
+        let positions = [
+            BufferPosition::line_col(0, 2),
+            BufferPosition::line_col(1, 2),
+        ];
+        let mut stop_ranges = Vec::new();
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        buffer_view.insert_snippet(
+            &mut ctx.buffers,
+            &mut word_database,
+            &snippet,
+            &positions,
+            &mut stop_ranges,
+            &mut events,
+        );
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!(
+            "  f(x) {\n  \n  }\n  f(x) {\n  \n  }",
+            buffer.content().to_string()
+        );
+
+        let range = |from: (u32, u32), to: (u32, u32)| {
+            BufferRange::between(
+                BufferPosition::line_col(from.0, from.1),
+                BufferPosition::line_col(to.0, to.1),
+            )
+        };
+        stop_ranges.sort_by_key(|(_, r)| r.from);
+        assert_eq!(
+            vec![
+                (0, range((0, 4), (0, 5))),
+                (1, range((1, 2), (1, 2))),
+                (0, range((3, 4), (3, 5))),
+                (1, range((4, 2), (4, 2))),
+            ],
+            stop_ranges
+        );
+    }
 }
@@ -0,0 +1,217 @@
+use std::str::FromStr;
+
+pub static CASE_NAMES: &[&str] = &[
+    "upper",
+    "lower",
+    "title",
+    "snake",
+    "camel",
+    "pascal",
+    "kebab",
+    "screaming",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    Upper,
+    Lower,
+    Title,
+    Snake,
+    Camel,
+    Pascal,
+    Kebab,
+    Screaming,
+}
+impl Case {
+    pub fn convert(self, text: &str, output: &mut String) {
+        match self {
+            Self::Upper => output.extend(text.chars().flat_map(char::to_uppercase)),
+            Self::Lower => output.extend(text.chars().flat_map(char::to_lowercase)),
+            Self::Title => {
+                let mut is_word_start = true;
+                for c in text.chars() {
+                    if is_word_start {
+                        output.extend(c.to_uppercase());
+                    } else {
+                        output.extend(c.to_lowercase());
+                    }
+                    is_word_start = !c.is_alphanumeric();
+                }
+            }
+            _ => {
+                let mut rest = text;
+                while let Some(from) = rest.find(char::is_alphanumeric) {
+                    output.push_str(&rest[..from]);
+                    rest = &rest[from..];
+                    let len = identifier_len(rest);
+                    self.convert_identifier(&rest[..len], output);
+                    rest = &rest[len..];
+                }
+                output.push_str(rest);
+            }
+        }
+    }
+
+    fn convert_identifier(self, identifier: &str, output: &mut String) {
+        fn push_capitalized(word: &str, output: &mut String) {
+            let mut chars = word.chars();
+            if let Some(c) = chars.next() {
+                output.extend(c.to_uppercase());
+            }
+            output.extend(chars.flat_map(char::to_lowercase));
+        }
+
+        let separator = match self {
+            Self::Snake | Self::Screaming => "_",
+            Self::Kebab => "-",
+            _ => "",
+        };
+
+        for (i, word) in IdentifierWords(identifier).enumerate() {
+            if i > 0 {
+                output.push_str(separator);
+            }
+            match self {
+                Self::Screaming => output.extend(word.chars().flat_map(char::to_uppercase)),
+                Self::Pascal => push_capitalized(word, output),
+                Self::Camel if i > 0 => push_capitalized(word, output),
+                _ => output.extend(word.chars().flat_map(char::to_lowercase)),
+            }
+        }
+    }
+}
+impl FromStr for Case {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upper" => Ok(Self::Upper),
+            "lower" => Ok(Self::Lower),
+            "title" => Ok(Self::Title),
+            "snake" => Ok(Self::Snake),
+            "camel" => Ok(Self::Camel),
+            "pascal" => Ok(Self::Pascal),
+            "kebab" => Ok(Self::Kebab),
+            "screaming" => Ok(Self::Screaming),
+            _ => Err(()),
+        }
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '_' | '-' | ' ')
+}
+
+// an identifier is a run of alphanumeric words joined by separators (like `foo_bar-baz qux`)
+fn identifier_len(text: &str) -> usize {
+    let mut len = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c.is_alphanumeric() {
+            len = i + c.len_utf8();
+        } else if !is_separator(c) {
+            break;
+        } else if let Some(&(_, next)) = chars.peek() {
+            if !next.is_alphanumeric() && !is_separator(next) {
+                break;
+            }
+        }
+    }
+    len
+}
+
+struct IdentifierWords<'a>(&'a str);
+impl<'a> Iterator for IdentifierWords<'a> {
+    type Item = &'a str;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0 = self.0.trim_start_matches(is_separator);
+        let mut chars = self.0.char_indices().peekable();
+        let mut previous = None;
+        let mut len = self.0.len();
+        while let Some((i, c)) = chars.next() {
+            if is_separator(c) {
+                len = i;
+                break;
+            }
+            if let Some(p) = previous {
+                let next_is_lowercase = matches!(chars.peek(), Some((_, n)) if n.is_lowercase());
+                let is_word_start = c.is_uppercase()
+                    && (is_lowercase_or_digit(p) || (p.is_uppercase() && next_is_lowercase));
+                if is_word_start {
+                    len = i;
+                    break;
+                }
+            }
+            previous = Some(c);
+        }
+
+        if len == 0 {
+            return None;
+        }
+        let (word, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(word)
+    }
+}
+
+fn is_lowercase_or_digit(c: char) -> bool {
+    c.is_lowercase() || c.is_numeric()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(case: Case, text: &str) -> String {
+        let mut output = String::new();
+        case.convert(text, &mut output);
+        output
+    }
+
+    #[test]
+    fn identifier_words() {
+        fn words(identifier: &str) -> Vec<&str> {
+            IdentifierWords(identifier).collect()
+        }
+
+        assert_eq!(vec!["foo"], words("foo"));
+        assert_eq!(vec!["foo", "Bar"], words("fooBar"));
+        assert_eq!(vec!["Foo", "Bar"], words("FooBar"));
+        assert_eq!(vec!["foo", "bar", "baz"], words("foo_bar-baz"));
+        assert_eq!(vec!["HTTP", "Server"], words("HTTPServer"));
+        assert_eq!(vec!["get", "HTTP"], words("getHTTP"));
+        assert_eq!(vec!["v2", "Api"], words("v2Api"));
+        assert_eq!(vec!["FOO", "BAR"], words("FOO_BAR"));
+    }
+
+    #[test]
+    fn case_conversion() {
+        assert_eq!("FOO_BAR", convert(Case::Upper, "foo_bar"));
+        assert_eq!("foobar", convert(Case::Lower, "fooBar"));
+        assert_eq!("Hello World-Wide", convert(Case::Title, "hello wORLD-wide"));
+
+        let text = "myHTTPServer.start(foo_bar, \"some value\")";
+        assert_eq!(
+            "my_http_server.start(foo_bar, \"some_value\")",
+            convert(Case::Snake, text)
+        );
+        assert_eq!(
+            "myHttpServer.start(fooBar, \"someValue\")",
+            convert(Case::Camel, text)
+        );
+        assert_eq!(
+            "MyHttpServer.Start(FooBar, \"SomeValue\")",
+            convert(Case::Pascal, text)
+        );
+        assert_eq!(
+            "my-http-server.start(foo-bar, \"some-value\")",
+            convert(Case::Kebab, text)
+        );
+        assert_eq!(
+            "MY_HTTP_SERVER.START(FOO_BAR, \"SOME_VALUE\")",
+            convert(Case::Screaming, text)
+        );
+
+        assert_eq!("_foo_bar_ ", convert(Case::Snake, "_fooBar_ "));
+        assert_eq!("A, B", convert(Case::Pascal, "a, b"));
+    }
+}
@@ -1,7 +1,7 @@
-use std::fmt;
+use std::{fmt, ops::Range};
 
 use crate::{
-    buffer::{BufferHandle, BufferProperties, CharDisplayDistances},
+    buffer::{BufferHandle, BufferProperties, BufferProvider, CharDisplayDistances},
     buffer_position::BufferPositionIndex,
     buffer_view::{BufferViewCollection, BufferViewHandle},
     editor::Editor,
@@ -161,6 +161,44 @@ impl Client {
         }
     }
 
+    // buffer lines that are at least partially shown in this client's viewport
+    pub(crate) fn visible_line_range(&self, editor: &Editor) -> Range<usize> {
+        let buffer_view_handle = match self.buffer_view_handle() {
+            Some(handle) => handle,
+            None => return 0..0,
+        };
+        if !self.has_ui() {
+            return 0..0;
+        }
+
+        let tab_size = editor.config.tab_size.get();
+        let width = self.viewport_size.0 as usize;
+        let height = self.viewport_size.1.saturating_sub(1) as usize;
+        let scroll = self.scroll as usize;
+
+        let buffer_view = editor.buffer_views.get(buffer_view_handle);
+        let lines = editor
+            .buffers
+            .get(buffer_view.buffer_handle)
+            .content()
+            .lines();
+
+        let mut range = lines.len()..lines.len();
+        let mut line_top = 0;
+        for (i, line) in lines.iter().enumerate() {
+            let line_height = 1 + line.display_len().total_len(tab_size) / width;
+            if range.start == lines.len() && line_top + line_height > scroll {
+                range.start = i;
+            }
+            line_top += line_height;
+            if line_top >= scroll + height {
+                range.end = i + 1;
+                break;
+            }
+        }
+        range
+    }
+
     pub(crate) fn on_stdin_input(&mut self, editor: &mut Editor, bytes: &[u8]) {
         let mut buf = Default::default();
         let texts = self.stdin_residual_bytes.receive_bytes(&mut buf, bytes);
@@ -179,7 +217,7 @@ impl Client {
                 editor.string_pool.release(path);
 
                 buffer.properties = BufferProperties::text();
-                buffer.properties.is_file = false;
+                buffer.properties.provider = BufferProvider::None;
 
                 let buffer_view_handle =
                     editor.buffer_views.add_new(self.handle(), buffer.handle());
@@ -234,8 +272,8 @@ impl Client {
         let position = buffer_view.cursors.main_cursor().position;
 
         let mut height = position.line_index as usize;
-        for display_len in &buffer.line_display_lens()[..position.line_index as usize] {
-            height += display_len.total_len(tab_size) / width;
+        for line in buffer.lines().range(..position.line_index as usize) {
+            height += line.display_len().total_len(tab_size) / width;
         }
 
         let cursor_line = buffer.lines()[position.line_index as usize].as_str();
@@ -24,9 +24,21 @@ pub enum CommandError {
     NoTargetClient,
     NoBufferOpened,
     UnsavedChanges,
+    FileChangedOnDisk,
     BufferReadError(BufferReadError),
     BufferWriteError(BufferWriteError),
     NoSuchBufferProperty,
+    NoSuchLineEnding,
+    InvalidBomValue,
+    InvalidLargeFileValue,
+    NoSuchEncoding,
+    NoSuchCase,
+    InvalidPairs,
+    NoSuchSortOption,
+    InvalidRotateCount,
+    NoSuchUndoBranch,
+    InvalidHistoryTravel,
+    NoSwapFile,
     ConfigError(ParseConfigError),
     NoSuchColor,
     InvalidColorValue,
@@ -46,9 +58,21 @@ impl fmt::Display for CommandError {
             Self::NoTargetClient => f.write_str("no target client"),
             Self::NoBufferOpened => f.write_str("no buffer opened"),
             Self::UnsavedChanges => f.write_str("unsaved changes"),
+            Self::FileChangedOnDisk => f.write_str("file changed on disk"),
             Self::BufferReadError(error) => error.fmt(f),
             Self::BufferWriteError(error) => error.fmt(f),
             Self::NoSuchBufferProperty => f.write_str("no such buffer property"),
+            Self::NoSuchLineEnding => f.write_str("no such line ending"),
+            Self::InvalidBomValue => f.write_str("invalid bom value"),
+            Self::InvalidLargeFileValue => f.write_str("invalid large file value"),
+            Self::NoSuchEncoding => f.write_str("no such encoding"),
+            Self::NoSuchCase => f.write_str("no such case"),
+            Self::NoSuchSortOption => f.write_str("no such sort option"),
+            Self::InvalidRotateCount => f.write_str("invalid rotate count"),
+            Self::InvalidPairs => f.write_str("pairs must have an even number of chars"),
+            Self::NoSuchUndoBranch => f.write_str("no such undo branch"),
+            Self::InvalidHistoryTravel => f.write_str("invalid count or duration"),
+            Self::NoSwapFile => f.write_str("no swap file"),
             Self::ConfigError(error) => error.fmt(f),
             Self::NoSuchColor => f.write_str("no such color"),
             Self::InvalidColorValue => f.write_str("invalid color value"),
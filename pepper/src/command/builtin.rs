@@ -1,18 +1,28 @@
-use std::path::Path;
+use std::{path::Path, time::SystemTime};
 
 use crate::{
-    buffer::{parse_path_and_position, BufferProperties},
+    buffer::{
+        parse_path_and_position, swap_file_path, Buffer, BufferCollection, BufferProperties,
+        BufferProvider,
+    },
+    buffer_history::{BufferHistory, HistoryTravel},
     buffer_position::BufferPosition,
+    buffer_view::BufferView,
+    case::{Case, CASE_NAMES},
     client::ViewAnchor,
     command::{CommandError, CommandIO, CommandManager, CompletionSource},
     config::{ParseConfigError, CONFIG_NAMES},
-    cursor::Cursor,
+    cursor::{Cursor, CursorCollection},
     editor::{EditorContext, EditorFlow},
     editor_utils::MessageKind,
+    encoding::{Encoding, ENCODING_NAMES},
+    events::EditorEventQueue,
     help,
     mode::{picker, read_line, ModeKind},
+    sort::{self, SortOptions, SORT_OPTION_NAMES},
     syntax::TokenKind,
     theme::{Color, THEME_COLOR_NAMES},
+    word_database::WordDatabase,
 };
 
 pub fn register_commands(commands: &mut CommandManager) {
@@ -105,7 +115,22 @@ pub fn register_commands(commands: &mut CommandManager) {
                 "saving-disabled" => properties.saving_enabled = false,
                 "word-database-enabled" => properties.word_database_enabled = true,
                 "word-database-disabled" => properties.word_database_enabled = false,
-                _ => return Err(CommandError::NoSuchBufferProperty),
+                "syntax-enabled" => properties.syntax_enabled = true,
+                "syntax-disabled" => properties.syntax_enabled = false,
+                "read-only" => properties.read_only = true,
+                "read-write" => properties.read_only = false,
+                "hex" => {
+                    properties.encoding = Encoding::Hex;
+                    properties.word_database_enabled = false;
+                    properties.syntax_enabled = false;
+                }
+                _ => match property
+                    .strip_prefix("encoding-")
+                    .and_then(|e| e.parse().ok())
+                {
+                    Some(encoding) => properties.encoding = encoding,
+                    None => return Err(CommandError::NoSuchBufferProperty),
+                },
             }
         }
 
@@ -146,37 +171,99 @@ pub fn register_commands(commands: &mut CommandManager) {
         let path = io.args.try_next().map(|p| Path::new(p));
         io.args.assert_empty()?;
 
+        let provider = path.map(|p| match ctx.editor.buffers.find_provider(p) {
+            Some(plugin_handle) => BufferProvider::Plugin(plugin_handle),
+            None => BufferProvider::File,
+        });
+
         let buffer_handle = io.current_buffer_handle(ctx)?;
         let buffer = ctx.editor.buffers.get_mut(buffer_handle);
+        if let Some(provider) = provider {
+            buffer.properties.provider = provider;
+        }
+        if !io.bang && path.is_none() && buffer.file_changed_on_disk() {
+            return Err(CommandError::FileChangedOnDisk);
+        }
+
+        if !io.bang && path.is_none() && buffer.is_directory() {
+            let mut changes = Vec::new();
+            buffer.directory_changes(&mut changes);
+            if !changes.is_empty() {
+                let mut write = ctx.editor.status_bar.write(MessageKind::Info);
+                write.str("directory changes:");
+                for change in &changes {
+                    write.fmt(format_args!("\n  {}", change));
+                }
+                write.str("\nuse `save!` to apply them");
+                return Ok(());
+            }
+        }
 
         buffer
-            .write_to_file(path, &mut ctx.editor.events)
+            .write_to_file(
+                path,
+                ctx.editor.config.backup_on_save,
+                &mut ctx.editor.events,
+            )
             .map_err(CommandError::BufferWriteError)?;
 
+        let message = if buffer.needs_save() {
+            "writing buffer to"
+        } else {
+            "buffer saved to"
+        };
         ctx.editor
             .status_bar
             .write(MessageKind::Info)
-            .fmt(format_args!("buffer saved to {:?}", &buffer.path));
+            .fmt(format_args!("{} {:?}", message, &buffer.path));
         Ok(())
     });
 
     r("save-all", &[], |ctx, io| {
         io.args.assert_empty()?;
 
+        // directory changes are only applied through `save!` after they were shown by `save`
+        fn will_write(buffer: &Buffer) -> bool {
+            buffer.properties.saving_enabled && !buffer.is_directory()
+        }
+
+        if !io.bang
+            && ctx
+                .editor
+                .buffers
+                .iter()
+                .any(|b| will_write(b) && b.file_changed_on_disk())
+        {
+            return Err(CommandError::FileChangedOnDisk);
+        }
+
         let mut count = 0;
+        let mut skipped_directories = 0;
         for buffer in ctx.editor.buffers.iter_mut() {
-            if buffer.properties.saving_enabled {
+            if buffer.is_directory() {
+                if buffer.needs_save() {
+                    skipped_directories += 1;
+                }
+            } else if will_write(buffer) {
                 buffer
-                    .write_to_file(None, &mut ctx.editor.events)
+                    .write_to_file(
+                        None,
+                        ctx.editor.config.backup_on_save,
+                        &mut ctx.editor.events,
+                    )
                     .map_err(CommandError::BufferWriteError)?;
                 count += 1;
             }
         }
 
-        ctx.editor
-            .status_bar
-            .write(MessageKind::Info)
-            .fmt(format_args!("{} buffers saved", count));
+        let mut write = ctx.editor.status_bar.write(MessageKind::Info);
+        write.fmt(format_args!("{} buffers saved", count));
+        if skipped_directories > 0 {
+            write.fmt(format_args!(
+                ". {} directory buffers with changes were skipped. use `save` on them to review their changes",
+                skipped_directories
+            ));
+        }
         Ok(())
     });
 
@@ -253,6 +340,324 @@ pub fn register_commands(commands: &mut CommandManager) {
         Ok(())
     });
 
+    r("recover", &[CompletionSource::Files], |ctx, io| {
+        let path = io.args.try_next();
+        io.args.assert_empty()?;
+
+        let path = match path {
+            Some(path) => path,
+            None => {
+                picker::recover::enter_mode(ctx);
+                return Ok(());
+            }
+        };
+
+        let recovery_directory = Path::new(&ctx.editor.config.recovery_directory);
+        if recovery_directory.as_os_str().is_empty()
+            || !swap_file_path(
+                &ctx.editor.current_directory,
+                recovery_directory,
+                Path::new(path),
+            )
+            .exists()
+        {
+            return Err(CommandError::NoSwapFile);
+        }
+
+        let client_handle = io.client_handle()?;
+        let handle = ctx
+            .editor
+            .recover_buffer_view_handle(client_handle, Path::new(path))
+            .map_err(CommandError::BufferReadError)?;
+        let client = ctx.clients.get_mut(client_handle);
+        client.set_buffer_view_handle(Some(handle), &ctx.editor.buffer_views);
+        Ok(())
+    });
+
+    static ENCODING_COMPLETIONS: &[CompletionSource] = &[CompletionSource::Custom(ENCODING_NAMES)];
+    r("registers", &[], |ctx, io| {
+        io.args.assert_empty()?;
+        picker::registers::enter_mode(ctx);
+        Ok(())
+    });
+
+    r("encoding", ENCODING_COMPLETIONS, |ctx, io| {
+        let encoding = io.args.try_next();
+        io.args.assert_empty()?;
+
+        let buffer_handle = io.current_buffer_handle(ctx)?;
+
+        match encoding {
+            Some(encoding) => {
+                let encoding: Encoding =
+                    encoding.parse().map_err(|_| CommandError::NoSuchEncoding)?;
+                let current_encoding = ctx.editor.buffers.get(buffer_handle).properties.encoding;
+                if (current_encoding == Encoding::Hex) != (encoding == Encoding::Hex) {
+                    io.assert_can_discard_buffer(ctx, buffer_handle)?;
+                    ctx.editor
+                        .buffers
+                        .get_mut(buffer_handle)
+                        .reopen_with_encoding(
+                            encoding,
+                            &mut ctx.editor.word_database,
+                            &mut ctx.editor.events,
+                        )
+                        .map_err(CommandError::BufferReadError)?;
+                } else {
+                    ctx.editor
+                        .buffers
+                        .get_mut(buffer_handle)
+                        .set_encoding(encoding);
+                }
+            }
+            None => ctx.editor.status_bar.write(MessageKind::Info).str(
+                ctx.editor
+                    .buffers
+                    .get(buffer_handle)
+                    .properties
+                    .encoding
+                    .name(),
+            ),
+        }
+
+        Ok(())
+    });
+
+    static LINE_ENDING_COMPLETIONS: &[CompletionSource] =
+        &[CompletionSource::Custom(&["lf", "crlf"])];
+    r("line-ending", LINE_ENDING_COMPLETIONS, |ctx, io| {
+        let line_ending = io.args.try_next();
+        io.args.assert_empty()?;
+
+        let buffer_handle = io.current_buffer_handle(ctx)?;
+        let buffer = ctx.editor.buffers.get_mut(buffer_handle);
+
+        match line_ending {
+            Some(line_ending) => {
+                let line_ending = line_ending
+                    .parse()
+                    .map_err(|_| CommandError::NoSuchLineEnding)?;
+                buffer.set_line_ending(line_ending);
+            }
+            None => {
+                let content = buffer.content();
+                let mixed = if content.has_mixed_line_endings() {
+                    " (mixed)"
+                } else {
+                    ""
+                };
+                ctx.editor
+                    .status_bar
+                    .write(MessageKind::Info)
+                    .fmt(format_args!("{}{}", content.line_ending().name(), mixed));
+            }
+        }
+
+        Ok(())
+    });
+
+    static BOM_COMPLETIONS: &[CompletionSource] =
+        &[CompletionSource::Custom(&["enabled", "disabled"])];
+    r("bom", BOM_COMPLETIONS, |ctx, io| {
+        let value = io.args.try_next();
+        io.args.assert_empty()?;
+
+        let buffer_handle = io.current_buffer_handle(ctx)?;
+        let buffer = ctx.editor.buffers.get_mut(buffer_handle);
+
+        match value {
+            Some("enabled") => buffer.set_bom(true),
+            Some("disabled") => buffer.set_bom(false),
+            Some(_) => return Err(CommandError::InvalidBomValue),
+            None => {
+                let value = if buffer.content().has_bom() {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+                ctx.editor.status_bar.write(MessageKind::Info).str(value);
+            }
+        }
+
+        Ok(())
+    });
+
+    static LARGE_FILE_COMPLETIONS: &[CompletionSource] =
+        &[CompletionSource::Custom(&["enabled", "disabled"])];
+    r("large-file", LARGE_FILE_COMPLETIONS, |ctx, io| {
+        let value = io.args.try_next();
+        io.args.assert_empty()?;
+
+        let buffer_handle = io.current_buffer_handle(ctx)?;
+        let buffer = ctx.editor.buffers.get_mut(buffer_handle);
+        let history_enabled = ctx.editor.config.large_file_history;
+
+        match value {
+            Some("enabled") => {
+                buffer.set_large_file(true, history_enabled, &mut ctx.editor.word_database)
+            }
+            Some("disabled") => {
+                buffer.set_large_file(false, history_enabled, &mut ctx.editor.word_database)
+            }
+            Some(_) => return Err(CommandError::InvalidLargeFileValue),
+            None => {
+                let value = if buffer.is_large_file() {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+                ctx.editor.status_bar.write(MessageKind::Info).str(value);
+            }
+        }
+
+        Ok(())
+    });
+
+    r("undo-branches", &[], |ctx, io| {
+        io.args.assert_empty()?;
+
+        let buffer_handle = io.current_buffer_handle(ctx)?;
+        let buffer = ctx.editor.buffers.get_mut(buffer_handle);
+        buffer.commit_edits();
+
+        let history = buffer.history();
+        let current_branch = history.current_branch();
+        let now = SystemTime::now();
+
+        let mut write = ctx.editor.status_bar.write(MessageKind::Info);
+        for (i, branch) in history.branches().enumerate() {
+            if i > 0 {
+                write.str("\n");
+            }
+            let elapsed = now
+                .duration_since(history.group_time(branch))
+                .unwrap_or_default();
+            let current = if current_branch == Some(branch) {
+                " (current)"
+            } else {
+                ""
+            };
+            write.fmt(format_args!(
+                "{}: {} changes, {} ago{}",
+                i + 1,
+                history.group_depth(Some(branch)),
+                HistoryTravel::Time(elapsed),
+                current,
+            ));
+        }
+        if current_branch.is_none() {
+            write.str("no undo history");
+        }
+
+        Ok(())
+    });
+
+    r("undo-branch", &[], |ctx, io| {
+        let index = io.args.next()?;
+        io.args.assert_empty()?;
+
+        let buffer_view_handle = io.current_buffer_view_handle(ctx)?;
+        let buffer_view = ctx.editor.buffer_views.get_mut(buffer_view_handle);
+        let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+        buffer.commit_edits();
+
+        let branch = index
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| i.checked_sub(1))
+            .and_then(|i| buffer.history().branches().nth(i))
+            .ok_or(CommandError::NoSuchUndoBranch)?;
+
+        buffer_view.jump_history(
+            &mut ctx.editor.buffers,
+            &mut ctx.editor.word_database,
+            Some(branch),
+            &mut ctx.editor.events,
+        );
+        Ok(())
+    });
+
+    r("earlier", &[], |ctx, io| {
+        travel_history(ctx, io, BufferHistory::earlier_group)
+    });
+    r("later", &[], |ctx, io| {
+        travel_history(ctx, io, BufferHistory::later_group)
+    });
+
+    static CONVERT_CASE_COMPLETIONS: &[CompletionSource] = &[CompletionSource::Custom(CASE_NAMES)];
+    r("convert-case", CONVERT_CASE_COMPLETIONS, |ctx, io| {
+        let case = io.args.next()?;
+        io.args.assert_empty()?;
+
+        let case: Case = case.parse().map_err(|_| CommandError::NoSuchCase)?;
+
+        edit_current_buffer_view(ctx, io, |buffer_view, buffers, word_database, events| {
+            buffer_view.transform_selections(buffers, word_database, events, |_, text, output| {
+                case.convert(text, output)
+            });
+        })
+    });
+
+    static SORT_COMPLETIONS: &[CompletionSource] = &[
+        CompletionSource::Custom(SORT_OPTION_NAMES),
+        CompletionSource::Custom(SORT_OPTION_NAMES),
+        CompletionSource::Custom(SORT_OPTION_NAMES),
+        CompletionSource::Custom(SORT_OPTION_NAMES),
+    ];
+    r("sort-lines", SORT_COMPLETIONS, |ctx, io| {
+        let options = parse_sort_options(io)?;
+        edit_current_buffer_view(ctx, io, |buffer_view, buffers, word_database, events| {
+            buffer_view.transform_lines(buffers, word_database, events, |lines| {
+                sort::sort(lines, options)
+            });
+        })
+    });
+
+    r("unique-lines", &[], |ctx, io| {
+        io.args.assert_empty()?;
+        edit_current_buffer_view(ctx, io, |buffer_view, buffers, word_database, events| {
+            buffer_view.transform_lines(buffers, word_database, events, sort::unique);
+        })
+    });
+
+    r("reverse-lines", &[], |ctx, io| {
+        io.args.assert_empty()?;
+        edit_current_buffer_view(ctx, io, |buffer_view, buffers, word_database, events| {
+            buffer_view.transform_lines(buffers, word_database, events, |lines| lines.reverse());
+        })
+    });
+
+    r("sort-selections", SORT_COMPLETIONS, |ctx, io| {
+        let options = parse_sort_options(io)?;
+        if options.unique {
+            return Err(CommandError::NoSuchSortOption);
+        }
+        permute_selections(ctx, io, |texts| sort::sort(texts, options))
+    });
+
+    r("reverse-selections", &[], |ctx, io| {
+        io.args.assert_empty()?;
+        permute_selections(ctx, io, |texts| texts.reverse())
+    });
+
+    r("rotate-selections", &[], |ctx, io| {
+        let count = match io.args.try_next() {
+            Some(count) => count
+                .parse::<isize>()
+                .map_err(|_| CommandError::InvalidRotateCount)?,
+            None => 1,
+        };
+        io.args.assert_empty()?;
+        permute_selections(ctx, io, |texts| {
+            if texts.is_empty() {
+                return;
+            }
+            let len = texts.len() as isize;
+            let count = ((count % len + len) % len) as usize;
+            texts.rotate_right(count);
+        })
+    });
+
     static CONFIG_COMPLETIONS: &[CompletionSource] = &[CompletionSource::Custom(CONFIG_NAMES)];
     r("config", CONFIG_COMPLETIONS, |ctx, io| {
         let key = io.args.next()?;
@@ -353,6 +758,65 @@ pub fn register_commands(commands: &mut CommandManager) {
     r("syntax-texts", &[], |ctx, io| {
         syntax_pattern(ctx, io, TokenKind::Text)
     });
+    r("syntax-line-comment", &[], |ctx, io| {
+        let prefix = io.args.next()?;
+        io.args.assert_empty()?;
+        ctx.editor.syntaxes.get_current().set_line_comment(prefix);
+        Ok(())
+    });
+    r("syntax-block-comment", &[], |ctx, io| {
+        let open = io.args.next()?;
+        let close = io.args.next()?;
+        io.args.assert_empty()?;
+        ctx.editor
+            .syntaxes
+            .get_current()
+            .set_block_comment(open, close);
+        Ok(())
+    });
+    r("syntax-indent", &[], |ctx, io| {
+        let pattern = io.args.next()?;
+        io.args.assert_empty()?;
+        match ctx
+            .editor
+            .syntaxes
+            .get_current()
+            .set_indent_pattern(pattern)
+        {
+            Ok(()) => Ok(()),
+            Err(error) => Err(CommandError::PatternError(error)),
+        }
+    });
+    r("syntax-dedent", &[], |ctx, io| {
+        let pattern = io.args.next()?;
+        io.args.assert_empty()?;
+        match ctx
+            .editor
+            .syntaxes
+            .get_current()
+            .set_dedent_pattern(pattern)
+        {
+            Ok(()) => Ok(()),
+            Err(error) => Err(CommandError::PatternError(error)),
+        }
+    });
+    r("syntax-pairs", &[], |ctx, io| {
+        let pairs = io.args.next()?;
+        io.args.assert_empty()?;
+        if pairs.chars().count() % 2 != 0 {
+            return Err(CommandError::InvalidPairs);
+        }
+        ctx.editor.syntaxes.get_current().set_pairs(pairs);
+        Ok(())
+    });
+
+    r("syntax-snippet", &[], |ctx, io| {
+        let name = io.args.next()?;
+        let body = io.args.next()?;
+        io.args.assert_empty()?;
+        ctx.editor.syntaxes.get_current().set_snippet(name, body);
+        Ok(())
+    });
 
     r("copy-command", &[], |ctx, io| {
         let command = io.args.next()?;
@@ -418,6 +882,99 @@ fn map(ctx: &mut EditorContext, io: &mut CommandIO, mode: ModeKind) -> Result<()
     }
 }
 
+fn travel_history(
+    ctx: &mut EditorContext,
+    io: &mut CommandIO,
+    target_group_fn: fn(&BufferHistory, HistoryTravel) -> Option<usize>,
+) -> Result<(), CommandError> {
+    let travel = match io.args.try_next() {
+        Some(travel) => travel
+            .parse()
+            .map_err(|_| CommandError::InvalidHistoryTravel)?,
+        None => HistoryTravel::Groups(1),
+    };
+    io.args.assert_empty()?;
+
+    let buffer_view_handle = io.current_buffer_view_handle(ctx)?;
+    let buffer_view = ctx.editor.buffer_views.get_mut(buffer_view_handle);
+    let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+    buffer.commit_edits();
+
+    let group_index = target_group_fn(buffer.history(), travel);
+    buffer_view.jump_history(
+        &mut ctx.editor.buffers,
+        &mut ctx.editor.word_database,
+        group_index,
+        &mut ctx.editor.events,
+    );
+    Ok(())
+}
+
+fn edit_current_buffer_view<F>(
+    ctx: &mut EditorContext,
+    io: &mut CommandIO,
+    edit: F,
+) -> Result<(), CommandError>
+where
+    F: FnOnce(&BufferView, &mut BufferCollection, &mut WordDatabase, &mut EditorEventQueue),
+{
+    let buffer_view_handle = io.current_buffer_view_handle(ctx)?;
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+    buffer.commit_edits();
+    edit(
+        buffer_view,
+        &mut ctx.editor.buffers,
+        &mut ctx.editor.word_database,
+        &mut ctx.editor.events,
+    );
+    ctx.editor
+        .buffers
+        .get_mut(buffer_view.buffer_handle)
+        .commit_edits();
+    Ok(())
+}
+
+fn parse_sort_options(io: &mut CommandIO) -> Result<SortOptions, CommandError> {
+    let mut options = SortOptions::default();
+    while let Some(option) = io.args.try_next() {
+        if !options.parse_option(option) {
+            return Err(CommandError::NoSuchSortOption);
+        }
+    }
+    Ok(options)
+}
+
+fn permute_selections<F>(
+    ctx: &mut EditorContext,
+    io: &mut CommandIO,
+    permute: F,
+) -> Result<(), CommandError>
+where
+    F: FnOnce(&mut Vec<&str>),
+{
+    let buffer_view_handle = io.current_buffer_view_handle(ctx)?;
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+
+    let mut text = ctx.editor.string_pool.acquire();
+    let mut ranges = [(0, 0); CursorCollection::capacity()];
+    let len = buffer_view.append_selection_text(&ctx.editor.buffers, &mut text, &mut ranges);
+    let mut texts: Vec<_> = ranges[..len]
+        .iter()
+        .map(|&(from, to)| &text[from as usize..to as usize])
+        .collect();
+    permute(&mut texts);
+
+    let result =
+        edit_current_buffer_view(ctx, io, |buffer_view, buffers, word_database, events| {
+            buffer_view.transform_selections(buffers, word_database, events, |i, _, output| {
+                output.push_str(texts[i])
+            });
+        });
+    ctx.editor.string_pool.release(text);
+    result
+}
+
 fn syntax_pattern(
     ctx: &mut EditorContext,
     io: &mut CommandIO,
@@ -68,6 +68,7 @@ macro_rules! config_values {
 config_values! {
     tab_size: NonZeroU8 = NonZeroU8::new(4).unwrap(),
     indent_with_tabs: bool = true,
+    auto_pairs: bool = true,
 
     visual_empty: char = '~',
     visual_space: char = '.',
@@ -76,4 +77,13 @@ config_values! {
 
     completion_min_len: u8 = 3,
     picker_max_height: u8 = 8,
+
+    backup_on_save: bool = false,
+    undo_directory: String = String::new(),
+    recovery_directory: String = String::new(),
+    state_file: String = String::new(),
+
+    large_file_size: u32 = 16 * 1024 * 1024,
+    large_file_line_count: u32 = 500_000,
+    large_file_history: bool = false,
 }
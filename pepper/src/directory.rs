@@ -0,0 +1,274 @@
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+pub fn read_directory_entries(path: &Path, entries: &mut Vec<String>) -> io::Result<()> {
+    entries.clear();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let mut name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(name) => name.to_string_lossy().into_owned(),
+        };
+        if entry.file_type()?.is_dir() {
+            name.push('/');
+        }
+        entries.push(name);
+    }
+    entries.sort_unstable_by(|a, b| {
+        let a_is_dir = a.ends_with('/');
+        let b_is_dir = b.ends_with('/');
+        b_is_dir.cmp(&a_is_dir).then_with(|| a.cmp(b))
+    });
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DirectoryChange<'a> {
+    Create(&'a str),
+    Rename { from: &'a str, to: &'a str },
+    Delete(&'a str),
+}
+impl<'a> DirectoryChange<'a> {
+    // created and renamed entries must be plain relative paths so they stay inside the directory
+    pub fn validate(&self) -> Result<(), DirectoryChangeError> {
+        let entry = match *self {
+            Self::Create(entry) | Self::Rename { to: entry, .. } => entry,
+            Self::Delete(_) => return Ok(()),
+        };
+        let path = Path::new(entry);
+        if path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            Ok(())
+        } else {
+            Err(DirectoryChangeError::InvalidEntry(entry.into()))
+        }
+    }
+
+    pub fn apply(&self, directory: &Path) -> Result<(), DirectoryChangeError> {
+        self.validate()?;
+
+        fn entry_path(directory: &Path, entry: &str) -> PathBuf {
+            directory.join(entry.trim_end_matches('/'))
+        }
+
+        fn assert_not_exists(path: &Path) -> Result<(), DirectoryChangeError> {
+            if path.exists() {
+                Err(DirectoryChangeError::AlreadyExists(path.to_path_buf()))
+            } else {
+                Ok(())
+            }
+        }
+
+        fn create_parent(path: &Path) -> io::Result<()> {
+            match path.parent() {
+                Some(parent) => fs::create_dir_all(parent),
+                None => Ok(()),
+            }
+        }
+
+        match *self {
+            Self::Create(entry) => {
+                let path = entry_path(directory, entry);
+                assert_not_exists(&path)?;
+                if entry.ends_with('/') {
+                    fs::create_dir_all(&path)?;
+                } else {
+                    create_parent(&path)?;
+                    fs::File::create(&path)?;
+                }
+            }
+            Self::Rename { from, to } => {
+                let to = entry_path(directory, to);
+                assert_not_exists(&to)?;
+                create_parent(&to)?;
+                fs::rename(entry_path(directory, from), to)?;
+            }
+            Self::Delete(entry) => {
+                let path = entry_path(directory, entry);
+                if entry.ends_with('/') {
+                    if fs::read_dir(&path)?.next().is_some() {
+                        return Err(DirectoryChangeError::NotEmpty(path));
+                    }
+                    fs::remove_dir(path)?;
+                } else {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+impl<'a> fmt::Display for DirectoryChange<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Create(entry) => write!(f, "create {}", entry),
+            Self::Rename { from, to } => write!(f, "rename {} -> {}", from, to),
+            Self::Delete(entry) => write!(f, "delete {}", entry),
+        }
+    }
+}
+
+pub enum DirectoryChangeError {
+    AlreadyExists(PathBuf),
+    NotEmpty(PathBuf),
+    InvalidEntry(String),
+    Io(io::Error),
+}
+impl From<io::Error> for DirectoryChangeError {
+    fn from(other: io::Error) -> Self {
+        Self::Io(other)
+    }
+}
+
+// Entries that were removed and added between the same two unchanged entries
+// are paired up as renames (as long as they're both files or both directories),
+// the same way a line diff would pair them as changes.
+pub fn directory_changes<'a, I>(
+    previous_entries: &'a [String],
+    current_entries: I,
+    changes: &mut Vec<DirectoryChange<'a>>,
+) where
+    I: Clone + Iterator<Item = &'a str>,
+{
+    let previous_set: HashSet<&str> = previous_entries.iter().map(String::as_str).collect();
+    let current_set: HashSet<&str> = current_entries.clone().collect();
+
+    fn unmatched<'a, I>(entries: I, others: &HashSet<&str>) -> Vec<(Option<&'a str>, &'a str)>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut last_common = None;
+        let mut unmatched = Vec::new();
+        for entry in entries {
+            if others.contains(entry) {
+                last_common = Some(entry);
+            } else {
+                unmatched.push((last_common, entry));
+            }
+        }
+        unmatched
+    }
+
+    let deleted = unmatched(previous_entries.iter().map(String::as_str), &current_set);
+    let mut created = unmatched(current_entries, &previous_set);
+
+    changes.clear();
+    let mut renames = Vec::new();
+    for (after, from) in deleted {
+        let is_dir = from.ends_with('/');
+        match created
+            .iter()
+            .position(|&(a, e)| a == after && e.ends_with('/') == is_dir)
+        {
+            Some(i) => {
+                let (_, to) = created.remove(i);
+                renames.push(DirectoryChange::Rename { from, to });
+            }
+            None => changes.push(DirectoryChange::Delete(from)),
+        }
+    }
+    changes.append(&mut renames);
+    changes.extend(created.into_iter().map(|(_, e)| DirectoryChange::Create(e)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes<'a>(previous: &'a [String], current: &'a str) -> Vec<DirectoryChange<'a>> {
+        let mut changes = Vec::new();
+        directory_changes(
+            previous,
+            current.lines().filter(|l| !l.is_empty()),
+            &mut changes,
+        );
+        changes
+    }
+
+    #[test]
+    fn directory_changes_from_listing() {
+        let previous: Vec<String> = ["dir/", "a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|&e| e.into())
+            .collect();
+
+        assert!(changes(&previous, "dir/\na.txt\nb.txt\nc.txt").is_empty());
+        assert!(changes(&previous, "a.txt\nc.txt\ndir/\nb.txt").is_empty());
+
+        assert_eq!(
+            vec![DirectoryChange::Rename {
+                from: "b.txt",
+                to: "renamed.txt"
+            }],
+            changes(&previous, "dir/\na.txt\nrenamed.txt\nc.txt"),
+        );
+        assert_eq!(
+            vec![
+                DirectoryChange::Delete("a.txt"),
+                DirectoryChange::Create("new/"),
+            ],
+            changes(&previous, "dir/\nb.txt\nc.txt\n\nnew/"),
+        );
+        assert_eq!(
+            vec![
+                DirectoryChange::Rename {
+                    from: "dir/",
+                    to: "other/"
+                },
+                DirectoryChange::Rename {
+                    from: "c.txt",
+                    to: "d.txt"
+                },
+            ],
+            changes(&previous, "other/\na.txt\nb.txt\nd.txt\n"),
+        );
+        assert_eq!(
+            vec![
+                DirectoryChange::Delete("dir/"),
+                DirectoryChange::Create("d.txt"),
+            ],
+            changes(&previous, "a.txt\nb.txt\nc.txt\nd.txt"),
+        );
+    }
+
+    #[test]
+    fn validate_directory_changes() {
+        assert!(DirectoryChange::Create("new.txt").validate().is_ok());
+        assert!(DirectoryChange::Create("new/dir/").validate().is_ok());
+        assert!(DirectoryChange::Create("../new.txt").validate().is_err());
+        assert!(DirectoryChange::Create("new/../../x").validate().is_err());
+        assert!(DirectoryChange::Create("/tmp/new.txt").validate().is_err());
+        assert!(DirectoryChange::Rename {
+            from: "a.txt",
+            to: "../a.txt"
+        }
+        .validate()
+        .is_err());
+        assert!(DirectoryChange::Delete("a.txt").validate().is_ok());
+    }
+
+    #[test]
+    fn delete_only_empty_directories() {
+        let directory =
+            std::env::temp_dir().join(format!("pepper-test-directory-{}", std::process::id()));
+        fs::create_dir_all(directory.join("full")).unwrap();
+        fs::create_dir_all(directory.join("empty")).unwrap();
+        fs::write(directory.join("full/file.txt"), "content").unwrap();
+
+        assert!(matches!(
+            DirectoryChange::Delete("full/").apply(&directory),
+            Err(DirectoryChangeError::NotEmpty(_))
+        ));
+        assert!(directory.join("full/file.txt").exists());
+        assert!(DirectoryChange::Delete("empty/").apply(&directory).is_ok());
+        assert!(!directory.join("empty").exists());
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
@@ -1,18 +1,22 @@
 use std::{
-    fmt,
+    fmt, fs, io,
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
 use crate::{
-    buffer::{BufferCollection, BufferProperties, BufferReadError},
+    buffer::{
+        swap_file_path, BufferCollection, BufferProperties, BufferProvider, BufferReadError,
+        SwapFile,
+    },
     buffer_position::{BufferPosition, BufferRange},
     buffer_view::{BufferViewCollection, BufferViewHandle},
     client::{ClientHandle, ClientManager},
     command::CommandManager,
     config::Config,
     editor_utils::{
-        KeyMapCollection, MatchResult, ReadLine, RegisterCollection, RegisterKey, StatusBar,
-        StringPool,
+        write_file_atomically, KeyMapCollection, MatchResult, MessageKind, ReadLine,
+        RegisterCollection, RegisterKey, RegisterKind, StatusBar, StringPool,
     },
     events::{
         ClientEvent, EditorEvent, EditorEventIter, EditorEventQueue, KeyParseAllError, KeyParser,
@@ -23,12 +27,19 @@ use crate::{
     picker::Picker,
     platform::{Key, Platform, PlatformRequest},
     plugin::{PluginCollection, PluginHandle},
+    serialization::{DeserializeError, Serialize},
     syntax::{HighlightResult, SyntaxCollection},
     theme::Theme,
     ui,
     word_database::WordDatabase,
 };
 
+const STATE_FILE_VERSION: u32 = 1;
+// buffers are only checked for changes on disk this often so idle does not stat every file
+const FILE_CHANGE_POLL_INTERVAL: Duration = Duration::from_secs(3);
+// and their swap files are written at most this often
+const SWAP_FILE_UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Copy)]
 pub enum EditorFlow {
     Continue,
@@ -156,17 +167,46 @@ impl EditorContext {
                     EditorEvent::BufferRead { handle } => {
                         let buffer = self.editor.buffers.get_mut(handle);
                         buffer.refresh_syntax(&self.editor.syntaxes);
+                        if !self.editor.config.undo_directory.is_empty() {
+                            buffer.load_history_from_file(
+                                &self.editor.current_directory,
+                                Path::new(&self.editor.config.undo_directory),
+                            );
+                        }
+                        if !self.editor.config.recovery_directory.is_empty()
+                            && buffer.has_foreign_swap_file(
+                                &self.editor.current_directory,
+                                Path::new(&self.editor.config.recovery_directory),
+                            )
+                        {
+                            self.editor.status_bar.write(MessageKind::Info).fmt(format_args!(
+                                "{:?} has unsaved changes from a previous session. use `recover` to restore them",
+                                &buffer.path
+                            ));
+                        }
                         self.editor.buffer_views.on_buffer_read(buffer);
+
+                        if let Some(plugin_handle) = buffer.take_provider_read() {
+                            PluginCollection::on_buffer_read(self, plugin_handle, handle);
+                        }
                     }
                     EditorEvent::BufferInsertText { handle, range, .. } => {
                         self.editor
                             .buffer_views
                             .on_buffer_insert_text(handle, range);
+                        self.editor
+                            .mode
+                            .insert_state
+                            .on_buffer_insert_text(handle, range);
                     }
                     EditorEvent::BufferDeleteText { handle, range } => {
                         self.editor
                             .buffer_views
                             .on_buffer_delete_text(handle, range);
+                        self.editor
+                            .mode
+                            .insert_state
+                            .on_buffer_delete_text(handle, range);
                     }
                     EditorEvent::BufferWrite { handle, new_path } => {
                         let buffer = self.editor.buffers.get_mut(handle);
@@ -174,6 +214,19 @@ impl EditorContext {
                             buffer.refresh_syntax(&self.editor.syntaxes);
                         }
 
+                        if !self.editor.config.undo_directory.is_empty() {
+                            let result = buffer.save_history_to_file(
+                                &self.editor.current_directory,
+                                Path::new(&self.editor.config.undo_directory),
+                            );
+                            if result.is_err() {
+                                self.editor
+                                    .status_bar
+                                    .write(MessageKind::Error)
+                                    .str("could not save undo history");
+                            }
+                        }
+
                         for client in self.clients.iter() {
                             if client.stdin_buffer_handle() == Some(buffer.handle()) {
                                 let mut buf = self.platform.buf_pool.acquire();
@@ -196,7 +249,23 @@ impl EditorContext {
                             }
                         }
                     }
+                    EditorEvent::BufferProviderWrite { handle } => {
+                        let buffer = self.editor.buffers.get(handle);
+                        if let BufferProvider::Plugin(plugin_handle) = buffer.properties.provider {
+                            PluginCollection::on_buffer_write(self, plugin_handle, handle);
+                        }
+                    }
+                    EditorEvent::BufferEditRejected { .. } => {
+                        self.editor
+                            .status_bar
+                            .write(MessageKind::Error)
+                            .str("buffer is read-only");
+                    }
                     EditorEvent::BufferClose { handle } => {
+                        self.editor.buffers.get_mut(handle).remove_swap_file(
+                            &self.editor.current_directory,
+                            Path::new(&self.editor.config.recovery_directory),
+                        );
                         self.editor.buffers.remove_now(
                             &mut self.platform,
                             handle,
@@ -207,6 +276,7 @@ impl EditorContext {
                         }
                         self.editor.buffer_views.remove_buffer_views(handle);
                         self.editor.mode.read_line_state.on_buffer_close(handle);
+                        self.editor.mode.insert_state.on_buffer_close(handle);
                     }
                     EditorEvent::FixCursors { handle, cursors } => {
                         let mut view_cursors =
@@ -237,6 +307,9 @@ pub struct Editor {
     pub buffered_keys: BufferedKeys,
     pub recording_macro: Option<RegisterKey>,
     pub registers: RegisterCollection,
+    state_file_path: Option<PathBuf>,
+    last_file_change_poll: Option<Instant>,
+    last_swap_file_update: Option<Instant>,
     pub read_line: ReadLine,
     pub picker: Picker,
     pub string_pool: StringPool,
@@ -265,6 +338,9 @@ impl Editor {
             buffered_keys: BufferedKeys::default(),
             recording_macro: None,
             registers: RegisterCollection::new(),
+            state_file_path: None,
+            last_file_change_poll: None,
+            last_swap_file_update: None,
             read_line: ReadLine::default(),
             picker: Picker::default(),
             string_pool: StringPool::default(),
@@ -281,7 +357,7 @@ impl Editor {
         &mut self,
         client_handle: ClientHandle,
         path: &Path,
-        properties: BufferProperties,
+        mut properties: BufferProperties,
         create_if_not_found: bool,
     ) -> Result<BufferViewHandle, BufferReadError> {
         if let Some(buffer_handle) = self.buffers.find_with_path(&self.current_directory, path) {
@@ -291,12 +367,49 @@ impl Editor {
             Ok(handle)
         } else {
             let path = path.strip_prefix(&self.current_directory).unwrap_or(path);
+            if let BufferProvider::File = properties.provider {
+                if let Some(plugin_handle) = self.buffers.find_provider(path) {
+                    properties.provider = BufferProvider::Plugin(plugin_handle);
+                }
+            }
+
             let buffer = self.buffers.add_new();
             buffer.path.clear();
             buffer.path.push(path);
             buffer.properties = properties;
 
-            match buffer.read_from_file(&mut self.word_database, &mut self.events) {
+            let large_file_size = self.config.large_file_size as u64;
+            if large_file_size > 0
+                && matches!(fs::metadata(path), Ok(m) if m.len() >= large_file_size)
+            {
+                buffer.set_large_file(
+                    true,
+                    self.config.large_file_history,
+                    &mut self.word_database,
+                );
+            }
+
+            // words are only added once it is known whether this is a large file
+            let word_database_enabled =
+                std::mem::replace(&mut buffer.properties.word_database_enabled, false);
+            let result = buffer.read_from_file(&mut self.word_database, &mut self.events);
+
+            let large_file_line_count = self.config.large_file_line_count as usize;
+            if result.is_ok()
+                && large_file_line_count > 0
+                && buffer.content().lines().len() >= large_file_line_count
+            {
+                buffer.set_large_file(
+                    true,
+                    self.config.large_file_history,
+                    &mut self.word_database,
+                );
+            }
+            if word_database_enabled {
+                buffer.enable_word_database(&mut self.word_database);
+            }
+
+            match result {
                 Ok(()) => {
                     let handle = self.buffer_views.add_new(client_handle, buffer.handle());
                     Ok(handle)
@@ -314,6 +427,33 @@ impl Editor {
         }
     }
 
+    pub fn recover_buffer_view_handle(
+        &mut self,
+        client_handle: ClientHandle,
+        path: &Path,
+    ) -> Result<BufferViewHandle, BufferReadError> {
+        let swap_file_path = swap_file_path(
+            &self.current_directory,
+            Path::new(&self.config.recovery_directory),
+            path,
+        );
+        let swap_file = SwapFile::read(&swap_file_path)?;
+
+        let handle = self.buffer_view_handle_from_path(
+            client_handle,
+            &swap_file.buffer_path,
+            BufferProperties::text(),
+            true,
+        )?;
+        let buffer_handle = self.buffer_views.get(handle).buffer_handle;
+        self.buffers.get_mut(buffer_handle).recover_from_swap_file(
+            &mut self.word_database,
+            &swap_file,
+            &mut self.events,
+        );
+        Ok(handle)
+    }
+
     pub fn enter_mode(&mut self, next: ModeKind) {
         Mode::change_to(self, next);
     }
@@ -329,6 +469,8 @@ impl Editor {
         mut keys: KeysIterator,
     ) -> EditorFlow {
         let start_index = keys.index;
+        let mut replaced_keys_len = 0;
+        let mut repeat_count = 0;
 
         match ctx.editor.keymaps.matches(
             ctx.editor.mode.kind(),
@@ -339,12 +481,27 @@ impl Editor {
             MatchResult::ReplaceWith(replaced_keys) => {
                 ctx.editor.buffered_keys.0.truncate(start_index);
                 ctx.editor.buffered_keys.0.extend_from_slice(replaced_keys);
+
+                replaced_keys_len = replaced_keys.len();
+                if ctx.editor.mode.kind() == ModeKind::Normal {
+                    let state = &mut ctx.editor.mode.normal_state;
+                    repeat_count = state.count.saturating_sub(1);
+                    state.count = 0;
+                }
             }
         }
 
         loop {
             if keys.index == ctx.editor.buffered_keys.0.len() {
-                break;
+                // mapped keys with a count are repeated as long as they end back in normal mode
+                if repeat_count == 0 || ctx.editor.mode.kind() != ModeKind::Normal {
+                    break;
+                }
+                repeat_count -= 1;
+                ctx.editor
+                    .buffered_keys
+                    .0
+                    .extend_from_within(start_index..start_index + replaced_keys_len);
             }
             let from_index = ctx.editor.recording_macro.map(|_| keys.index);
 
@@ -362,7 +519,10 @@ impl Editor {
             {
                 for key in &ctx.editor.buffered_keys.0[from_index..keys.index] {
                     use fmt::Write;
-                    let register = ctx.editor.registers.get_mut(register_key);
+                    let register = ctx
+                        .editor
+                        .registers
+                        .get_mut_as(register_key, RegisterKind::Macro);
                     let _ = write!(register, "{}", key);
                 }
             }
@@ -440,6 +600,205 @@ impl Editor {
     }
 
     pub(crate) fn on_idle(&mut self) {
+        // whether `interval` passed since `last`, in which case it becomes `now`
+        fn interval_elapsed(last: &mut Option<Instant>, now: Instant, interval: Duration) -> bool {
+            match last {
+                Some(last) if now.duration_since(*last) < interval => false,
+                _ => {
+                    *last = Some(now);
+                    true
+                }
+            }
+        }
+
+        let now = Instant::now();
+        if interval_elapsed(
+            &mut self.last_file_change_poll,
+            now,
+            FILE_CHANGE_POLL_INTERVAL,
+        ) {
+            for buffer in self.buffers.iter_mut() {
+                if !buffer.poll_file_change() {
+                    continue;
+                }
+
+                if buffer.needs_save() {
+                    self.status_bar.write(MessageKind::Error).fmt(format_args!(
+                        "{:?} changed on disk. use `reopen!` to discard your changes or `save!` to overwrite it",
+                        &buffer.path
+                    ));
+                    continue;
+                }
+
+                match buffer.reload_from_file(&mut self.word_database, &mut self.events) {
+                    Ok(()) => self.status_bar.write(MessageKind::Info).fmt(format_args!(
+                        "{:?} changed on disk and was reloaded",
+                        &buffer.path
+                    )),
+                    Err(error) => self
+                        .status_bar
+                        .write(MessageKind::Error)
+                        .fmt(format_args!("{}", error)),
+                }
+            }
+        }
+
+        if !self.config.recovery_directory.is_empty()
+            && interval_elapsed(
+                &mut self.last_swap_file_update,
+                now,
+                SWAP_FILE_UPDATE_INTERVAL,
+            )
+        {
+            let recovery_directory = Path::new(&self.config.recovery_directory);
+            for buffer in self.buffers.iter_mut() {
+                if buffer
+                    .update_swap_file(&self.current_directory, recovery_directory)
+                    .is_err()
+                {
+                    self.status_bar.write(MessageKind::Error).fmt(format_args!(
+                        "could not write swap file for {:?}",
+                        &buffer.path
+                    ));
+                }
+            }
+        }
+
         self.events.enqueue(EditorEvent::Idle);
     }
+
+    /// Restores the registers saved to `state_file` by a previous session, if any.
+    /// Only a state file that was loaded (or that did not exist yet) is saved back on exit
+    /// so an unreadable or invalid one is never overwritten.
+    pub(crate) fn load_state(&mut self) {
+        if self.config.state_file.is_empty() {
+            return;
+        }
+        let path = self.current_directory.join(&self.config.state_file);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                if error.kind() == io::ErrorKind::NotFound {
+                    self.state_file_path = Some(path);
+                } else {
+                    self.status_bar.write(MessageKind::Error).fmt(format_args!(
+                        "could not read state file {:?}: {}",
+                        &path, error
+                    ));
+                }
+                return;
+            }
+        };
+
+        let mut deserializer = &bytes[..];
+        let registers = match u32::deserialize(&mut deserializer) {
+            Ok(STATE_FILE_VERSION) => RegisterCollection::deserialize(&mut deserializer),
+            _ => Err(DeserializeError::InvalidData),
+        };
+        match registers {
+            Ok(registers) => {
+                self.registers = registers;
+                self.state_file_path = Some(path);
+            }
+            Err(_) => self
+                .status_bar
+                .write(MessageKind::Error)
+                .fmt(format_args!("invalid state file {:?}", &path)),
+        }
+    }
+
+    fn save_state(&self) -> io::Result<()> {
+        let path = match &self.state_file_path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let mut bytes = Vec::new();
+        STATE_FILE_VERSION.serialize(&mut bytes);
+        self.registers.serialize(&mut bytes);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_file_atomically(path, false, |writer| io::Write::write_all(writer, &bytes))
+    }
+
+    /// Called when the server shuts down. Registers are saved to `state_file` and swap files
+    /// are only kept when it's going down because of a panic, in which case they're also
+    /// brought up to date.
+    pub(crate) fn on_exit(&mut self, panicking: bool) {
+        let _ = self.save_state();
+
+        let recovery_directory = Path::new(&self.config.recovery_directory);
+        if recovery_directory.as_os_str().is_empty() {
+            return;
+        }
+
+        for buffer in self.buffers.iter_mut() {
+            if panicking && buffer.needs_save() {
+                let _ = buffer.write_swap_file(&self.current_directory, recovery_directory);
+            } else {
+                buffer.remove_swap_file(&self.current_directory, recovery_directory);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_file_keeps_other_buffer_words() {
+        let dir = std::env::temp_dir().join(format!("pepper-test-large-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let small_path = dir.join("small.txt");
+        let large_path = dir.join("large.txt");
+        fs::write(&small_path, "shared small").unwrap();
+        fs::write(&large_path, "shared\nshared\nlarge").unwrap();
+
+        let mut editor = Editor::new(PathBuf::new());
+        editor.config.large_file_line_count = 3;
+        let client_handle = ClientHandle::from_index(0).unwrap();
+
+        editor
+            .buffer_view_handle_from_path(
+                client_handle,
+                &small_path,
+                BufferProperties::text(),
+                false,
+            )
+            .unwrap_or_else(|_| panic!("could not open buffer"));
+        assert_eq!(2, editor.word_database.word_indices().count());
+
+        let handle = editor
+            .buffer_view_handle_from_path(
+                client_handle,
+                &large_path,
+                BufferProperties::text(),
+                false,
+            )
+            .unwrap_or_else(|_| panic!("could not open buffer"));
+        let buffer_handle = editor.buffer_views.get(handle).buffer_handle;
+        assert!(editor.buffers.get(buffer_handle).is_large_file());
+
+        let words: Vec<_> = editor
+            .word_database
+            .word_indices()
+            .map(|(_, word)| word)
+            .collect();
+        assert_eq!(2, words.len());
+        assert!(words.contains(&"shared"));
+        assert!(words.contains(&"small"));
+
+        editor.buffers.get_mut(buffer_handle).set_large_file(
+            false,
+            editor.config.large_file_history,
+            &mut editor.word_database,
+        );
+        assert_eq!(3, editor.word_database.word_indices().count());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
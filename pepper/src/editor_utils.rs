@@ -1,4 +1,4 @@
-use std::{fmt, process::Command};
+use std::{fmt, fs, io, path::Path, process::Command};
 
 use crate::{
     command::{CommandManager, CommandTokenizer},
@@ -6,6 +6,7 @@ use crate::{
     events::{KeyParseAllError, KeyParser},
     mode::ModeKind,
     platform::{Key, Platform},
+    serialization::{DeserializeError, Deserializer, Serialize, Serializer},
     word_database::{WordIter, WordKind},
 };
 
@@ -279,8 +280,18 @@ impl RegisterKey {
 
 const REGISTERS_LEN: usize = (b'z' - b'a' + 1) as _;
 
+// how a register was last written to, which decides what picking it from the registers picker does
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterKind {
+    #[default]
+    Text,
+    Macro,
+    Marker,
+}
+
 pub struct RegisterCollection {
     registers: [String; REGISTERS_LEN],
+    kinds: [RegisterKind; REGISTERS_LEN],
 }
 
 impl RegisterCollection {
@@ -288,6 +299,7 @@ impl RegisterCollection {
         const DEFAULT_STRING: String = String::new();
         Self {
             registers: [DEFAULT_STRING; REGISTERS_LEN],
+            kinds: [RegisterKind::Text; REGISTERS_LEN],
         }
     }
 
@@ -295,14 +307,80 @@ impl RegisterCollection {
         &self.registers[key.0 as usize]
     }
 
+    pub fn kind(&self, key: RegisterKey) -> RegisterKind {
+        self.kinds[key.0 as usize]
+    }
+
     pub fn get_mut(&mut self, key: RegisterKey) -> &mut String {
+        self.get_mut_as(key, RegisterKind::Text)
+    }
+
+    pub fn get_mut_as(&mut self, key: RegisterKey, kind: RegisterKind) -> &mut String {
+        self.kinds[key.0 as usize] = kind;
         &mut self.registers[key.0 as usize]
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = (RegisterKey, &str)> {
+        self.registers
+            .iter()
+            .enumerate()
+            .map(|(i, register)| (RegisterKey(i as _), &register[..]))
+    }
+}
+
+// only non empty registers are serialized and the auto macro register is left out
+// since it's overwritten all the time
+impl<'de> Serialize<'de> for RegisterCollection {
+    fn serialize<S>(&self, serializer: &mut S)
+    where
+        S: Serializer,
+    {
+        fn is_saved(&(key, register): &(RegisterKey, &str)) -> bool {
+            key != AUTO_MACRO_REGISTER && !register.is_empty()
+        }
+
+        let len = self.iter().filter(is_saved).count() as u8;
+        len.serialize(serializer);
+        for (key, register) in self.iter().filter(is_saved) {
+            key.0.serialize(serializer);
+            (self.kind(key) as u8).serialize(serializer);
+            register.serialize(serializer);
+        }
+    }
+
+    fn deserialize<D>(deserializer: &mut D) -> Result<Self, DeserializeError>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut registers = Self::new();
+        let len = u8::deserialize(deserializer)?;
+        for _ in 0..len {
+            let key = u8::deserialize(deserializer)?;
+            let kind = match u8::deserialize(deserializer)? {
+                0 => RegisterKind::Text,
+                1 => RegisterKind::Macro,
+                2 => RegisterKind::Marker,
+                _ => return Err(DeserializeError::InvalidData),
+            };
+            let register = <&str>::deserialize(deserializer)?;
+            if key as usize >= REGISTERS_LEN {
+                return Err(DeserializeError::InvalidData);
+            }
+            registers
+                .get_mut_as(RegisterKey(key), kind)
+                .push_str(register);
+        }
+        Ok(registers)
+    }
 }
 
 // FNV-1a : https://en.wikipedia.org/wiki/Fowler–Noll–Vo_hash_function
-pub const fn hash_bytes(mut bytes: &[u8]) -> u64 {
-    let mut hash: u64 = 0xcbf29ce484222325;
+pub const fn hash_bytes(bytes: &[u8]) -> u64 {
+    hash_more_bytes(0xcbf29ce484222325, bytes)
+}
+
+// continues the hash of some previous bytes so it's the same as hashing them all together
+pub const fn hash_more_bytes(mut hash: u64, mut bytes: &[u8]) -> u64 {
     while let [b, rest @ ..] = bytes {
         hash ^= *b as u64;
         hash = hash.wrapping_mul(0x100000001b3);
@@ -388,6 +466,62 @@ pub fn parse_process_command(command: &str) -> Option<Command> {
     Some(command)
 }
 
+/// Writes through a temporary file next to `path` that is then renamed over it, so an
+/// interrupted write never leaves a truncated file. Permissions of an existing file are kept
+/// and, if `backup`, it is first copied to `path~`.
+pub fn write_file_atomically<F>(path: &Path, backup: bool, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut io::BufWriter<fs::File>) -> io::Result<()>,
+{
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => path.into(),
+    };
+    let file_name = match path.file_name() {
+        Some(file_name) => file_name,
+        None => return Err(io::ErrorKind::NotFound.into()),
+    };
+
+    let mut temp_file_name = std::ffi::OsString::from(".");
+    temp_file_name.push(file_name);
+    temp_file_name.push(".pepper-save");
+    let temp_path = path.with_file_name(temp_file_name);
+
+    let original_metadata = fs::metadata(&path).ok();
+
+    let result = fs::File::create(&temp_path).and_then(|file| {
+        let mut writer = io::BufWriter::new(file);
+        write(&mut writer)?;
+        let file = writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        if let Some(metadata) = &original_metadata {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()
+    });
+    if let Err(error) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(error);
+    }
+
+    if backup && original_metadata.is_some() {
+        let mut backup_path = path.clone().into_os_string();
+        backup_path.push("~");
+        if let Err(error) = fs::copy(&path, &backup_path) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(error);
+        }
+    }
+
+    if let Err(error) = fs::rename(&temp_path, &path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(error);
+    }
+
+    Ok(())
+}
+
 pub fn load_config(ctx: &mut EditorContext, config_name: &str, config_content: &str) -> EditorFlow {
     for (line_index, line) in config_content.lines().enumerate() {
         if line.is_empty() || line.starts_with('#') {
@@ -505,4 +639,39 @@ mod tests {
             residue.receive_bytes(&mut Default::default(), &[])
         );
     }
+
+    #[test]
+    fn serialize_registers() {
+        let key = |c| RegisterKey::from_char(c).unwrap();
+
+        let mut registers = RegisterCollection::new();
+        registers
+            .get_mut_as(key('b'), RegisterKind::Macro)
+            .push_str("macro keys");
+        registers
+            .get_mut_as(key('m'), RegisterKind::Marker)
+            .push_str("src/main.rs:3,4");
+        registers.get_mut(key('t')).push_str("localhost:8080");
+        registers.get_mut(AUTO_MACRO_REGISTER).push_str("auto");
+
+        let mut bytes = Vec::new();
+        registers.serialize(&mut bytes);
+        let mut deserializer = &bytes[..];
+        let registers = match RegisterCollection::deserialize(&mut deserializer) {
+            Ok(registers) => registers,
+            Err(_) => panic!("could not deserialize registers"),
+        };
+        assert!(deserializer.is_empty());
+
+        assert_eq!("macro keys", registers.get(key('b')));
+        assert_eq!("src/main.rs:3,4", registers.get(key('m')));
+        assert!(registers.kind(key('b')) == RegisterKind::Macro);
+        assert!(registers.kind(key('m')) == RegisterKind::Marker);
+        assert!(registers.kind(key('t')) == RegisterKind::Text);
+        assert_eq!("", registers.get(AUTO_MACRO_REGISTER));
+        assert_eq!("", registers.get(key('z')));
+
+        let mut deserializer = &bytes[..bytes.len() - 1];
+        assert!(RegisterCollection::deserialize(&mut deserializer).is_err());
+    }
 }
@@ -0,0 +1,280 @@
+use std::{fmt, str::FromStr};
+
+pub static ENCODING_NAMES: &[&str] =
+    &["utf8", "utf16le", "utf16be", "latin1", "windows1252", "hex"];
+
+const HEX_BYTES_PER_LINE: usize = 16;
+
+static WINDOWS_1252_HIGH_CHARS: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Windows1252,
+    Hex,
+}
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf8",
+            Self::Utf16Le => "utf16le",
+            Self::Utf16Be => "utf16be",
+            Self::Latin1 => "latin1",
+            Self::Windows1252 => "windows1252",
+            Self::Hex => "hex",
+        }
+    }
+
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\xff\xfe") {
+            Some(Self::Utf16Le)
+        } else if bytes.starts_with(b"\xfe\xff") {
+            Some(Self::Utf16Be)
+        } else {
+            None
+        }
+    }
+
+    pub fn decode(self, bytes: &[u8], text: &mut String) -> Result<(), DecodeError> {
+        match self {
+            Self::Utf8 => match std::str::from_utf8(bytes) {
+                Ok(s) => text.push_str(s),
+                Err(_) => return Err(DecodeError),
+            },
+            Self::Utf16Le | Self::Utf16Be => {
+                let chunks = bytes.chunks_exact(2);
+                if !chunks.remainder().is_empty() {
+                    return Err(DecodeError);
+                }
+                let units = chunks.map(|b| match self {
+                    Self::Utf16Le => u16::from_le_bytes([b[0], b[1]]),
+                    _ => u16::from_be_bytes([b[0], b[1]]),
+                });
+                for c in char::decode_utf16(units) {
+                    match c {
+                        Ok(c) => text.push(c),
+                        Err(_) => return Err(DecodeError),
+                    }
+                }
+            }
+            Self::Latin1 => text.extend(bytes.iter().map(|&b| b as char)),
+            Self::Windows1252 => text.extend(bytes.iter().map(|&b| match b {
+                0x80..=0x9f => WINDOWS_1252_HIGH_CHARS[(b - 0x80) as usize],
+                _ => b as char,
+            })),
+            Self::Hex => {
+                use std::fmt::Write;
+                for (i, line) in bytes.chunks(HEX_BYTES_PER_LINE).enumerate() {
+                    let _ = write!(text, "{:08x}:", i * HEX_BYTES_PER_LINE);
+                    for b in line {
+                        let _ = write!(text, " {:02x}", b);
+                    }
+                    for _ in line.len()..HEX_BYTES_PER_LINE {
+                        text.push_str("   ");
+                    }
+                    text.push_str("  ");
+                    text.extend(line.iter().map(|&b| match b {
+                        0x20..=0x7e => b as char,
+                        _ => '.',
+                    }));
+                    text.push('\n');
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn encode(self, text: &str, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            Self::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+            Self::Utf16Le => {
+                for unit in text.encode_utf16() {
+                    bytes.extend_from_slice(&unit.to_le_bytes());
+                }
+            }
+            Self::Utf16Be => {
+                for unit in text.encode_utf16() {
+                    bytes.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            Self::Latin1 => {
+                for c in text.chars() {
+                    match c as u32 {
+                        0..=0xff => bytes.push(c as u8),
+                        _ => return Err(EncodeError::InvalidChar { encoding: self, c }),
+                    }
+                }
+            }
+            Self::Windows1252 => {
+                for c in text.chars() {
+                    match c as u32 {
+                        0..=0x7f | 0xa0..=0xff => bytes.push(c as u8),
+                        _ => match WINDOWS_1252_HIGH_CHARS.iter().position(|&h| h == c) {
+                            Some(i) => bytes.push(0x80 + i as u8),
+                            None => return Err(EncodeError::InvalidChar { encoding: self, c }),
+                        },
+                    }
+                }
+            }
+            Self::Hex => {
+                for (i, line) in text.lines().enumerate() {
+                    // offsets are ignored and the ascii column starts after a double space
+                    let line = match line.split_once(':') {
+                        Some((offset, rest))
+                            if !offset.is_empty()
+                                && offset.bytes().all(|b| b.is_ascii_hexdigit()) =>
+                        {
+                            rest
+                        }
+                        _ => line,
+                    };
+                    let line = match line.trim_start().find("  ") {
+                        Some(i) => &line.trim_start()[..i],
+                        None => line,
+                    };
+                    for digits in line.split_whitespace() {
+                        if digits.len() % 2 != 0 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+                            return Err(EncodeError::InvalidHexLine(i + 1));
+                        }
+                        for j in (0..digits.len()).step_by(2) {
+                            match u8::from_str_radix(&digits[j..j + 2], 16) {
+                                Ok(b) => bytes.push(b),
+                                Err(_) => return Err(EncodeError::InvalidHexLine(i + 1)),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+impl FromStr for Encoding {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf8" => Ok(Self::Utf8),
+            "utf16le" => Ok(Self::Utf16Le),
+            "utf16be" => Ok(Self::Utf16Be),
+            "latin1" => Ok(Self::Latin1),
+            "windows1252" => Ok(Self::Windows1252),
+            "hex" => Ok(Self::Hex),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DecodeError;
+
+#[derive(Debug)]
+pub enum EncodeError {
+    InvalidChar { encoding: Encoding, c: char },
+    InvalidHexLine(usize),
+}
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidChar { encoding, c } => write!(
+                f,
+                "character '{}' can not be encoded as {}",
+                c,
+                encoding.name()
+            ),
+            Self::InvalidHexLine(line) => write!(f, "invalid hex dump at line {}", line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(encoding: Encoding, bytes: &[u8], expected: &str) {
+        let mut text = String::new();
+        encoding.decode(bytes, &mut text).unwrap();
+        assert_eq!(expected, text);
+
+        let mut encoded = Vec::new();
+        encoding.encode(&text, &mut encoded).unwrap();
+        assert_eq!(bytes, &encoded[..]);
+    }
+
+    #[test]
+    fn encoding_round_trip() {
+        round_trip(Encoding::Utf8, "açaí €".as_bytes(), "açaí €");
+        round_trip(
+            Encoding::Utf16Le,
+            b"\xff\xfea\x00\xe7\x00\xac\x20",
+            "\u{feff}aç€",
+        );
+        round_trip(
+            Encoding::Utf16Be,
+            b"\xfe\xff\x00a\x00\xe7\x20\xac",
+            "\u{feff}aç€",
+        );
+        round_trip(Encoding::Latin1, b"a\xe7a\xed\x80", "açaí\u{80}");
+        round_trip(Encoding::Windows1252, b"a\xe7a\xed\x80\x9f", "açaí€Ÿ");
+        round_trip(
+            Encoding::Hex,
+            b"0123456789abcdef\x00\xff\n",
+            concat!(
+                "00000000: 30 31 32 33 34 35 36 37 38 39 61 62 63 64 65 66  0123456789abcdef\n",
+                "00000010: 00 ff 0a                                         ...\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn encoding_edit_hex() {
+        let mut bytes = Vec::new();
+        Encoding::Hex
+            .encode("00000000: 61 62ff 63  ab.c\n00000004: 64\n0a\n", &mut bytes)
+            .unwrap();
+        assert_eq!(b"ab\xffcd\n", &bytes[..]);
+
+        bytes.clear();
+        Encoding::Hex
+            .encode("3a 61  :a\n00000002: 62 3a  b:\n", &mut bytes)
+            .unwrap();
+        assert_eq!(b":ab:", &bytes[..]);
+    }
+
+    #[test]
+    fn encoding_errors() {
+        let mut text = String::new();
+        assert!(Encoding::Utf8.decode(b"a\xe7a", &mut text).is_err());
+        assert!(Encoding::Utf16Le.decode(b"a\x00b", &mut text).is_err());
+        assert!(Encoding::Utf16Le.decode(b"\x00\xd8", &mut text).is_err());
+
+        let mut bytes = Vec::new();
+        assert!(Encoding::Latin1.encode("€", &mut bytes).is_err());
+        assert!(Encoding::Windows1252.encode("\u{100}", &mut bytes).is_err());
+        assert!(Encoding::Hex.encode("00000000: 6", &mut bytes).is_err());
+        assert!(Encoding::Hex.encode("00000000: 6g", &mut bytes).is_err());
+    }
+
+    #[test]
+    fn encoding_names() {
+        for &name in ENCODING_NAMES {
+            assert_eq!(Ok(name), name.parse::<Encoding>().map(Encoding::name));
+        }
+    }
+
+    #[test]
+    fn encoding_detect() {
+        assert_eq!(Some(Encoding::Utf16Le), Encoding::detect(b"\xff\xfea\x00"));
+        assert_eq!(Some(Encoding::Utf16Be), Encoding::detect(b"\xfe\xff\x00a"));
+        assert_eq!(None, Encoding::detect(b"\xef\xbb\xbfa"));
+        assert_eq!(None, Encoding::detect(b"a"));
+    }
+}
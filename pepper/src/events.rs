@@ -50,6 +50,12 @@ pub enum EditorEvent {
         handle: BufferHandle,
         new_path: bool,
     },
+    BufferProviderWrite {
+        handle: BufferHandle,
+    },
+    BufferEditRejected {
+        handle: BufferHandle,
+    },
     BufferClose {
         handle: BufferHandle,
     },
@@ -0,0 +1,164 @@
+use crate::{buffer_position::BufferPosition, buffer_view::BufferViewHandle};
+
+static LABEL_CHARS: &[u8] = b"asdfghjklqwertyuiopzxcvbnm";
+
+#[derive(Default, Clone, Copy)]
+pub struct JumpLabel {
+    bytes: [u8; 2],
+    len: u8,
+}
+impl JumpLabel {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+pub enum JumpLabelMatch {
+    None,
+    Partial,
+    Found(BufferPosition),
+}
+
+// labeled positions of a buffer view that can be jumped to by typing their label.
+// all labels have the same length: a single char when they're few, two chars otherwise
+#[derive(Default)]
+pub struct JumpLabels {
+    buffer_view_handle: Option<BufferViewHandle>,
+    positions: Vec<BufferPosition>,
+}
+impl JumpLabels {
+    pub const fn capacity() -> usize {
+        LABEL_CHARS.len() * LABEL_CHARS.len()
+    }
+
+    pub fn buffer_view_handle(&self) -> Option<BufferViewHandle> {
+        self.buffer_view_handle
+    }
+
+    // sorted by position
+    pub fn positions(&self) -> &[BufferPosition] {
+        &self.positions
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer_view_handle = None;
+        self.positions.clear();
+    }
+
+    // positions should be sorted and only the first `capacity()` of them are labeled
+    pub fn set<I>(&mut self, buffer_view_handle: BufferViewHandle, positions: I)
+    where
+        I: Iterator<Item = BufferPosition>,
+    {
+        self.buffer_view_handle = Some(buffer_view_handle);
+        self.positions.clear();
+        self.positions.extend(positions.take(Self::capacity()));
+    }
+
+    fn label_len(&self) -> usize {
+        if self.positions.len() <= LABEL_CHARS.len() {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn label(&self, index: usize) -> JumpLabel {
+        let len = LABEL_CHARS.len();
+        if self.label_len() == 1 {
+            JumpLabel {
+                bytes: [LABEL_CHARS[index], 0],
+                len: 1,
+            }
+        } else {
+            JumpLabel {
+                bytes: [LABEL_CHARS[index / len], LABEL_CHARS[index % len]],
+                len: 2,
+            }
+        }
+    }
+
+    pub fn find(&self, label: &[u8]) -> JumpLabelMatch {
+        let mut index = 0;
+        for &b in label {
+            match LABEL_CHARS.iter().position(|&c| c == b) {
+                Some(i) => index = index * LABEL_CHARS.len() + i,
+                None => return JumpLabelMatch::None,
+            }
+        }
+
+        let label_len = self.label_len();
+        if label.len() < label_len {
+            for _ in label.len()..label_len {
+                index *= LABEL_CHARS.len();
+            }
+            if index < self.positions.len() {
+                JumpLabelMatch::Partial
+            } else {
+                JumpLabelMatch::None
+            }
+        } else if label.len() == label_len && index < self.positions.len() {
+            JumpLabelMatch::Found(self.positions[index])
+        } else {
+            JumpLabelMatch::None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        buffer::BufferCollection, buffer_view::BufferViewCollection, client::ClientHandle,
+    };
+
+    fn set_labels(labels: &mut JumpLabels, count: usize) {
+        let mut buffers = BufferCollection::default();
+        let mut buffer_views = BufferViewCollection::default();
+        let buffer_handle = buffers.add_new().handle();
+        let handle = buffer_views.add_new(ClientHandle::from_index(0).unwrap(), buffer_handle);
+        labels.set(
+            handle,
+            (0..count).map(|i| BufferPosition::line_col(i as _, 0)),
+        );
+    }
+
+    fn found_line(labels: &JumpLabels, label: &str) -> Option<u32> {
+        match labels.find(label.as_bytes()) {
+            JumpLabelMatch::Found(position) => Some(position.line_index),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn single_char_labels() {
+        let mut labels = JumpLabels::default();
+        set_labels(&mut labels, 3);
+        assert_eq!(b"a", labels.label(0).as_bytes());
+        assert_eq!(b"d", labels.label(2).as_bytes());
+        assert_eq!(Some(0), found_line(&labels, "a"));
+        assert_eq!(Some(2), found_line(&labels, "d"));
+        assert_eq!(None, found_line(&labels, "f"));
+        assert_eq!(None, found_line(&labels, "?"));
+        assert_eq!(None, found_line(&labels, "as"));
+    }
+
+    #[test]
+    fn two_char_labels() {
+        let mut labels = JumpLabels::default();
+        set_labels(&mut labels, 30);
+        assert_eq!(b"aa", labels.label(0).as_bytes());
+        assert_eq!(b"am", labels.label(25).as_bytes());
+        assert_eq!(b"sa", labels.label(26).as_bytes());
+        assert!(matches!(labels.find(b"a"), JumpLabelMatch::Partial));
+        assert!(matches!(labels.find(b"s"), JumpLabelMatch::Partial));
+        assert!(matches!(labels.find(b"d"), JumpLabelMatch::None));
+        assert_eq!(Some(1), found_line(&labels, "as"));
+        assert_eq!(Some(29), found_line(&labels, "sf"));
+        assert_eq!(None, found_line(&labels, "sg"));
+
+        set_labels(&mut labels, JumpLabels::capacity() + 10);
+        assert_eq!(JumpLabels::capacity(), labels.positions().len());
+    }
+}
@@ -3,15 +3,19 @@ pub mod buffer;
 pub mod buffer_history;
 pub mod buffer_position;
 pub mod buffer_view;
+pub mod case;
 pub mod client;
 pub mod command;
 pub mod config;
 pub mod cursor;
+pub mod directory;
 pub mod editor;
 pub mod editor_utils;
+pub mod encoding;
 pub mod events;
 pub mod glob;
 pub mod help;
+pub mod jump_labels;
 pub mod mode;
 pub mod navigation_history;
 pub mod pattern;
@@ -19,6 +23,8 @@ pub mod picker;
 pub mod platform;
 pub mod plugin;
 pub mod serialization;
+pub mod snippet;
+pub mod sort;
 pub mod syntax;
 pub mod theme;
 pub mod ui;
@@ -60,6 +66,7 @@ pub struct Args {
     pub as_focused_client: bool,
     pub quit: bool,
     pub server: bool,
+    pub read_only: bool,
     pub configs: Vec<ArgsConfig>,
     pub files: Vec<String>,
 }
@@ -88,6 +95,7 @@ fn print_help() {
     println!("  --as-focused-client      sends events as if it was the currently focused client");
     println!("  --quit                   sends a `quit` event on start");
     println!("  --server                 only run as server");
+    println!("  -R, --read-only          opens files as read-only buffers (clients only)");
     println!("  -c, --config[!]          sources config file at path (repeatable) (server only)");
     println!("                           with `!` it will suppress the 'file not found' error");
 }
@@ -137,6 +145,7 @@ impl Args {
                 "--as-focused-client" => parsed.as_focused_client = true,
                 "--quit" => parsed.quit = true,
                 "--server" => parsed.server = true,
+                "-R" | "--read-only" => parsed.read_only = true,
                 "-c" | "-c!" | "--config" | "--config!" => {
                     let suppress_file_not_found = arg.ends_with('!');
                     match args.next() {
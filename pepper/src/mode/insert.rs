@@ -1,14 +1,17 @@
 use std::fmt::Write;
 
 use crate::{
-    buffer_position::{BufferPosition, BufferRange},
+    buffer::BufferHandle,
+    buffer_position::{BufferPosition, BufferPositionIndex, BufferRange},
     buffer_view::{BufferViewHandle, CursorMovement, CursorMovementKind},
     client::ClientHandle,
+    config::Config,
     editor::{Editor, EditorContext, EditorFlow, KeysIterator},
-    editor_utils::AUTO_MACRO_REGISTER,
+    editor_utils::{RegisterKind, AUTO_MACRO_REGISTER},
     mode::{ModeKind, ModeState},
     platform::Key,
     plugin::{CompletionContext, PluginHandle},
+    snippet::{Snippet, SnippetSession},
     word_database::{WordIndicesIter, WordKind},
 };
 
@@ -16,15 +19,52 @@ use crate::{
 pub struct State {
     completion_positions: Vec<BufferPosition>,
     completing_plugin_handle: Option<PluginHandle>,
+    completion_snippets: Vec<(String, String)>,
+    snippet_session: SnippetSession,
+}
+
+impl State {
+    pub fn clear_completion_snippets(&mut self) {
+        self.completion_snippets.clear();
+    }
+
+    // completion entries named `name` are expanded as `snippet` with `<tab>`
+    pub fn add_completion_snippet(&mut self, name: &str, snippet: &str) {
+        self.completion_snippets.push((name.into(), snippet.into()));
+    }
+
+    pub(crate) fn on_buffer_insert_text(
+        &mut self,
+        buffer_handle: BufferHandle,
+        range: BufferRange,
+    ) {
+        self.snippet_session.on_insert_text(buffer_handle, range);
+    }
+
+    pub(crate) fn on_buffer_delete_text(
+        &mut self,
+        buffer_handle: BufferHandle,
+        range: BufferRange,
+    ) {
+        self.snippet_session.on_delete_text(buffer_handle, range);
+    }
+
+    pub(crate) fn on_buffer_close(&mut self, buffer_handle: BufferHandle) {
+        if self.snippet_session.is_active_in(buffer_handle) {
+            self.snippet_session.end();
+        }
+    }
 }
 
 impl ModeState for State {
     fn on_enter(editor: &mut Editor) {
         cancel_completion(editor);
+        editor.mode.insert_state.snippet_session.end();
     }
 
     fn on_exit(editor: &mut Editor) {
         cancel_completion(editor);
+        editor.mode.insert_state.snippet_session.end();
     }
 
     fn on_keys(
@@ -41,7 +81,10 @@ impl ModeState for State {
         };
 
         let key = keys.next(&ctx.editor.buffered_keys);
-        let register = ctx.editor.registers.get_mut(AUTO_MACRO_REGISTER);
+        let register = ctx
+            .editor
+            .registers
+            .get_mut_as(AUTO_MACRO_REGISTER, RegisterKind::Macro);
         let _ = write!(register, "{}", key);
 
         match key {
@@ -94,7 +137,18 @@ impl ModeState for State {
                 cancel_completion(&mut ctx.editor);
                 return Some(EditorFlow::Continue);
             }
+            Key::Ctrl('j') | Key::Ctrl('k') => {
+                jump_to_snippet_stop(ctx, handle, key == Key::Ctrl('j'));
+                cancel_completion(&mut ctx.editor);
+                return Some(EditorFlow::Continue);
+            }
             Key::Tab => {
+                if jump_to_snippet_stop(ctx, handle, true) || expand_snippet(ctx, handle) {
+                    cancel_completion(&mut ctx.editor);
+                    return Some(EditorFlow::Continue);
+                }
+                delete_selections(ctx, handle);
+
                 static SPACES_BUF: &[u8; u8::MAX as usize] = &[b' '; u8::MAX as usize];
                 let text = if ctx.editor.config.indent_with_tabs {
                     "\t"
@@ -114,9 +168,13 @@ impl ModeState for State {
                     );
             }
             Key::Enter | Key::Ctrl('m') => {
+                delete_selections(ctx, handle);
+                dedent_lines_at_cursors(ctx, handle, None);
+                ctx.trigger_event_handlers();
                 let buffer_view = ctx.editor.buffer_views.get(handle);
                 let cursor_count = buffer_view.cursors[..].len();
                 let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+                let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
 
                 let mut buf = ctx.editor.string_pool.acquire();
                 for i in (0..cursor_count).rev() {
@@ -133,6 +191,16 @@ impl ModeState for State {
                         buf.push_str(&indentation_word.text[..indentation_len as usize]);
                     }
 
+                    let line = buffer.content().lines()[position.line_index as usize].as_str();
+                    let (before, after) = line.split_at(position.column_byte_index as usize);
+                    if syntax.should_indent_after(before) {
+                        push_indentation_level(&mut buf, &ctx.editor.config);
+                    }
+                    if syntax.should_dedent(after) {
+                        let len = indentation_level_len(&buf[1..], &ctx.editor.config);
+                        buf.drain(1..1 + len);
+                    }
+
                     buffer.insert_text(
                         &mut ctx.editor.word_database,
                         position,
@@ -144,43 +212,82 @@ impl ModeState for State {
                 ctx.editor.string_pool.release(buf);
             }
             Key::Char(c) => {
+                delete_selections(ctx, handle);
                 let mut buf = [0; std::mem::size_of::<char>()];
                 let s = c.encode_utf8(&mut buf);
                 let buffer_view = ctx.editor.buffer_views.get(handle);
-                buffer_view.insert_text_at_cursor_positions(
-                    &mut ctx.editor.buffers,
-                    &mut ctx.editor.word_database,
-                    s,
-                    &mut ctx.editor.events,
-                );
+                if ctx.editor.config.auto_pairs {
+                    let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                    let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+                    buffer_view.insert_char_with_pairs(
+                        &mut ctx.editor.buffers,
+                        &mut ctx.editor.word_database,
+                        c,
+                        syntax.pairs(),
+                        &mut ctx.editor.events,
+                    );
+                } else {
+                    buffer_view.insert_text_at_cursor_positions(
+                        &mut ctx.editor.buffers,
+                        &mut ctx.editor.word_database,
+                        s,
+                        &mut ctx.editor.events,
+                    );
+                }
+                dedent_lines_at_cursors(ctx, handle, Some(c));
             }
             Key::Backspace | Key::Ctrl('h') => {
-                let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                buffer_view.move_cursors(
-                    &ctx.editor.buffers,
-                    CursorMovement::ColumnsBackward(1),
-                    CursorMovementKind::PositionOnly,
-                    ctx.editor.config.tab_size,
-                );
-                buffer_view.delete_text_in_cursor_ranges(
-                    &mut ctx.editor.buffers,
-                    &mut ctx.editor.word_database,
-                    &mut ctx.editor.events,
-                );
+                if !delete_selections(ctx, handle) {
+                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+                    buffer_view.move_cursors(
+                        &ctx.editor.buffers,
+                        CursorMovement::ColumnsBackward(1),
+                        CursorMovementKind::PositionOnly,
+                        ctx.editor.config.tab_size,
+                    );
+                    if ctx.editor.config.auto_pairs {
+                        let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                        let pairs = ctx.editor.syntaxes.get(buffer.syntax_handle()).pairs();
+                        let content = buffer.content();
+                        for cursor in &mut buffer_view.cursors.mut_guard()[..] {
+                            if cursor.anchor.line_index != cursor.position.line_index {
+                                continue;
+                            }
+                            let line = content.lines()[cursor.anchor.line_index as usize].as_str();
+                            let (before, after) =
+                                line.split_at(cursor.anchor.column_byte_index as _);
+                            let previous_char = before.chars().next_back();
+                            let next_char = after.chars().next();
+                            if let (Some(p), Some(n)) = (previous_char, next_char) {
+                                if pairs.contains(&(p, n)) {
+                                    cursor.anchor.column_byte_index +=
+                                        n.len_utf8() as BufferPositionIndex;
+                                }
+                            }
+                        }
+                    }
+                    buffer_view.delete_text_in_cursor_ranges(
+                        &mut ctx.editor.buffers,
+                        &mut ctx.editor.word_database,
+                        &mut ctx.editor.events,
+                    );
+                }
             }
             Key::Delete => {
-                let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                buffer_view.move_cursors(
-                    &ctx.editor.buffers,
-                    CursorMovement::ColumnsForward(1),
-                    CursorMovementKind::PositionOnly,
-                    ctx.editor.config.tab_size,
-                );
-                buffer_view.delete_text_in_cursor_ranges(
-                    &mut ctx.editor.buffers,
-                    &mut ctx.editor.word_database,
-                    &mut ctx.editor.events,
-                );
+                if !delete_selections(ctx, handle) {
+                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+                    buffer_view.move_cursors(
+                        &ctx.editor.buffers,
+                        CursorMovement::ColumnsForward(1),
+                        CursorMovementKind::PositionOnly,
+                        ctx.editor.config.tab_size,
+                    );
+                    buffer_view.delete_text_in_cursor_ranges(
+                        &mut ctx.editor.buffers,
+                        &mut ctx.editor.word_database,
+                        &mut ctx.editor.events,
+                    );
+                }
             }
             Key::Ctrl('w') => {
                 let buffer_view = ctx.editor.buffer_views.get_mut(handle);
@@ -213,8 +320,202 @@ impl ModeState for State {
     }
 }
 
+fn push_indentation_level(text: &mut String, config: &Config) {
+    if config.indent_with_tabs {
+        text.push('\t');
+    } else {
+        for _ in 0..config.tab_size.get() {
+            text.push(' ');
+        }
+    }
+}
+
+fn indentation_level_len(line: &str, config: &Config) -> usize {
+    if line.starts_with('\t') {
+        1
+    } else {
+        let tab_size = config.tab_size.get() as usize;
+        line.bytes()
+            .take(tab_size)
+            .take_while(|&b| b == b' ')
+            .count()
+    }
+}
+
+// after typing, dedents lines whose content becomes a match of its syntax's dedent pattern
+// as long as they're not already less indented than the line above them.
+// symbols (like `}`) match as soon as they're typed while keywords (like `else`) only match once
+// followed by a non identifier char or a line break (`typed_char` is `None`) so that identifiers
+// starting with them (like `else_value`) are left alone
+fn dedent_lines_at_cursors(
+    ctx: &mut EditorContext,
+    handle: BufferViewHandle,
+    typed_char: Option<char>,
+) {
+    let inserted_len = match typed_char {
+        Some(c) if WordKind::from_char(c) == WordKind::Identifier => return,
+        Some(c) => c.len_utf8(),
+        None => 0,
+    };
+
+    let buffer_view = ctx.editor.buffer_views.get(handle);
+    let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+    let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+
+    // cursors have not yet been moved by the insertion.
+    // only the first cursor on each line is considered
+    let mut previous_line_index = None;
+    for cursor in &buffer_view.cursors[..] {
+        let line_index = cursor.position.line_index;
+        if previous_line_index == Some(line_index) {
+            continue;
+        }
+        previous_line_index = Some(line_index);
+
+        let lines = buffer.content().lines();
+        let line = lines[line_index as usize].as_str();
+        let column = cursor.position.column_byte_index as usize + inserted_len;
+        let indentation_len = line.len() - line.trim_start().len();
+        if column <= indentation_len {
+            continue;
+        }
+
+        let text = &line[..column];
+        let keyword = &line[..column - inserted_len];
+        let is_symbol_match =
+            matches!(typed_char, Some(c) if !c.is_whitespace()) && syntax.should_dedent(text);
+        let is_keyword_match = matches!(
+            keyword.chars().next_back().map(WordKind::from_char),
+            Some(WordKind::Identifier)
+        ) && syntax.should_dedent(keyword);
+        if !is_symbol_match && !is_keyword_match {
+            continue;
+        }
+
+        let previous_line = (0..line_index as usize)
+            .rev()
+            .map(|i| lines[i].as_str())
+            .find(|l| !l.trim_start().is_empty());
+        if let Some(previous_line) = previous_line {
+            let previous_indentation_len = previous_line.len() - previous_line.trim_start().len();
+            if indentation_len < previous_indentation_len {
+                continue;
+            }
+        }
+
+        let len = indentation_level_len(line, &ctx.editor.config);
+        if len > 0 {
+            let range = BufferRange::between(
+                BufferPosition::line_col(line_index, 0),
+                BufferPosition::line_col(line_index, len as _),
+            );
+            buffer.delete_range(&mut ctx.editor.word_database, range, &mut ctx.editor.events);
+        }
+    }
+}
+
+// typing over selections (like snippet placeholders) replaces them
+fn delete_selections(ctx: &mut EditorContext, handle: BufferViewHandle) -> bool {
+    let buffer_view = ctx.editor.buffer_views.get(handle);
+    if buffer_view.cursors[..]
+        .iter()
+        .all(|c| c.anchor == c.position)
+    {
+        return false;
+    }
+    buffer_view.delete_text_in_cursor_ranges(
+        &mut ctx.editor.buffers,
+        &mut ctx.editor.word_database,
+        &mut ctx.editor.events,
+    );
+    ctx.trigger_event_handlers();
+    true
+}
+
+fn jump_to_snippet_stop(ctx: &mut EditorContext, handle: BufferViewHandle, forward: bool) -> bool {
+    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+    let session = &mut ctx.editor.mode.insert_state.snippet_session;
+    if !session.is_active_in(buffer_view.buffer_handle) {
+        return false;
+    }
+    session.jump(forward, &mut buffer_view.cursors.mut_guard());
+    true
+}
+
+// expands the snippet named by the text before the main cursor.
+// it's either a snippet from the current completions (like the ones from lsp servers)
+// or one from the buffer's syntax (see `syntax-snippet`)
+fn expand_snippet(ctx: &mut EditorContext, handle: BufferViewHandle) -> bool {
+    let buffer_view = ctx.editor.buffer_views.get(handle);
+    let state = &ctx.editor.mode.insert_state;
+
+    let mut positions = Vec::new();
+    if state.completion_positions.len() == buffer_view.cursors[..].len() {
+        positions.extend_from_slice(&state.completion_positions);
+    } else {
+        buffer_view.find_completion_positions(&mut ctx.editor.buffers, &mut positions);
+    }
+
+    let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+    let main_cursor_position = buffer_view.cursors.main_cursor().position;
+    let position = positions[buffer_view.cursors.main_cursor_index()];
+    if position.line_index != main_cursor_position.line_index
+        || position.column_byte_index >= main_cursor_position.column_byte_index
+    {
+        return false;
+    }
+    let line = buffer.content().lines()[position.line_index as usize].as_str();
+    let name =
+        &line[position.column_byte_index as usize..main_cursor_position.column_byte_index as usize];
+
+    let snippet = match state.completion_snippets.iter().find(|(n, _)| n == name) {
+        Some((_, snippet)) => Snippet::parse(snippet, false),
+        None => match ctx
+            .editor
+            .syntaxes
+            .get(buffer.syntax_handle())
+            .snippet(name)
+        {
+            // config commands are single line so `\n` is used for line breaks
+            Some(snippet) => Snippet::parse(snippet, true),
+            None => return false,
+        },
+    };
+
+    insert_snippet(ctx, handle, &snippet, &positions);
+    true
+}
+
+fn insert_snippet(
+    ctx: &mut EditorContext,
+    handle: BufferViewHandle,
+    snippet: &Snippet,
+    positions: &[BufferPosition],
+) {
+    let buffer_view = ctx.editor.buffer_views.get(handle);
+    let mut stop_ranges = Vec::new();
+    buffer_view.insert_snippet(
+        &mut ctx.editor.buffers,
+        &mut ctx.editor.word_database,
+        snippet,
+        positions,
+        &mut stop_ranges,
+        &mut ctx.editor.events,
+    );
+    ctx.trigger_event_handlers();
+
+    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+    ctx.editor.mode.insert_state.snippet_session.start(
+        buffer_view.buffer_handle,
+        snippet.stop_count(),
+        &stop_ranges,
+        &mut buffer_view.cursors.mut_guard(),
+    );
+}
+
 fn cancel_completion(editor: &mut Editor) {
     editor.picker.clear();
+    editor.mode.insert_state.completion_snippets.clear();
     editor.mode.insert_state.completion_positions.clear();
     editor.mode.insert_state.completing_plugin_handle = None;
 }
@@ -256,6 +557,7 @@ fn update_completions(
             }
             None => {
                 ctx.editor.picker.clear();
+                ctx.editor.mode.insert_state.completion_snippets.clear();
 
                 let completion_requested = word.kind == WordKind::Identifier
                     && word.text.len() >= ctx.editor.config.completion_min_len as _;
@@ -400,13 +702,29 @@ fn apply_completion(
     };
 
     let completion = ctx.editor.string_pool.acquire_with(entry);
-    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
-    buffer_view.apply_completion(
-        &mut ctx.editor.buffers,
-        &mut ctx.editor.word_database,
-        &completion,
-        &ctx.editor.mode.insert_state.completion_positions,
-        &mut ctx.editor.events,
-    );
+    let state = &ctx.editor.mode.insert_state;
+    let snippet = state
+        .completion_snippets
+        .iter()
+        .find(|(n, _)| *n == completion)
+        .map(|(_, snippet)| Snippet::parse(snippet, false));
+    match snippet {
+        // snippets that are just text are not expanded so it's still possible to cycle past them
+        Some(snippet) if snippet.stop_count() > 1 || snippet.text() != completion => {
+            let positions = state.completion_positions.clone();
+            insert_snippet(ctx, buffer_view_handle, &snippet, &positions);
+            cancel_completion(&mut ctx.editor);
+        }
+        _ => {
+            let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+            buffer_view.apply_completion(
+                &mut ctx.editor.buffers,
+                &mut ctx.editor.word_database,
+                &completion,
+                &state.completion_positions,
+                &mut ctx.editor.events,
+            );
+        }
+    }
     ctx.editor.string_pool.release(completion);
 }
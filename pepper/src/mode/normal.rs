@@ -3,20 +3,23 @@ use std::{cmp::Ordering, fmt::Write, path::Path};
 use crate::{
     buffer::{
         find_path_and_position_at, parse_path_and_position, BufferContent, BufferHandle,
-        BufferProperties,
+        BufferProperties, SurroundPair,
     },
     buffer_position::{BufferPosition, BufferPositionIndex, BufferRange},
     buffer_view::{BufferViewHandle, CursorMovement, CursorMovementKind},
     client::{ClientHandle, ViewAnchor},
     cursor::{Cursor, CursorCollection},
     editor::{Editor, EditorContext, EditorFlow, KeysIterator},
-    editor_utils::{hash_bytes, MessageKind, RegisterKey, AUTO_MACRO_REGISTER, SEARCH_REGISTER},
+    editor_utils::{
+        hash_bytes, MessageKind, RegisterKey, RegisterKind, AUTO_MACRO_REGISTER, SEARCH_REGISTER,
+    },
     help::HELP_PREFIX,
+    jump_labels::{JumpLabelMatch, JumpLabels},
     mode::{picker, read_line, ModeKind, ModeState},
     navigation_history::{NavigationHistory, NavigationMovement},
     pattern::PatternEscaper,
     platform::Key,
-    word_database::WordKind,
+    word_database::{WordIter, WordKind},
 };
 
 enum CharJump {
@@ -33,6 +36,7 @@ pub struct State {
     pub count: u32,
     last_copy_hash: u64,
     last_copy_ranges: Vec<(BufferPositionIndex, BufferPositionIndex)>,
+    pub jump_labels: JumpLabels,
 }
 
 impl State {
@@ -41,7 +45,9 @@ impl State {
         match state.movement_kind {
             CursorMovementKind::PositionAndAnchor => state.is_recording_auto_macro = false,
             CursorMovementKind::PositionOnly => {
-                let auto_macro_register = editor.registers.get_mut(AUTO_MACRO_REGISTER);
+                let auto_macro_register = editor
+                    .registers
+                    .get_mut_as(AUTO_MACRO_REGISTER, RegisterKind::Macro);
 
                 if !state.is_recording_auto_macro {
                     auto_macro_register.clear();
@@ -60,7 +66,9 @@ impl State {
     }
 
     fn on_edit_keys(editor: &mut Editor, keys: &KeysIterator, from_index: usize) {
-        let auto_macro_register = editor.registers.get_mut(AUTO_MACRO_REGISTER);
+        let auto_macro_register = editor
+            .registers
+            .get_mut_as(AUTO_MACRO_REGISTER, RegisterKind::Macro);
         let state = &mut editor.mode.normal_state;
         if !state.is_recording_auto_macro {
             auto_macro_register.clear();
@@ -74,6 +82,8 @@ impl State {
         for key in &editor.buffered_keys.as_slice()[from_index..keys.index] {
             let _ = write!(auto_macro_register, "{}", key);
         }
+
+        state.count = 0;
     }
 
     fn on_client_keys_with_buffer_view(
@@ -82,8 +92,8 @@ impl State {
         keys: &mut KeysIterator,
         handle: BufferViewHandle,
     ) -> Option<EditorFlow> {
-        let state = &mut ctx.editor.mode.normal_state;
         let keys_from_index = keys.index;
+        let state = &mut ctx.editor.mode.normal_state;
         match keys.next(&ctx.editor.buffered_keys) {
             Key::Char('h') => ctx.editor.buffer_views.get_mut(handle).move_cursors(
                 &ctx.editor.buffers,
@@ -211,6 +221,33 @@ impl State {
                             ),
                         });
                     }
+                    Key::Char('i') => {
+                        let tab_size = ctx.editor.config.tab_size.get();
+                        for cursor in &mut cursors[..] {
+                            let range = buffer.find_indentation_block_at(
+                                cursor.position.line_index,
+                                tab_size,
+                                false,
+                            );
+                            cursor.anchor = range.from;
+                            cursor.position = range.to;
+                        }
+                    }
+                    Key::Char('p') => {
+                        for cursor in &mut cursors[..] {
+                            let range = buffer.find_paragraph_at(cursor.position.line_index, false);
+                            cursor.anchor = range.from;
+                            cursor.position = range.to;
+                        }
+                    }
+                    Key::Char(',') => {
+                        for cursor in &mut cursors[..] {
+                            if let Some(range) = buffer.find_argument_at(cursor.position, false) {
+                                cursor.anchor = range.from;
+                                cursor.position = range.to;
+                            }
+                        }
+                    }
                     Key::Char('(' | ')') => balanced_brackets(buffer, &mut cursors[..], '(', ')'),
                     Key::Char('[' | ']') => balanced_brackets(buffer, &mut cursors[..], '[', ']'),
                     Key::Char('{' | '}') => balanced_brackets(buffer, &mut cursors[..], '{', '}'),
@@ -301,6 +338,33 @@ impl State {
                             ),
                         });
                     }
+                    Key::Char('i') => {
+                        let tab_size = ctx.editor.config.tab_size.get();
+                        for cursor in &mut cursors[..] {
+                            let range = buffer.find_indentation_block_at(
+                                cursor.position.line_index,
+                                tab_size,
+                                true,
+                            );
+                            cursor.anchor = range.from;
+                            cursor.position = range.to;
+                        }
+                    }
+                    Key::Char('p') => {
+                        for cursor in &mut cursors[..] {
+                            let range = buffer.find_paragraph_at(cursor.position.line_index, true);
+                            cursor.anchor = range.from;
+                            cursor.position = range.to;
+                        }
+                    }
+                    Key::Char(',') => {
+                        for cursor in &mut cursors[..] {
+                            if let Some(range) = buffer.find_argument_at(cursor.position, true) {
+                                cursor.anchor = range.from;
+                                cursor.position = range.to;
+                            }
+                        }
+                    }
                     Key::Char('(' | ')') => balanced_brackets(buffer, &mut cursors[..], '(', ')'),
                     Key::Char('[' | ']') => balanced_brackets(buffer, &mut cursors[..], '[', ']'),
                     Key::Char('{' | '}') => balanced_brackets(buffer, &mut cursors[..], '{', '}'),
@@ -388,6 +452,18 @@ impl State {
                         state.movement_kind,
                         ctx.editor.config.tab_size,
                     ),
+                    Key::Ctrl(c @ ('a' | 'x')) => {
+                        let count = state.count.max(1) as i64;
+                        let delta = if c == 'a' { count } else { -count };
+                        add_to_numbers(ctx, handle, delta, true);
+                        Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
+                        return Some(EditorFlow::Continue);
+                    }
+                    Key::Char('c') => {
+                        toggle_comments(ctx, handle);
+                        Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
+                        return Some(EditorFlow::Continue);
+                    }
                     Key::Char('m') => {
                         let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle).content();
                         for cursor in &mut buffer_view.cursors.mut_guard()[..] {
@@ -440,123 +516,21 @@ impl State {
                         }
                     }
                     Key::Char(c @ ('f' | 'F')) => {
-                        let should_close_current_buffer = c == 'F';
-                        let buffer_handle = buffer_view.buffer_handle;
-
-                        let mut len = 0;
-                        let mut ranges = [BufferRange::zero(); CursorCollection::capacity()];
-                        for cursor in &buffer_view.cursors[..] {
-                            ranges[len] = cursor.to_range();
-                            len += 1;
-                        }
-
-                        let mut jumped = false;
-                        let mut path_buf = ctx.editor.string_pool.acquire();
-                        let mut error_buf = ctx.editor.string_pool.acquire();
-                        let fallback_line_index = state.count.saturating_sub(1) as _;
-
-                        for range in &ranges[..len] {
-                            let line_index = range.from.line_index;
-                            if range.to.line_index != line_index {
-                                continue;
-                            }
-
-                            let buffer = ctx.editor.buffers.get(buffer_handle);
-                            let line = buffer.content().lines()[line_index as usize].as_str();
-
-                            let from = range.from.column_byte_index;
-                            let to = range.to.column_byte_index;
-
-                            let (path, position) = if from < to {
-                                parse_path_and_position(&line[from as usize..to as usize])
-                            } else {
-                                find_path_and_position_at(line, from as _)
-                            };
-                            let position = match position {
-                                Some(position) => position,
-                                None => BufferPosition::line_col(fallback_line_index, 0),
-                            };
-
-                            path_buf.clear();
-                            if Path::new(path).is_relative() {
-                                if buffer.path.starts_with(HELP_PREFIX) {
-                                    path_buf.push_str(HELP_PREFIX);
-                                } else if let Some(parent) =
-                                    buffer.path.parent().and_then(Path::to_str)
-                                {
-                                    if !parent.is_empty() {
-                                        path_buf.push_str(parent);
-                                        path_buf.push('/');
-                                    }
-                                }
-                            }
-                            path_buf.push_str(path);
-
-                            match ctx.editor.buffer_view_handle_from_path(
-                                client_handle,
-                                Path::new(&path_buf),
-                                BufferProperties::text(),
-                                false,
-                            ) {
-                                Ok(buffer_view_handle) => {
-                                    if jumped {
-                                        continue;
-                                    }
-                                    jumped = true;
-
-                                    ctx.editor.mode.normal_state.movement_kind =
-                                        CursorMovementKind::PositionAndAnchor;
-                                    let client = ctx.clients.get_mut(client_handle);
-                                    if should_close_current_buffer {
-                                        if let Some(buffer_view_handle) =
-                                            client.buffer_view_handle()
-                                        {
-                                            let buffer_view =
-                                                ctx.editor.buffer_views.get(buffer_view_handle);
-                                            ctx.editor.buffers.defer_remove(
-                                                buffer_view.buffer_handle,
-                                                &mut ctx.editor.events,
-                                            );
-                                        }
-                                    }
-                                    client.set_buffer_view_handle(
-                                        Some(buffer_view_handle),
-                                        &ctx.editor.buffer_views,
-                                    );
-
-                                    let buffer_view =
-                                        ctx.editor.buffer_views.get_mut(buffer_view_handle);
-                                    let position = ctx
-                                        .editor
-                                        .buffers
-                                        .get(buffer_view.buffer_handle)
-                                        .content()
-                                        .saturate_position(position);
-                                    let mut cursors = buffer_view.cursors.mut_guard();
-                                    cursors.clear();
-                                    cursors.add(Cursor {
-                                        anchor: position,
-                                        position,
-                                    });
-                                }
-                                Err(error) => {
-                                    if !error_buf.is_empty() {
-                                        error_buf.push('\n');
-                                    }
-                                    let _ = write!(error_buf, "{}", error);
-                                }
-                            }
-                        }
-
-                        if !error_buf.is_empty() {
-                            ctx.editor
-                                .status_bar
-                                .write(MessageKind::Error)
-                                .str(&error_buf);
+                        open_paths_at_cursors(ctx, client_handle, handle, c == 'F')
+                    }
+                    Key::Char(c @ ('w' | 'W' | 'n' | 'N')) => {
+                        let search_matches = c == 'n' || c == 'N';
+                        let add_cursor = c.is_ascii_uppercase();
+                        if !jump_to_label(
+                            ctx,
+                            client_handle,
+                            handle,
+                            keys,
+                            search_matches,
+                            add_cursor,
+                        ) {
+                            return None;
                         }
-
-                        ctx.editor.string_pool.release(path_buf);
-                        ctx.editor.string_pool.release(error_buf);
                     }
                     _ => (),
                 }
@@ -872,6 +846,98 @@ impl State {
                 Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
                 return Some(EditorFlow::Continue);
             }
+            Key::Ctrl(c @ ('a' | 'x')) => {
+                let count = state.count.max(1) as i64;
+                let delta = if c == 'a' { count } else { -count };
+                add_to_numbers(ctx, handle, delta, false);
+                Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
+                return Some(EditorFlow::Continue);
+            }
+            Key::Char('S') => {
+                match keys.next(&ctx.editor.buffered_keys) {
+                    Key::None => return None,
+                    Key::Char('a') => match keys.next(&ctx.editor.buffered_keys) {
+                        Key::None => return None,
+                        Key::Char('t') => {
+                            Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
+                            read_line::surround::enter_tag_mode(ctx, None);
+                            return Some(EditorFlow::Continue);
+                        }
+                        Key::Char(c) => match surround_pair_delimiters(c) {
+                            Some((open, close)) => {
+                                let buffer_view = ctx.editor.buffer_views.get(handle);
+                                buffer_view.add_surround(
+                                    &mut ctx.editor.buffers,
+                                    &mut ctx.editor.word_database,
+                                    open.encode_utf8(&mut [0; 4]),
+                                    close.encode_utf8(&mut [0; 4]),
+                                    &mut ctx.editor.events,
+                                );
+                            }
+                            None => return Some(EditorFlow::Continue),
+                        },
+                        _ => return Some(EditorFlow::Continue),
+                    },
+                    Key::Char('c') => {
+                        let pair = match keys.next(&ctx.editor.buffered_keys) {
+                            Key::None => return None,
+                            Key::Char(c) => match surround_pair_from_char(c) {
+                                Some(pair) => pair,
+                                None => return Some(EditorFlow::Continue),
+                            },
+                            _ => return Some(EditorFlow::Continue),
+                        };
+                        match keys.next(&ctx.editor.buffered_keys) {
+                            Key::None => return None,
+                            Key::Char('t') => {
+                                Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
+                                read_line::surround::enter_tag_mode(ctx, Some(pair));
+                                return Some(EditorFlow::Continue);
+                            }
+                            Key::Char(c) => match surround_pair_delimiters(c) {
+                                Some((open, close)) => {
+                                    let buffer_view = ctx.editor.buffer_views.get(handle);
+                                    buffer_view.change_surround(
+                                        &mut ctx.editor.buffers,
+                                        &mut ctx.editor.word_database,
+                                        pair,
+                                        open.encode_utf8(&mut [0; 4]),
+                                        close.encode_utf8(&mut [0; 4]),
+                                        &mut ctx.editor.events,
+                                    );
+                                }
+                                None => return Some(EditorFlow::Continue),
+                            },
+                            _ => return Some(EditorFlow::Continue),
+                        }
+                    }
+                    Key::Char('d') => match keys.next(&ctx.editor.buffered_keys) {
+                        Key::None => return None,
+                        Key::Char(c) => match surround_pair_from_char(c) {
+                            Some(pair) => {
+                                let buffer_view = ctx.editor.buffer_views.get(handle);
+                                buffer_view.delete_surround(
+                                    &mut ctx.editor.buffers,
+                                    &mut ctx.editor.word_database,
+                                    pair,
+                                    &mut ctx.editor.events,
+                                );
+                            }
+                            None => return Some(EditorFlow::Continue),
+                        },
+                        _ => return Some(EditorFlow::Continue),
+                    },
+                    _ => return Some(EditorFlow::Continue),
+                }
+
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                ctx.editor
+                    .buffers
+                    .get_mut(buffer_view.buffer_handle)
+                    .commit_edits();
+                Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
+                return Some(EditorFlow::Continue);
+            }
             Key::Char('c' | 'C') => match keys.next(&ctx.editor.buffered_keys) {
                 Key::None => return None,
                 Key::Char('c') => {
@@ -1045,7 +1111,7 @@ impl State {
                 Key::None => return None,
                 Key::Char(c) => {
                     if let Some(key) = RegisterKey::from_char(c) {
-                        let register = ctx.editor.registers.get_mut(key);
+                        let register = ctx.editor.registers.get_mut_as(key, RegisterKind::Marker);
                         register.clear();
 
                         let buffer_view = ctx.editor.buffer_views.get(handle);
@@ -1098,10 +1164,7 @@ impl State {
                         }
                     } else {
                         if let Some(key) = RegisterKey::from_char(key) {
-                            let register = ctx.editor.registers.get(key);
-                            let text = ctx.editor.string_pool.acquire_with(register);
-                            paste_text(ctx, handle, &text);
-                            ctx.editor.string_pool.release(text);
+                            paste_register(ctx, handle, key);
                             return Some(EditorFlow::Continue);
                         }
                     }
@@ -1110,24 +1173,41 @@ impl State {
             },
             Key::Char('|') => read_line::process::enter_replace_mode(ctx),
             Key::Char('!') => read_line::process::enter_insert_mode(ctx),
+            Key::Enter => {
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                if ctx
+                    .editor
+                    .buffers
+                    .get(buffer_view.buffer_handle)
+                    .is_directory()
+                {
+                    open_paths_at_cursors(ctx, client_handle, handle, false);
+                }
+            }
             Key::Char('u') => {
                 let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                buffer_view.undo(
-                    &mut ctx.editor.buffers,
-                    &mut ctx.editor.word_database,
-                    &mut ctx.editor.events,
-                );
+                for _ in 0..state.count.max(1) {
+                    buffer_view.undo(
+                        &mut ctx.editor.buffers,
+                        &mut ctx.editor.word_database,
+                        &mut ctx.editor.events,
+                    );
+                }
                 state.movement_kind = CursorMovementKind::PositionAndAnchor;
+                state.count = 0;
                 return Some(EditorFlow::Continue);
             }
             Key::Char('U') => {
                 let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                buffer_view.redo(
-                    &mut ctx.editor.buffers,
-                    &mut ctx.editor.word_database,
-                    &mut ctx.editor.events,
-                );
+                for _ in 0..state.count.max(1) {
+                    buffer_view.redo(
+                        &mut ctx.editor.buffers,
+                        &mut ctx.editor.word_database,
+                        &mut ctx.editor.events,
+                    );
+                }
                 state.movement_kind = CursorMovementKind::PositionAndAnchor;
+                state.count = 0;
                 return Some(EditorFlow::Continue);
             }
             _ => (),
@@ -1149,6 +1229,7 @@ impl Default for State {
             count: 0,
             last_copy_hash: 0,
             last_copy_ranges: Vec::new(),
+            jump_labels: JumpLabels::default(),
         }
     }
 }
@@ -1159,9 +1240,12 @@ impl ModeState for State {
         state.movement_kind = CursorMovementKind::PositionAndAnchor;
         state.is_recording_auto_macro = false;
         state.count = 0;
+        state.jump_labels.clear();
     }
 
-    fn on_exit(_: &mut Editor) {}
+    fn on_exit(editor: &mut Editor) {
+        editor.mode.normal_state.jump_labels.clear();
+    }
 
     fn on_keys(
         ctx: &mut EditorContext,
@@ -1224,7 +1308,10 @@ impl ModeState for State {
                     Key::Char(c) => {
                         if let Some(key) = RegisterKey::from_char(c) {
                             handled_keys = true;
-                            ctx.editor.registers.get_mut(key).clear();
+                            ctx.editor
+                                .registers
+                                .get_mut_as(key, RegisterKind::Macro)
+                                .clear();
                             ctx.editor.recording_macro = Some(key);
                         }
                     }
@@ -1238,7 +1325,9 @@ impl ModeState for State {
                     Key::None => return None,
                     Key::Char(c) => {
                         if let Some(key) = RegisterKey::from_char(c.to_ascii_lowercase()) {
-                            for _ in 0..state.count.max(1) {
+                            let count = state.count.max(1);
+                            state.count = 0;
+                            for _ in 0..count {
                                 let keys = ctx.editor.registers.get(key);
                                 match ctx.editor.buffered_keys.parse(keys) {
                                     Ok(keys) => {
@@ -1263,45 +1352,8 @@ impl ModeState for State {
                 Key::None => return None,
                 Key::Char(c) => {
                     handled_keys = true;
-                    let c = c.to_ascii_lowercase();
-                    if let Some(key) = RegisterKey::from_char(c) {
-                        let register = ctx.editor.registers.get(key);
-                        let (path, position) = parse_path_and_position(register);
-                        let path = ctx.editor.string_pool.acquire_with(path);
-                        match ctx.editor.buffer_view_handle_from_path(
-                            client_handle,
-                            Path::new(&path),
-                            BufferProperties::text(),
-                            false,
-                        ) {
-                            Ok(handle) => {
-                                let client = ctx.clients.get_mut(client_handle);
-                                client
-                                    .set_buffer_view_handle(Some(handle), &ctx.editor.buffer_views);
-
-                                if let Some(position) = position {
-                                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                                    let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
-                                    let position = buffer.content().saturate_position(position);
-
-                                    let mut cursors = buffer_view.cursors.mut_guard();
-                                    cursors.clear();
-                                    cursors.add(Cursor {
-                                        anchor: position,
-                                        position,
-                                    });
-                                }
-
-                                ctx.editor.mode.normal_state.movement_kind =
-                                    CursorMovementKind::PositionAndAnchor;
-                            }
-                            Err(error) => ctx
-                                .editor
-                                .status_bar
-                                .write(MessageKind::Error)
-                                .fmt(format_args!("invalid marker '{}': {}", &path, error)),
-                        }
-                        ctx.editor.string_pool.release(path);
+                    if let Some(key) = RegisterKey::from_char(c.to_ascii_lowercase()) {
+                        go_to_marker(ctx, client_handle, key);
                     }
                 }
                 _ => (),
@@ -1421,6 +1473,166 @@ impl ModeState for State {
     }
 }
 
+fn open_paths_at_cursors(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    handle: BufferViewHandle,
+    should_close_current_buffer: bool,
+) {
+    let buffer_view = ctx.editor.buffer_views.get(handle);
+    let buffer_handle = buffer_view.buffer_handle;
+
+    let mut len = 0;
+    let mut ranges = [BufferRange::zero(); CursorCollection::capacity()];
+    for cursor in &buffer_view.cursors[..] {
+        ranges[len] = cursor.to_range();
+        len += 1;
+    }
+
+    let mut jumped = false;
+    let mut path_buf = ctx.editor.string_pool.acquire();
+    let mut error_buf = ctx.editor.string_pool.acquire();
+    let fallback_line_index = ctx.editor.mode.normal_state.count.saturating_sub(1) as _;
+
+    for range in &ranges[..len] {
+        let line_index = range.from.line_index;
+        if range.to.line_index != line_index {
+            continue;
+        }
+
+        let buffer = ctx.editor.buffers.get(buffer_handle);
+        let line = buffer.content().lines()[line_index as usize].as_str();
+
+        let from = range.from.column_byte_index;
+        let to = range.to.column_byte_index;
+
+        let (path, position) = if buffer.is_directory() {
+            (line, None)
+        } else if from < to {
+            parse_path_and_position(&line[from as usize..to as usize])
+        } else {
+            find_path_and_position_at(line, from as _)
+        };
+        let position = match position {
+            Some(position) => position,
+            None => BufferPosition::line_col(fallback_line_index, 0),
+        };
+
+        path_buf.clear();
+        if Path::new(path).is_relative() {
+            if buffer.path.starts_with(HELP_PREFIX) {
+                path_buf.push_str(HELP_PREFIX);
+            } else if buffer.is_directory() {
+                if let Some(directory) = buffer.path.to_str() {
+                    path_buf.push_str(directory.trim_end_matches('/'));
+                    path_buf.push('/');
+                }
+            } else if let Some(parent) = buffer.path.parent().and_then(Path::to_str) {
+                if !parent.is_empty() {
+                    path_buf.push_str(parent);
+                    path_buf.push('/');
+                }
+            }
+        }
+        path_buf.push_str(path);
+
+        match ctx.editor.buffer_view_handle_from_path(
+            client_handle,
+            Path::new(&path_buf),
+            BufferProperties::text(),
+            false,
+        ) {
+            Ok(buffer_view_handle) => {
+                if jumped {
+                    continue;
+                }
+                jumped = true;
+
+                ctx.editor.mode.normal_state.movement_kind = CursorMovementKind::PositionAndAnchor;
+                let client = ctx.clients.get_mut(client_handle);
+                if should_close_current_buffer {
+                    if let Some(buffer_view_handle) = client.buffer_view_handle() {
+                        let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+                        ctx.editor
+                            .buffers
+                            .defer_remove(buffer_view.buffer_handle, &mut ctx.editor.events);
+                    }
+                }
+                client.set_buffer_view_handle(Some(buffer_view_handle), &ctx.editor.buffer_views);
+
+                let buffer_view = ctx.editor.buffer_views.get_mut(buffer_view_handle);
+                let position = ctx
+                    .editor
+                    .buffers
+                    .get(buffer_view.buffer_handle)
+                    .content()
+                    .saturate_position(position);
+                let mut cursors = buffer_view.cursors.mut_guard();
+                cursors.clear();
+                cursors.add(Cursor {
+                    anchor: position,
+                    position,
+                });
+            }
+            Err(error) => {
+                if !error_buf.is_empty() {
+                    error_buf.push('\n');
+                }
+                let _ = write!(error_buf, "{}", error);
+            }
+        }
+    }
+
+    if !error_buf.is_empty() {
+        ctx.editor
+            .status_bar
+            .write(MessageKind::Error)
+            .str(&error_buf);
+    }
+
+    ctx.editor.string_pool.release(path_buf);
+    ctx.editor.string_pool.release(error_buf);
+}
+
+// opens the `path:line,col` stored in a register by `m<char>`
+pub(crate) fn go_to_marker(ctx: &mut EditorContext, client_handle: ClientHandle, key: RegisterKey) {
+    let register = ctx.editor.registers.get(key);
+    let (path, position) = parse_path_and_position(register);
+    let path = ctx.editor.string_pool.acquire_with(path);
+    match ctx.editor.buffer_view_handle_from_path(
+        client_handle,
+        Path::new(&path),
+        BufferProperties::text(),
+        false,
+    ) {
+        Ok(handle) => {
+            let client = ctx.clients.get_mut(client_handle);
+            client.set_buffer_view_handle(Some(handle), &ctx.editor.buffer_views);
+
+            if let Some(position) = position {
+                let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let position = buffer.content().saturate_position(position);
+
+                let mut cursors = buffer_view.cursors.mut_guard();
+                cursors.clear();
+                cursors.add(Cursor {
+                    anchor: position,
+                    position,
+                });
+            }
+
+            ctx.editor.mode.normal_state.movement_kind = CursorMovementKind::PositionAndAnchor;
+        }
+        Err(error) => ctx
+            .editor
+            .status_bar
+            .write(MessageKind::Error)
+            .fmt(format_args!("invalid marker '{}': {}", &path, error)),
+    }
+    ctx.editor.string_pool.release(path);
+}
+
 fn copy_text(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle, text: &mut String) {
     let state = &mut ctx.editor.mode.normal_state;
     let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
@@ -1437,6 +1649,17 @@ fn copy_text(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle, text
     state.movement_kind = CursorMovementKind::PositionAndAnchor;
 }
 
+pub(crate) fn paste_register(
+    ctx: &mut EditorContext,
+    buffer_view_handle: BufferViewHandle,
+    key: RegisterKey,
+) {
+    let register = ctx.editor.registers.get(key);
+    let text = ctx.editor.string_pool.acquire_with(register);
+    paste_text(ctx, buffer_view_handle, &text);
+    ctx.editor.string_pool.release(text);
+}
+
 fn paste_text(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle, text: &str) {
     let state = &mut ctx.editor.mode.normal_state;
     let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
@@ -1448,6 +1671,7 @@ fn paste_text(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle, tex
 
     state.movement_kind = CursorMovementKind::PositionAndAnchor;
     state.is_recording_auto_macro = false;
+    state.count = 0;
 
     ctx.trigger_event_handlers();
 
@@ -1729,3 +1953,181 @@ fn move_to_lint(ctx: &mut EditorContext, client_handle: ClientHandle, forward: b
         position,
     });
 }
+
+fn add_to_numbers(
+    ctx: &mut EditorContext,
+    buffer_view_handle: BufferViewHandle,
+    delta: i64,
+    is_sequence: bool,
+) {
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    buffer_view.add_to_numbers(
+        &mut ctx.editor.buffers,
+        &mut ctx.editor.word_database,
+        delta,
+        is_sequence,
+        &mut ctx.editor.events,
+    );
+    ctx.editor
+        .buffers
+        .get_mut(buffer_view.buffer_handle)
+        .commit_edits();
+}
+
+// labels visible words (or search matches) and then moves the main cursor (or adds a new one)
+// to the position whose label is typed. returns false while waiting for the label keys
+fn jump_to_label(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_view_handle: BufferViewHandle,
+    keys: &mut KeysIterator,
+    search_matches: bool,
+    add_cursor: bool,
+) -> bool {
+    if ctx
+        .editor
+        .mode
+        .normal_state
+        .jump_labels
+        .buffer_view_handle()
+        != Some(buffer_view_handle)
+    {
+        let line_range = ctx
+            .clients
+            .get(client_handle)
+            .visible_line_range(&ctx.editor);
+        let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+        let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+
+        let mut positions = Vec::new();
+        if search_matches {
+            for range in buffer.search_ranges() {
+                if line_range.contains(&(range.from.line_index as _)) {
+                    positions.push(range.from);
+                }
+            }
+        } else {
+            let lines = buffer.content().lines();
+            for line_index in line_range {
+                let mut column_byte_index = 0;
+                for word in WordIter(lines[line_index].as_str()) {
+                    if word.kind == WordKind::Identifier {
+                        positions.push(BufferPosition::line_col(
+                            line_index as _,
+                            column_byte_index as _,
+                        ));
+                    }
+                    column_byte_index += word.text.len();
+                }
+            }
+        }
+
+        if positions.is_empty() {
+            return true;
+        }
+        ctx.editor
+            .mode
+            .normal_state
+            .jump_labels
+            .set(buffer_view_handle, positions.into_iter());
+    }
+
+    let jump_labels = &mut ctx.editor.mode.normal_state.jump_labels;
+
+    let mut label = [0; 2];
+    let mut label_len = 0;
+    let position = loop {
+        match keys.next(&ctx.editor.buffered_keys) {
+            Key::None => return false,
+            Key::Char(c) if c.is_ascii() && label_len < label.len() => {
+                label[label_len] = c as _;
+                label_len += 1;
+            }
+            _ => {
+                jump_labels.clear();
+                return true;
+            }
+        }
+        match jump_labels.find(&label[..label_len]) {
+            JumpLabelMatch::None => {
+                jump_labels.clear();
+                return true;
+            }
+            JumpLabelMatch::Partial => (),
+            JumpLabelMatch::Found(position) => {
+                jump_labels.clear();
+                break position;
+            }
+        }
+    };
+
+    NavigationHistory::save_snapshot(ctx.clients.get_mut(client_handle), &ctx.editor.buffer_views);
+    let movement_kind = ctx.editor.mode.normal_state.movement_kind;
+    let mut cursors = ctx
+        .editor
+        .buffer_views
+        .get_mut(buffer_view_handle)
+        .cursors
+        .mut_guard();
+    if add_cursor {
+        cursors.add(Cursor {
+            anchor: position,
+            position,
+        });
+    } else {
+        let anchor = match movement_kind {
+            CursorMovementKind::PositionAndAnchor => position,
+            CursorMovementKind::PositionOnly => cursors.main_cursor().anchor,
+        };
+        cursors.clear();
+        cursors.add(Cursor { anchor, position });
+    }
+    true
+}
+
+fn toggle_comments(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle) {
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+    let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+    let line_comment = syntax.line_comment();
+    let block_comment = syntax.block_comment();
+    if line_comment.is_empty() && (block_comment.0.is_empty() || block_comment.1.is_empty()) {
+        ctx.editor
+            .status_bar
+            .write(MessageKind::Error)
+            .str("no comment syntax for this buffer");
+        return;
+    }
+
+    buffer_view.toggle_comments(
+        &mut ctx.editor.buffers,
+        &mut ctx.editor.word_database,
+        line_comment,
+        block_comment,
+        &mut ctx.editor.events,
+    );
+    ctx.editor
+        .buffers
+        .get_mut(buffer_view.buffer_handle)
+        .commit_edits();
+}
+
+fn surround_pair_from_char(c: char) -> Option<SurroundPair> {
+    match c {
+        '(' | ')' => Some(SurroundPair::Balanced('(', ')')),
+        '[' | ']' => Some(SurroundPair::Balanced('[', ']')),
+        '{' | '}' => Some(SurroundPair::Balanced('{', '}')),
+        '<' | '>' => Some(SurroundPair::Balanced('<', '>')),
+        '|' | '"' | '\'' | '`' => Some(SurroundPair::Delimiter(c)),
+        't' => Some(SurroundPair::Tag),
+        _ => None,
+    }
+}
+
+fn surround_pair_delimiters(c: char) -> Option<(char, char)> {
+    match surround_pair_from_char(c)? {
+        SurroundPair::Balanced(left, right) => Some((left, right)),
+        SurroundPair::Delimiter(delimiter) => Some((delimiter, delimiter)),
+        SurroundPair::Tag => None,
+    }
+}
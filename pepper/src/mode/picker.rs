@@ -228,6 +228,198 @@ pub mod opened_buffers {
     }
 }
 
+pub mod recover {
+    use super::*;
+
+    use std::path::Path;
+
+    use crate::buffer::{recoverable_swap_file_paths, SwapFile};
+
+    pub fn enter_mode(ctx: &mut EditorContext) {
+        fn on_client_keys(
+            ctx: &mut EditorContext,
+            client_handle: ClientHandle,
+            _: &mut KeysIterator,
+            poll: ReadLinePoll,
+        ) -> Option<EditorFlow> {
+            match poll {
+                ReadLinePoll::Pending => return Some(EditorFlow::Continue),
+                ReadLinePoll::Submitted => (),
+                ReadLinePoll::Canceled => {
+                    ctx.editor.enter_mode(ModeKind::default());
+                    return Some(EditorFlow::Continue);
+                }
+            }
+
+            let path = match ctx.editor.picker.current_entry(&ctx.editor.word_database) {
+                Some((_, entry)) => entry,
+                _ => {
+                    ctx.editor.enter_mode(ModeKind::default());
+                    return Some(EditorFlow::Continue);
+                }
+            };
+
+            let path = ctx.editor.string_pool.acquire_with(path);
+            match ctx
+                .editor
+                .recover_buffer_view_handle(client_handle, Path::new(&path))
+            {
+                Ok(buffer_view_handle) => {
+                    let client = ctx.clients.get_mut(client_handle);
+                    client
+                        .set_buffer_view_handle(Some(buffer_view_handle), &ctx.editor.buffer_views);
+                }
+                Err(error) => ctx
+                    .editor
+                    .status_bar
+                    .write(MessageKind::Error)
+                    .fmt(format_args!("{}", error)),
+            }
+            ctx.editor.string_pool.release(path);
+
+            ctx.editor.enter_mode(ModeKind::default());
+            Some(EditorFlow::Continue)
+        }
+
+        ctx.editor.read_line.set_prompt("recover:");
+        ctx.editor.picker.clear();
+
+        if !ctx.editor.config.recovery_directory.is_empty() {
+            let recovery_directory = Path::new(&ctx.editor.config.recovery_directory);
+            for swap_file_path in recoverable_swap_file_paths(recovery_directory) {
+                let swap_file = match SwapFile::read(&swap_file_path) {
+                    Ok(swap_file) => swap_file,
+                    Err(_) => continue,
+                };
+                let path = swap_file
+                    .buffer_path
+                    .strip_prefix(&ctx.editor.current_directory)
+                    .unwrap_or(&swap_file.buffer_path);
+                if let Some(path) = path.to_str() {
+                    ctx.editor.picker.add_custom_entry(path);
+                }
+            }
+        }
+
+        ctx.editor.picker.filter(WordIndicesIter::empty(), "");
+        ctx.editor.picker.move_cursor(0);
+
+        if ctx.editor.picker.len() > 0 {
+            ctx.editor.mode.picker_state.on_client_keys = on_client_keys;
+            ctx.editor.enter_mode(ModeKind::Picker);
+        } else {
+            ctx.editor
+                .status_bar
+                .write(MessageKind::Error)
+                .str("no buffer to recover");
+        }
+    }
+}
+
+pub mod registers {
+    use super::*;
+
+    use crate::{
+        editor_utils::{RegisterKey, RegisterKind},
+        mode::normal,
+    };
+
+    pub fn enter_mode(ctx: &mut EditorContext) {
+        fn on_client_keys(
+            ctx: &mut EditorContext,
+            client_handle: ClientHandle,
+            _: &mut KeysIterator,
+            poll: ReadLinePoll,
+        ) -> Option<EditorFlow> {
+            match poll {
+                ReadLinePoll::Pending => return Some(EditorFlow::Continue),
+                ReadLinePoll::Submitted => (),
+                ReadLinePoll::Canceled => {
+                    ctx.editor.enter_mode(ModeKind::default());
+                    return Some(EditorFlow::Continue);
+                }
+            }
+
+            let key = match ctx.editor.picker.current_entry(&ctx.editor.word_database) {
+                Some((_, entry)) => entry.chars().next().and_then(RegisterKey::from_char),
+                None => None,
+            };
+            ctx.editor.enter_mode(ModeKind::default());
+            let key = match key {
+                Some(key) => key,
+                None => return Some(EditorFlow::Continue),
+            };
+
+            match ctx.editor.registers.kind(key) {
+                RegisterKind::Text => {
+                    let client = ctx.clients.get(client_handle);
+                    if let Some(handle) = client.buffer_view_handle() {
+                        normal::paste_register(ctx, handle, key);
+                    }
+                    Some(EditorFlow::Continue)
+                }
+                RegisterKind::Macro => {
+                    let register = ctx.editor.registers.get(key);
+                    match ctx.editor.buffered_keys.parse(register) {
+                        Ok(keys) => Some(Editor::execute_keys(ctx, client_handle, keys)),
+                        Err(error) => {
+                            ctx.editor
+                                .status_bar
+                                .write(MessageKind::Error)
+                                .fmt(format_args!("{}", error));
+                            Some(EditorFlow::Continue)
+                        }
+                    }
+                }
+                RegisterKind::Marker => {
+                    normal::go_to_marker(ctx, client_handle, key);
+                    Some(EditorFlow::Continue)
+                }
+            }
+        }
+
+        ctx.editor.read_line.set_prompt("register:");
+        ctx.editor.picker.clear();
+
+        let mut entry = ctx.editor.string_pool.acquire();
+        for (key, register) in ctx.editor.registers.iter() {
+            if register.is_empty() {
+                continue;
+            }
+
+            entry.clear();
+            entry.push(key.as_u8() as _);
+            entry.push_str(match ctx.editor.registers.kind(key) {
+                RegisterKind::Text => " text: ",
+                RegisterKind::Macro => " macro: ",
+                RegisterKind::Marker => " marker: ",
+            });
+            for c in register.chars() {
+                match c {
+                    '\n' => entry.push_str("\\n"),
+                    '\t' => entry.push_str("\\t"),
+                    c => entry.push(c),
+                }
+            }
+            ctx.editor.picker.add_custom_entry(&entry);
+        }
+        ctx.editor.string_pool.release(entry);
+
+        ctx.editor.picker.filter(WordIndicesIter::empty(), "");
+        ctx.editor.picker.move_cursor(0);
+
+        if ctx.editor.picker.len() > 0 {
+            ctx.editor.mode.picker_state.on_client_keys = on_client_keys;
+            ctx.editor.enter_mode(ModeKind::Picker);
+        } else {
+            ctx.editor
+                .status_bar
+                .write(MessageKind::Error)
+                .str("all registers are empty");
+        }
+    }
+}
+
 pub mod find_file {
     use super::*;
 
@@ -1,7 +1,7 @@
 use std::process::Stdio;
 
 use crate::{
-    buffer::{BufferCollection, BufferHandle},
+    buffer::{BufferCollection, BufferHandle, SurroundPair},
     buffer_position::{BufferPosition, BufferPositionIndex},
     buffer_view::CursorMovementKind,
     client::ClientHandle,
@@ -22,6 +22,7 @@ pub struct State {
     find_pattern_command: String,
     find_pattern_buffer_handle: Option<BufferHandle>,
     find_pattern_residual_bytes: ResidualStrBytes,
+    surround_change_pair: Option<SurroundPair>,
 }
 
 impl State {
@@ -74,6 +75,7 @@ impl Default for State {
             find_pattern_command: String::new(),
             find_pattern_buffer_handle: None,
             find_pattern_residual_bytes: ResidualStrBytes::default(),
+            surround_change_pair: None,
         }
     }
 }
@@ -119,9 +121,10 @@ pub mod search {
         ) -> Option<EditorFlow> {
             match poll {
                 ReadLinePoll::Pending => {
-                    update_search(ctx, client_handle);
+                    update_search(ctx, client_handle, false);
                 }
                 ReadLinePoll::Submitted => {
+                    update_search(ctx, client_handle, true);
                     if let Some(buffer_view) = ctx
                         .clients
                         .get(client_handle)
@@ -158,13 +161,13 @@ pub mod search {
 
         save_current_position(ctx, client_handle);
         ctx.editor.read_line.set_prompt("search:");
-        update_search(ctx, client_handle);
+        update_search(ctx, client_handle, false);
 
         ctx.editor.mode.read_line_state.on_client_keys = on_client_keys;
         ctx.editor.enter_mode(ModeKind::ReadLine);
     }
 
-    fn update_search(ctx: &mut EditorContext, client_handle: ClientHandle) {
+    fn update_search(ctx: &mut EditorContext, client_handle: ClientHandle, submitted: bool) {
         let handle = match ctx.clients.get_mut(client_handle).buffer_view_handle() {
             Some(handle) => handle,
             None => return,
@@ -172,6 +175,11 @@ pub mod search {
         let buffer_view = ctx.editor.buffer_views.get_mut(handle);
         let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
 
+        // large files are only searched once the pattern is submitted
+        if buffer.is_large_file() != submitted {
+            return;
+        }
+
         let _ = ctx
             .editor
             .aux_pattern
@@ -755,6 +763,76 @@ pub mod process {
     }
 }
 
+pub mod surround {
+    use super::*;
+
+    pub fn enter_tag_mode(ctx: &mut EditorContext, change_pair: Option<SurroundPair>) {
+        fn on_client_keys(
+            ctx: &mut EditorContext,
+            client_handle: ClientHandle,
+            _: &mut KeysIterator,
+            poll: ReadLinePoll,
+        ) -> Option<EditorFlow> {
+            match poll {
+                ReadLinePoll::Pending => return Some(EditorFlow::Continue),
+                ReadLinePoll::Submitted => (),
+                ReadLinePoll::Canceled => {
+                    ctx.editor.enter_mode(ModeKind::default());
+                    return Some(EditorFlow::Continue);
+                }
+            }
+
+            let tag = ctx.editor.read_line.input().trim();
+            let name = tag.split_whitespace().next().unwrap_or("");
+            let handle = ctx.clients.get(client_handle).buffer_view_handle();
+            if let Some(handle) = handle.filter(|_| !name.is_empty()) {
+                let mut open = ctx.editor.string_pool.acquire();
+                let mut close = ctx.editor.string_pool.acquire();
+                open.push('<');
+                open.push_str(tag);
+                open.push('>');
+                close.push_str("</");
+                close.push_str(name);
+                close.push('>');
+
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                match ctx.editor.mode.read_line_state.surround_change_pair {
+                    Some(pair) => buffer_view.change_surround(
+                        &mut ctx.editor.buffers,
+                        &mut ctx.editor.word_database,
+                        pair,
+                        &open,
+                        &close,
+                        &mut ctx.editor.events,
+                    ),
+                    None => buffer_view.add_surround(
+                        &mut ctx.editor.buffers,
+                        &mut ctx.editor.word_database,
+                        &open,
+                        &close,
+                        &mut ctx.editor.events,
+                    ),
+                }
+                ctx.editor
+                    .buffers
+                    .get_mut(buffer_view.buffer_handle)
+                    .commit_edits();
+
+                ctx.editor.string_pool.release(open);
+                ctx.editor.string_pool.release(close);
+            }
+
+            ctx.editor.enter_mode(ModeKind::default());
+            Some(EditorFlow::Continue)
+        }
+
+        ctx.editor.read_line.set_prompt("surround-tag:");
+        ctx.editor.mode.read_line_state.surround_change_pair = change_pair;
+        ctx.editor.mode.read_line_state.on_client_keys = on_client_keys;
+        ctx.editor.enter_mode(ModeKind::ReadLine);
+    }
+}
+
 pub mod find_pattern {
     use super::*;
 
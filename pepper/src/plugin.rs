@@ -10,8 +10,8 @@ use crate::{
     ResourceFile,
 };
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub struct PluginHandle(u32);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PluginHandle(pub(crate) u32);
 
 #[derive(Clone, Copy)]
 pub struct PluginDefinition {
@@ -28,6 +28,9 @@ pub struct Plugin {
     pub on_process_output: fn(PluginHandle, &mut EditorContext, u32, &[u8]),
     pub on_process_exit: fn(PluginHandle, &mut EditorContext, u32),
 
+    pub on_buffer_read: fn(PluginHandle, &mut EditorContext, BufferHandle),
+    pub on_buffer_write: fn(PluginHandle, &mut EditorContext, BufferHandle),
+
     pub on_keys: fn(
         PluginHandle,
         &mut EditorContext,
@@ -47,6 +50,9 @@ impl Default for Plugin {
             on_process_output: |_, _, _, _| (),
             on_process_exit: |_, _, _| (),
 
+            on_buffer_read: |_, _, _| (),
+            on_buffer_write: |_, _, _| (),
+
             on_keys: |_, _, _, _| Some(EditorFlow::Continue),
             on_completion: |_, _, _| false,
         }
@@ -136,4 +142,22 @@ impl PluginCollection {
         let f = ctx.plugins.plugins[plugin_handle.0 as usize].on_process_exit;
         f(plugin_handle, ctx, process_id);
     }
+
+    pub(crate) fn on_buffer_read(
+        ctx: &mut EditorContext,
+        plugin_handle: PluginHandle,
+        buffer_handle: BufferHandle,
+    ) {
+        let f = ctx.plugins.plugins[plugin_handle.0 as usize].on_buffer_read;
+        f(plugin_handle, ctx, buffer_handle);
+    }
+
+    pub(crate) fn on_buffer_write(
+        ctx: &mut EditorContext,
+        plugin_handle: PluginHandle,
+        buffer_handle: BufferHandle,
+    ) {
+        let f = ctx.plugins.plugins[plugin_handle.0 as usize].on_buffer_write;
+        f(plugin_handle, ctx, buffer_handle);
+    }
 }
@@ -0,0 +1,452 @@
+use std::ops::Range;
+
+use crate::{
+    buffer::BufferHandle,
+    buffer_position::{BufferPosition, BufferPositionIndex, BufferRange},
+    cursor::{Cursor, CursorCollectionMutGuard},
+};
+
+enum Node {
+    Text(String),
+    Stop { index: u32, children: Vec<Node> },
+}
+
+fn push_char(nodes: &mut Vec<Node>, c: char) {
+    match nodes.last_mut() {
+        Some(Node::Text(text)) => text.push(c),
+        _ => nodes.push(Node::Text(c.to_string())),
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+    escaped_line_breaks: bool,
+}
+impl<'a> Parser<'a> {
+    fn consume(&mut self, c: char) -> bool {
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn parse_index(&mut self) -> Option<u32> {
+        let len = self.rest.bytes().take_while(u8::is_ascii_digit).count();
+        let index = self.rest[..len].parse().ok()?;
+        self.rest = &self.rest[len..];
+        Some(index)
+    }
+
+    fn parse_name(&mut self) -> Option<&'a str> {
+        let len = match self.rest.bytes().next() {
+            Some(b) if b == b'_' || b.is_ascii_alphabetic() => self
+                .rest
+                .bytes()
+                .take_while(|&b| b == b'_' || b.is_ascii_alphanumeric())
+                .count(),
+            _ => return None,
+        };
+        let (name, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(name)
+    }
+
+    // returns false if it's nested and its closing `}` was not found
+    fn parse(&mut self, nodes: &mut Vec<Node>, is_nested: bool) -> bool {
+        loop {
+            let mut chars = self.rest.chars();
+            match chars.next() {
+                Some('}') if is_nested => {
+                    self.rest = chars.as_str();
+                    return true;
+                }
+                Some('\\') => match chars.next() {
+                    Some(c) if matches!(c, '$' | '}' | '\\') => {
+                        push_char(nodes, c);
+                        self.rest = chars.as_str();
+                    }
+                    Some('n') if self.escaped_line_breaks => {
+                        push_char(nodes, '\n');
+                        self.rest = chars.as_str();
+                    }
+                    _ => {
+                        push_char(nodes, '\\');
+                        self.rest = &self.rest[1..];
+                    }
+                },
+                Some('$') => {
+                    let previous_rest = self.rest;
+                    self.rest = chars.as_str();
+                    if !self.parse_dollar(nodes) {
+                        push_char(nodes, '$');
+                        self.rest = &previous_rest[1..];
+                    }
+                }
+                Some(c) => {
+                    push_char(nodes, c);
+                    self.rest = chars.as_str();
+                }
+                None => return !is_nested,
+            }
+        }
+    }
+
+    fn parse_dollar(&mut self, nodes: &mut Vec<Node>) -> bool {
+        if let Some(index) = self.parse_index() {
+            nodes.push(Node::Stop {
+                index,
+                children: Vec::new(),
+            });
+            return true;
+        }
+        // variables are not supported so they always resolve to nothing
+        if self.parse_name().is_some() {
+            return true;
+        }
+        if !self.consume('{') {
+            return false;
+        }
+
+        if let Some(index) = self.parse_index() {
+            let mut children = Vec::new();
+            if self.consume(':') {
+                if !self.parse(&mut children, true) {
+                    return false;
+                }
+            } else if self.consume('|') {
+                // only the first choice is used
+                let mut choice = String::new();
+                let mut chars = self.rest.chars();
+                loop {
+                    match chars.next() {
+                        Some('\\') => match chars.next() {
+                            Some('n') if self.escaped_line_breaks => choice.push('\n'),
+                            Some(c) => choice.push(c),
+                            None => return false,
+                        },
+                        Some(',') | Some('|') => break,
+                        Some(c) => choice.push(c),
+                        None => return false,
+                    }
+                }
+                let end = match chars.as_str().find("|}") {
+                    Some(end) => end,
+                    None => return false,
+                };
+                self.rest = &chars.as_str()[end + 2..];
+                children.push(Node::Text(choice));
+                nodes.push(Node::Stop { index, children });
+                return true;
+            } else if !self.consume('}') {
+                return false;
+            }
+            nodes.push(Node::Stop { index, children });
+            true
+        } else if self.parse_name().is_some() {
+            if self.consume('}') {
+                true
+            } else if self.consume(':') {
+                let mut children = Vec::new();
+                if !self.parse(&mut children, true) {
+                    return false;
+                }
+                nodes.extend(children);
+                true
+            } else {
+                false
+            }
+        } else {
+            false
+        }
+    }
+}
+
+fn find_placeholder(nodes: &[Node], index: u32, text: &mut String) -> bool {
+    for node in nodes {
+        if let Node::Stop { index: i, children } = node {
+            if *i == index && !children.is_empty() {
+                flatten(children, text);
+                return true;
+            }
+            if find_placeholder(children, index, text) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn flatten(nodes: &[Node], text: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(t) => text.push_str(t),
+            Node::Stop { children, .. } => flatten(children, text),
+        }
+    }
+}
+
+// snippet text with its tab stops resolved to byte ranges of that text.
+// stops are renumbered in the order they are visited, that is, `$1`, `$2`, ..., and finally `$0`
+#[derive(Default)]
+pub struct Snippet {
+    text: String,
+    stops: Vec<(u32, Range<usize>)>,
+    stop_count: u32,
+}
+impl Snippet {
+    // parses the lsp snippet syntax:
+    // `$1`, `${1}`, `${1:placeholder}`, `${1|choice,another|}`, `$VAR`, `${VAR:default}` and `\$`.
+    // with `escaped_line_breaks`, `\n` is also parsed as a line break (for single line sources)
+    pub fn parse(source: &str, escaped_line_breaks: bool) -> Self {
+        fn emit(
+            root: &[Node],
+            nodes: &[Node],
+            text: &mut String,
+            stops: &mut Vec<(u32, Range<usize>)>,
+        ) {
+            for node in nodes {
+                match node {
+                    Node::Text(t) => text.push_str(t),
+                    Node::Stop { index, children } => {
+                        let from = text.len();
+                        if children.is_empty() {
+                            // mirrors the placeholder of another stop with the same index
+                            find_placeholder(root, *index, text);
+                        } else {
+                            emit(root, children, text, stops);
+                        }
+                        stops.push((*index, from..text.len()));
+                    }
+                }
+            }
+        }
+
+        let mut nodes = Vec::new();
+        let mut parser = Parser {
+            rest: source,
+            escaped_line_breaks,
+        };
+        parser.parse(&mut nodes, false);
+
+        let mut this = Self::default();
+        emit(&nodes, &nodes, &mut this.text, &mut this.stops);
+
+        let mut indices: Vec<_> = this.stops.iter().map(|(i, _)| *i).collect();
+        indices.sort_unstable_by_key(|&i| (i == 0, i));
+        indices.dedup();
+        if indices.last() != Some(&0) {
+            indices.push(0);
+            let len = this.text.len();
+            this.stops.push((0, len..len));
+        }
+
+        for (index, _) in &mut this.stops {
+            *index = indices.iter().position(|i| i == index).unwrap_or(0) as _;
+        }
+        this.stop_count = indices.len() as _;
+        this
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn stops(&self) -> &[(u32, Range<usize>)] {
+        &self.stops
+    }
+
+    pub fn stop_count(&self) -> u32 {
+        self.stop_count
+    }
+
+    // writes the snippet text with all lines but the first one prefixed by `indentation`
+    pub fn write_indented(&self, indentation: &str, output: &mut String) {
+        for (i, line) in self.text.split('\n').enumerate() {
+            if i > 0 {
+                output.push('\n');
+                output.push_str(indentation);
+            }
+            output.push_str(line);
+        }
+    }
+
+    // buffer position of a snippet text byte index after it's inserted (with `write_indented`) at `position`
+    pub fn position_at(
+        &self,
+        index: usize,
+        position: BufferPosition,
+        indentation_len: usize,
+    ) -> BufferPosition {
+        let text = &self.text[..index];
+        match text.rfind('\n') {
+            Some(line_start) => BufferPosition::line_col(
+                position.line_index + text.matches('\n').count() as BufferPositionIndex,
+                (indentation_len + index - line_start - 1) as _,
+            ),
+            None => BufferPosition::line_col(
+                position.line_index,
+                position.column_byte_index + index as BufferPositionIndex,
+            ),
+        }
+    }
+}
+
+// tracks the tab stops of an expanded snippet while they are edited.
+// each stop may have several ranges (from mirrors or from expanding at multiple cursors)
+// which are selected together as cursors when jumping to it
+#[derive(Default)]
+pub struct SnippetSession {
+    buffer_handle: Option<BufferHandle>,
+    ranges: Vec<(u32, BufferRange)>,
+    current_stop: u32,
+    stop_count: u32,
+}
+impl SnippetSession {
+    pub fn is_active_in(&self, buffer_handle: BufferHandle) -> bool {
+        self.buffer_handle == Some(buffer_handle)
+    }
+
+    pub fn start(
+        &mut self,
+        buffer_handle: BufferHandle,
+        stop_count: u32,
+        ranges: &[(u32, BufferRange)],
+        cursors: &mut CursorCollectionMutGuard,
+    ) {
+        self.buffer_handle = Some(buffer_handle);
+        self.ranges.clear();
+        self.ranges.extend_from_slice(ranges);
+        self.current_stop = 0;
+        self.stop_count = stop_count;
+        self.select_current_stop(cursors);
+    }
+
+    pub fn end(&mut self) {
+        self.buffer_handle = None;
+        self.ranges.clear();
+    }
+
+    pub fn jump(&mut self, forward: bool, cursors: &mut CursorCollectionMutGuard) {
+        if forward {
+            self.current_stop = (self.current_stop + 1).min(self.stop_count - 1);
+        } else {
+            self.current_stop = self.current_stop.saturating_sub(1);
+        }
+        self.select_current_stop(cursors);
+    }
+
+    // the session ends once reaching the final stop
+    fn select_current_stop(&mut self, cursors: &mut CursorCollectionMutGuard) {
+        cursors.clear();
+        for &(stop, range) in &self.ranges {
+            if stop == self.current_stop {
+                cursors.add(Cursor {
+                    anchor: range.from,
+                    position: range.to,
+                });
+            }
+        }
+        if self.current_stop + 1 >= self.stop_count {
+            self.end();
+        }
+    }
+
+    pub fn on_insert_text(&mut self, buffer_handle: BufferHandle, range: BufferRange) {
+        if !self.is_active_in(buffer_handle) {
+            return;
+        }
+        for (stop, stop_range) in &mut self.ranges {
+            // text typed at the start of a current stop range grows it
+            if *stop != self.current_stop || stop_range.from != range.from {
+                stop_range.from = stop_range.from.insert(range);
+            }
+            stop_range.to = stop_range.to.insert(range);
+        }
+    }
+
+    pub fn on_delete_text(&mut self, buffer_handle: BufferHandle, range: BufferRange) {
+        if !self.is_active_in(buffer_handle) {
+            return;
+        }
+        for (_, stop_range) in &mut self.ranges {
+            stop_range.from = stop_range.from.delete(range);
+            stop_range.to = stop_range.to.delete(range);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> (String, String) {
+        let snippet = Snippet::parse(source, false);
+        let mut stops = String::new();
+        for (i, (stop, range)) in snippet.stops().iter().enumerate() {
+            if i > 0 {
+                stops.push(' ');
+            }
+            stops.push_str(&format!("{}:{}", stop, &snippet.text()[range.clone()]));
+        }
+        (snippet.text().into(), stops)
+    }
+
+    #[test]
+    fn parse_snippets() {
+        assert_eq!(("".into(), "0:".into()), parse(""));
+        assert_eq!(("abc".into(), "0:".into()), parse("abc"));
+        assert_eq!(("fn () {}".into(), "0: 1:".into()), parse("fn $1() {$0}"));
+        assert_eq!(
+            ("fn name() {}".into(), "0:name 1:".into()),
+            parse("fn ${1:name}() {}")
+        );
+        assert_eq!(
+            ("a b a".into(), "0:a 0:b 0:a 1:".into()),
+            parse("${1:a} ${1:b} $1")
+        );
+        assert_eq!(
+            ("let x = x;".into(), "0:x 0:x 1:".into()),
+            parse("let $1 = ${1:x};")
+        );
+        assert_eq!(
+            ("(inner)".into(), "1:inner 0:(inner) 2:".into()),
+            parse("${1:(${2:inner})}")
+        );
+        assert_eq!(("one".into(), "0:one 1:".into()), parse("${1|one,two|}"));
+        assert_eq!(("def".into(), "0:".into()), parse("$VAR${OTHER}${X:def}"));
+        assert_eq!(("$1 }".into(), "0:".into()), parse("\\$1 \\}"));
+        assert_eq!(("${1:a $".into(), "0:".into()), parse("${1:a $"));
+        assert_eq!(("$ {".into(), "0:".into()), parse("$ {"));
+        assert_eq!(("a\\n".into(), "0:a 1:".into()), parse("${1:a}\\n"));
+    }
+
+    #[test]
+    fn parse_escaped_line_breaks() {
+        let snippet = Snippet::parse("f(${1:a\\nb}) {\\n\t$0\\n}", true);
+        assert_eq!("f(a\nb) {\n\t\n}", snippet.text());
+        let snippet = Snippet::parse("printf(\"$1\\\\n\");", true);
+        assert_eq!("printf(\"\\n\");", snippet.text());
+    }
+
+    #[test]
+    fn indented_positions() {
+        let snippet = Snippet::parse("if $1 {\n\t$0\n}", false);
+        let mut text = String::new();
+        snippet.write_indented("  ", &mut text);
+        assert_eq!("if  {\n  \t\n  }", text);
+
+        let position = BufferPosition::line_col(2, 4);
+        let stops = snippet.stops();
+        assert_eq!(
+            BufferPosition::line_col(2, 7),
+            snippet.position_at(stops[0].1.start, position, 2)
+        );
+        assert_eq!(
+            BufferPosition::line_col(3, 3),
+            snippet.position_at(stops[1].1.start, position, 2)
+        );
+    }
+}
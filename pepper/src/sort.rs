@@ -0,0 +1,138 @@
+use std::{cmp::Ordering, collections::HashSet};
+
+pub static SORT_OPTION_NAMES: &[&str] = &["reverse", "numeric", "ignore-case", "unique"];
+
+#[derive(Default, Clone, Copy)]
+pub struct SortOptions {
+    pub reverse: bool,
+    pub numeric: bool,
+    pub ignore_case: bool,
+    pub unique: bool,
+}
+impl SortOptions {
+    pub fn parse_option(&mut self, option: &str) -> bool {
+        match option {
+            "reverse" => self.reverse = true,
+            "numeric" => self.numeric = true,
+            "ignore-case" => self.ignore_case = true,
+            "unique" => self.unique = true,
+            _ => return false,
+        }
+        true
+    }
+}
+
+pub fn sort<T>(texts: &mut Vec<T>, options: SortOptions)
+where
+    T: AsRef<str>,
+{
+    let compare = |a: &str, b: &str| {
+        if options.numeric {
+            match (numeric_key(a), numeric_key(b)) {
+                (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                (a, b) => a.is_some().cmp(&b.is_some()),
+            }
+        } else if options.ignore_case {
+            a.chars()
+                .flat_map(char::to_lowercase)
+                .cmp(b.chars().flat_map(char::to_lowercase))
+        } else {
+            a.cmp(b)
+        }
+    };
+
+    if options.reverse {
+        texts.sort_by(|a, b| compare(b.as_ref(), a.as_ref()));
+    } else {
+        texts.sort_by(|a, b| compare(a.as_ref(), b.as_ref()));
+    }
+    if options.unique {
+        texts.dedup_by(|a, b| compare(a.as_ref(), b.as_ref()) == Ordering::Equal);
+    }
+}
+
+// removes repeated texts while keeping the first occurrence of each one
+pub fn unique(texts: &mut Vec<&str>) {
+    let mut seen = HashSet::new();
+    texts.retain(|&text| seen.insert(text));
+}
+
+// the first number in text (like `-12` or `3.5`), if any
+fn numeric_key(text: &str) -> Option<f64> {
+    let bytes = text.as_bytes();
+    let mut from = bytes.iter().position(u8::is_ascii_digit)?;
+    if from > 0 && bytes[from - 1] == b'-' {
+        from -= 1;
+    }
+
+    let mut to = from + 1;
+    while to < bytes.len() && bytes[to].is_ascii_digit() {
+        to += 1;
+    }
+    if to + 1 < bytes.len() && bytes[to] == b'.' && bytes[to + 1].is_ascii_digit() {
+        to += 1;
+        while to < bytes.len() && bytes[to].is_ascii_digit() {
+            to += 1;
+        }
+    }
+
+    text[from..to].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(texts: &[&'static str], options: &[&str]) -> Vec<&'static str> {
+        let mut sort_options = SortOptions::default();
+        for option in options {
+            assert!(sort_options.parse_option(option));
+        }
+        let mut texts = texts.to_vec();
+        sort(&mut texts, sort_options);
+        texts
+    }
+
+    #[test]
+    fn numeric_keys() {
+        assert_eq!(None, numeric_key(""));
+        assert_eq!(None, numeric_key("abc"));
+        assert_eq!(Some(12.0), numeric_key("abc12def3"));
+        assert_eq!(Some(-4.0), numeric_key("x = -4;"));
+        assert_eq!(Some(3.25), numeric_key("3.25."));
+        assert_eq!(Some(3.0), numeric_key("3."));
+    }
+
+    #[test]
+    fn sort_texts() {
+        let texts = &["b", "C", "a", "b", "A"];
+        assert_eq!(vec!["A", "C", "a", "b", "b"], sorted(texts, &[]));
+        assert_eq!(vec!["b", "b", "a", "C", "A"], sorted(texts, &["reverse"]));
+        assert_eq!(
+            vec!["a", "A", "b", "b", "C"],
+            sorted(texts, &["ignore-case"])
+        );
+        assert_eq!(vec!["A", "C", "a", "b"], sorted(texts, &["unique"]));
+        assert_eq!(
+            vec!["a", "b", "C"],
+            sorted(texts, &["ignore-case", "unique"])
+        );
+
+        let texts = &["item 10", "item 9", "none", "item -1", "item 9.5"];
+        assert_eq!(
+            vec!["none", "item -1", "item 9", "item 9.5", "item 10"],
+            sorted(texts, &["numeric"])
+        );
+        assert_eq!(
+            vec!["item 10", "item 9.5", "item 9", "item -1", "none"],
+            sorted(texts, &["numeric", "reverse"])
+        );
+    }
+
+    #[test]
+    fn unique_texts() {
+        let mut texts = vec!["b", "a", "b", "c", "a"];
+        unique(&mut texts);
+        assert_eq!(vec!["b", "a", "c"], texts);
+    }
+}
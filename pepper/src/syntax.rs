@@ -74,10 +74,18 @@ impl Default for LineParseState {
     }
 }
 
+const DEFAULT_PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')];
+
 pub struct Syntax {
     glob_hash: u64,
     glob: Glob,
     rules: [Pattern; 7],
+    line_comment: String,
+    block_comment: (String, String),
+    indent_pattern: Pattern,
+    dedent_pattern: Pattern,
+    pairs: Vec<(char, char)>,
+    snippets: Vec<(String, String)>,
 }
 
 impl Syntax {
@@ -96,6 +104,12 @@ impl Syntax {
                 Pattern::new(),
                 text_pattern,
             ],
+            line_comment: String::new(),
+            block_comment: (String::new(), String::new()),
+            indent_pattern: Pattern::new(),
+            dedent_pattern: Pattern::new(),
+            pairs: DEFAULT_PAIRS.to_vec(),
+            snippets: Vec::new(),
         }
     }
 
@@ -103,6 +117,14 @@ impl Syntax {
         for r in &mut self.rules {
             r.clear();
         }
+        self.line_comment.clear();
+        self.block_comment.0.clear();
+        self.block_comment.1.clear();
+        self.indent_pattern.clear();
+        self.dedent_pattern.clear();
+        self.pairs.clear();
+        self.pairs.extend_from_slice(DEFAULT_PAIRS);
+        self.snippets.clear();
     }
 
     fn set_glob(&mut self, glob: &str, glob_hash: u64) -> Result<(), InvalidGlobError> {
@@ -114,6 +136,78 @@ impl Syntax {
         self.rules[kind as usize].compile(pattern)
     }
 
+    pub fn line_comment(&self) -> &str {
+        &self.line_comment
+    }
+
+    pub fn set_line_comment(&mut self, prefix: &str) {
+        self.line_comment.clear();
+        self.line_comment.push_str(prefix);
+    }
+
+    pub fn block_comment(&self) -> (&str, &str) {
+        (&self.block_comment.0, &self.block_comment.1)
+    }
+
+    pub fn set_block_comment(&mut self, open: &str, close: &str) {
+        self.block_comment.0.clear();
+        self.block_comment.0.push_str(open);
+        self.block_comment.1.clear();
+        self.block_comment.1.push_str(close);
+    }
+
+    pub fn set_indent_pattern(&mut self, pattern: &str) -> Result<(), PatternError> {
+        self.indent_pattern.compile(pattern)
+    }
+
+    pub fn set_dedent_pattern(&mut self, pattern: &str) -> Result<(), PatternError> {
+        self.dedent_pattern.compile(pattern)
+    }
+
+    pub fn pairs(&self) -> &[(char, char)] {
+        &self.pairs
+    }
+
+    // `pairs` is a sequence of open and close chars (like `()[]""`)
+    pub fn set_pairs(&mut self, pairs: &str) {
+        self.pairs.clear();
+        let mut chars = pairs.chars();
+        while let (Some(open), Some(close)) = (chars.next(), chars.next()) {
+            self.pairs.push((open, close));
+        }
+    }
+
+    pub fn snippet(&self, name: &str) -> Option<&str> {
+        self.snippets
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, body)| &body[..])
+    }
+
+    pub fn set_snippet(&mut self, name: &str, body: &str) {
+        match self.snippets.iter_mut().find(|(n, _)| n == name) {
+            Some((_, b)) => {
+                b.clear();
+                b.push_str(body);
+            }
+            None => self.snippets.push((name.into(), body.into())),
+        }
+    }
+
+    // whether a line break after `text` should increase indentation (like after a `{`)
+    pub fn should_indent_after(&self, text: &str) -> bool {
+        let text = text.trim_end();
+        self.indent_pattern
+            .match_indices(text, None)
+            .any(|range| range.end == text.len())
+    }
+
+    // whether a line whose content is `text` should have one less indentation level (like `}`)
+    pub fn should_dedent(&self, text: &str) -> bool {
+        let text = text.trim();
+        !text.is_empty() && self.dedent_pattern.matches(text, 0) == MatchResult::Ok(text.len())
+    }
+
     fn parse_line(
         &self,
         line: &str,
@@ -302,6 +396,7 @@ impl HighlightedBuffer {
 
     pub fn clear(&mut self) {
         self.highlighted_len = 1;
+        self.lines[0].tokens.clear();
         self.dirty_line_indexes.clear();
     }
 
@@ -731,4 +826,29 @@ mod tests {
             assert_eq!(None, tokens.next());
         }
     }
+
+    #[test]
+    fn indentation_patterns() {
+        let mut syntax = Syntax::new();
+        assert!(!syntax.should_indent_after("fn main() {"));
+        assert!(!syntax.should_dedent("}"));
+
+        syntax.set_indent_pattern("%{|%(|then").unwrap();
+        syntax.set_dedent_pattern("%}|%)|elseif|else|end").unwrap();
+
+        assert!(syntax.should_indent_after("fn main() {"));
+        assert!(syntax.should_indent_after("    call(  "));
+        assert!(syntax.should_indent_after("if a then"));
+        assert!(!syntax.should_indent_after("fn main() {}"));
+        assert!(!syntax.should_indent_after("then a"));
+        assert!(!syntax.should_indent_after(""));
+
+        assert!(syntax.should_dedent("    }"));
+        assert!(syntax.should_dedent(")"));
+        assert!(syntax.should_dedent("else"));
+        assert!(syntax.should_dedent("elseif"));
+        assert!(!syntax.should_dedent("});"));
+        assert!(!syntax.should_dedent("elsewhere"));
+        assert!(!syntax.should_dedent("  "));
+    }
 }
@@ -41,6 +41,7 @@ theme_colors! {
     background,
     active_line_background,
     highlight,
+    jump_label,
     normal_cursor,
     select_cursor,
     insert_cursor,
@@ -69,6 +70,7 @@ pub fn gruvbox_theme() -> Theme {
         background: Color::from_u32(0x1d2021),
         active_line_background: Color::from_u32(0x282828),
         highlight: Color::from_u32(0xfabd2f),
+        jump_label: Color::from_u32(0xb16286),
         normal_cursor: Color::from_u32(0xcc241d),
         insert_cursor: Color::from_u32(0xfabd2f),
         select_cursor: Color::from_u32(0x458588),
@@ -6,6 +6,7 @@ use crate::{
     buffer_view::{BufferViewHandle, CursorMovementKind},
     editor::Editor,
     editor_utils::MessageKind,
+    jump_labels::JumpLabel,
     mode::ModeKind,
     syntax::{Token, TokenKind},
     theme::Color,
@@ -199,20 +200,20 @@ fn draw_buffer_view(
 
     let mut scroll_offset = BufferPosition::zero();
     let mut scroll_padding_top = ctx.scroll as usize;
-    for (line_index, display_len) in buffer_content.line_display_lens().iter().enumerate() {
+    for (line_index, line) in buffer_content.lines().iter().enumerate() {
         scroll_offset.line_index = line_index as _;
 
         if scroll_padding_top == 0 {
             break;
         }
 
-        let line_height = 1 + display_len.total_len(tab_size) / draw_width;
+        let line_height = 1 + line.display_len().total_len(tab_size) / draw_width;
         if line_height <= scroll_padding_top {
             scroll_padding_top -= line_height;
             continue;
         }
 
-        let line = buffer_content.lines()[line_index].as_str();
+        let line = line.as_str();
         let target_display_len = (scroll_padding_top * draw_width) as _;
         for d in CharDisplayDistances::new(line, tab_size) {
             if d.distance >= target_display_len {
@@ -248,6 +249,14 @@ fn draw_buffer_view(
         }
     }
 
+    let jump_labels = &ctx.editor.mode.normal_state.jump_labels;
+    let jump_label_positions = if jump_labels.buffer_view_handle() == Some(buffer_view_handle) {
+        jump_labels.positions()
+    } else {
+        &[]
+    };
+    let mut current_jump_label_index = 0;
+
     let mut current_lint_index = lints.len();
     let mut current_lint_range = BufferRange::zero();
     for (i, lint) in lints.iter().enumerate() {
@@ -309,6 +318,7 @@ fn draw_buffer_view(
             Selection(TokenKind),
             Highlight,
             Cursor,
+            JumpLabel,
         }
 
         if lines_drawn_count == draw_height {
@@ -322,6 +332,8 @@ fn draw_buffer_view(
         let mut x = 0;
         let mut last_line_token = Token::default();
         let mut line_tokens = highlighted_buffer.line_tokens(line_index).iter();
+        let mut jump_label = JumpLabel::default();
+        let mut jump_label_drawn_len = 0;
 
         let background_color = if line_index == active_line_index as _ {
             ctx.editor.theme.active_line_background
@@ -388,6 +400,29 @@ fn draw_buffer_view(
             let inside_lint_range =
                 current_lint_range.from <= char_position && char_position < current_lint_range.to;
 
+            while current_jump_label_index < jump_label_positions.len()
+                && jump_label_positions[current_jump_label_index] < char_position
+            {
+                current_jump_label_index += 1;
+            }
+            if current_jump_label_index < jump_label_positions.len()
+                && jump_label_positions[current_jump_label_index] == char_position
+            {
+                jump_label = jump_labels.label(current_jump_label_index);
+                jump_label_drawn_len = 0;
+            }
+            // labels are drawn over the text that follows them until a tab
+            let jump_label_byte = match jump_label.as_bytes().get(jump_label_drawn_len) {
+                Some(&b) if c != '\t' => {
+                    jump_label_drawn_len += 1;
+                    Some(b)
+                }
+                _ => {
+                    jump_label_drawn_len = jump_label.as_bytes().len();
+                    None
+                }
+            };
+
             if inside_lint_range != was_inside_lint_range {
                 was_inside_lint_range = inside_lint_range;
                 if inside_lint_range {
@@ -397,7 +432,13 @@ fn draw_buffer_view(
                 }
             }
 
-            if char_position == current_cursor_position {
+            if jump_label_byte.is_some() {
+                if draw_state != DrawState::JumpLabel {
+                    draw_state = DrawState::JumpLabel;
+                    set_background_color(buf, ctx.editor.theme.jump_label);
+                    set_foreground_color(buf, ctx.editor.theme.background);
+                }
+            } else if char_position == current_cursor_position {
                 if draw_state != DrawState::Cursor {
                     draw_state = DrawState::Cursor;
                     set_background_color(buf, cursor_color);
@@ -425,6 +466,10 @@ fn draw_buffer_view(
             let previous_buf_len = buf.len();
 
             match c {
+                _ if jump_label_byte.is_some() => {
+                    x += 1;
+                    buf.extend(jump_label_byte);
+                }
                 '\n' => {
                     x += 1;
                     buf.push(b' ');
@@ -560,6 +605,8 @@ fn draw_statusbar(
 ) {
     let view_name;
     let needs_save;
+    let large_file;
+    let read_only;
     let main_cursor_position;
     let search_ranges;
 
@@ -570,12 +617,16 @@ fn draw_statusbar(
 
             view_name = buffer.path.to_str().unwrap_or("");
             needs_save = buffer.needs_save();
+            large_file = buffer.is_large_file();
+            read_only = buffer.properties.read_only;
             main_cursor_position = buffer_view.cursors.main_cursor().position;
             search_ranges = buffer.search_ranges();
         }
         None => {
             view_name = "";
             needs_save = false;
+            large_file = false;
+            read_only = false;
             main_cursor_position = BufferPosition::zero();
             search_ranges = &[];
         }
@@ -754,6 +805,12 @@ fn draw_statusbar(
             let column_number = main_cursor_position.column_byte_index + 1;
             let _ = write!(buf, ":{},{}", line_number, column_number);
         }
+        if large_file {
+            buf.extend_from_slice(b" [large]");
+        }
+        if read_only {
+            buf.extend_from_slice(b" [read-only]");
+        }
         buf.push(b' ');
 
         let status = match std::str::from_utf8(&buf[status_start_index..]) {
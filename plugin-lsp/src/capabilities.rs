@@ -120,7 +120,7 @@ pub fn client_capabilities(json: &mut Json) -> JsonValue {
 
             {
                 let mut completion_item = JsonObject::default();
-                completion_item.set("snippetSupport".into(), false.into(), json);
+                completion_item.set("snippetSupport".into(), true.into(), json);
                 completion_item.set("commitCharactersSupport".into(), false.into(), json);
 
                 let mut documentation_formats = JsonArray::default();
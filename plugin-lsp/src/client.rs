@@ -1112,7 +1112,9 @@ impl Client {
                     util::send_pending_did_change(self, editor, platform);
                     util::send_did_close(self, editor, platform, handle);
                 }
-                EditorEvent::FixCursors { .. } => (),
+                EditorEvent::BufferProviderWrite { .. }
+                | EditorEvent::BufferEditRejected { .. }
+                | EditorEvent::FixCursors { .. } => (),
             }
         }
     }
@@ -633,8 +633,9 @@ pub(crate) fn on_response(
                         (location.range.start.line as usize).saturating_sub(surrounding_len);
                     let end = location.range.end.line as usize + surrounding_len;
 
-                    for line in context_buffer.lines()[start..=end]
-                        .iter()
+                    for line in context_buffer
+                        .lines()
+                        .range(start..=end)
                         .skip_while(|l| l.as_str().is_empty())
                     {
                         text.push_str(line.as_str());
@@ -957,11 +958,22 @@ pub(crate) fn on_response(
             };
 
             ctx.editor.picker.clear();
+            ctx.editor.mode.insert_state.clear_completion_snippets();
             for completion in completions.elements(&client.json) {
                 if let Ok(completion) = DocumentCompletionItem::from_json(completion, &client.json)
                 {
                     let text = completion.text.as_str(&client.json);
-                    ctx.editor.picker.add_custom_entry(text);
+                    if completion.is_snippet {
+                        // the snippet is expanded once its name is picked
+                        let name = completion.name.as_str(&client.json);
+                        ctx.editor.picker.add_custom_entry(name);
+                        ctx.editor
+                            .mode
+                            .insert_state
+                            .add_completion_snippet(name, text);
+                    } else {
+                        ctx.editor.picker.add_custom_entry(text);
+                    }
                 }
             }
 
@@ -624,10 +624,11 @@ impl WorkspaceEdit {
                     TextEdit::apply_edits(editor, buffer_handle, temp_edits, edit.edits, json);
 
                     if is_temp {
-                        let _ = editor
-                            .buffers
-                            .get_mut(buffer_handle)
-                            .write_to_file(None, &mut editor.events);
+                        let _ = editor.buffers.get_mut(buffer_handle).write_to_file(
+                            None,
+                            editor.config.backup_on_save,
+                            &mut editor.events,
+                        );
 
                         editor
                             .buffers
@@ -823,7 +824,9 @@ impl<'json> FromJson<'json> for DocumentSymbolInformation {
 
 #[derive(Default)]
 pub struct DocumentCompletionItem {
+    pub name: JsonString,
     pub text: JsonString,
+    pub is_snippet: bool,
 }
 impl<'json> FromJson<'json> for DocumentCompletionItem {
     fn from_json(value: JsonValue, json: &'json Json) -> Result<Self, JsonConvertError> {
@@ -832,16 +835,30 @@ impl<'json> FromJson<'json> for DocumentCompletionItem {
             _ => return Err(JsonConvertError),
         };
         let mut this = Self::default();
+        let mut filter_text = None;
+        let mut insert_text = None;
+        let mut edit_text = None;
         for (key, value) in value.members(json) {
             match key {
-                "label" => this.text = JsonString::from_json(value, json)?,
-                "insertText" => {
-                    this.text = JsonString::from_json(value, json)?;
-                    break;
+                "label" => this.name = JsonString::from_json(value, json)?,
+                "filterText" => filter_text = Some(JsonString::from_json(value, json)?),
+                "insertText" => insert_text = Some(JsonString::from_json(value, json)?),
+                "insertTextFormat" => this.is_snippet = matches!(value, JsonValue::Integer(2)),
+                // both `TextEdit` and `InsertReplaceEdit` have a `newText`
+                "textEdit" => {
+                    edit_text = Some(JsonString::from_json(value.get("newText", json), json)?)
                 }
                 _ => (),
             }
         }
+        // `textEdit` takes precedence over `insertText`
+        this.text = match edit_text.or(insert_text) {
+            Some(text) => text,
+            None => this.name.clone(),
+        };
+        if let Some(filter_text) = filter_text {
+            this.name = filter_text;
+        }
         Ok(this)
     }
 }